@@ -0,0 +1,233 @@
+// Environment light from an equirectangular image, importance sampled
+// following its luminance
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use image::codecs::hdr::HdrDecoder;
+
+use crate::lights::{Light, LightSample};
+use crate::pdf::distribution::Distribution2D;
+use crate::tools::random_double;
+use crate::vec3::{Color, Point3, Vec3};
+
+use super::Background;
+
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    // linear radiance, top row first
+    pixels: Vec<Color>,
+    // around the y axis, in radians
+    rotation: f64,
+    intensity: f64,
+    distribution: Distribution2D,
+    // over the sphere, without the intensity
+    average: Color,
+}
+
+impl EnvironmentMap {
+    // Load a .hdr or .exr image, `rotation` in degrees turns it around the
+    // vertical axis and `intensity` scales its radiance
+    pub fn load(filename: &str, rotation: f64, intensity: f64) -> Result<EnvironmentMap, String> {
+        let err = |e: String| format!("Failed to load {}: {}", filename, e);
+        let extension = Path::new(filename)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase());
+
+        let (width, height, pixels) = match extension.as_deref() {
+            Some("hdr") => load_hdr(filename).map_err(err)?,
+            Some("exr") => load_exr(filename).map_err(err)?,
+            _ => return Err(err("not an .hdr or .exr image".to_string())),
+        };
+
+        Ok(EnvironmentMap::new(
+            width,
+            height,
+            pixels,
+            rotation.to_radians(),
+            intensity,
+        ))
+    }
+
+    pub fn new(
+        width: usize,
+        height: usize,
+        pixels: Vec<Color>,
+        rotation: f64,
+        intensity: f64,
+    ) -> EnvironmentMap {
+        // the rows near the poles cover a smaller solid angle
+        let sin_theta = |idx: usize| (PI * ((idx / width) as f64 + 0.5) / height as f64).sin();
+        let func: Vec<_> = pixels
+            .iter()
+            .enumerate()
+            .map(|(idx, c)| c.luminance() * sin_theta(idx))
+            .collect();
+        let (sum, weights) = pixels
+            .iter()
+            .enumerate()
+            .fold((Color::zero(), 0.0), |(sum, weights), (idx, c)| {
+                (sum + sin_theta(idx) * *c, weights + sin_theta(idx))
+            });
+
+        EnvironmentMap {
+            width,
+            height,
+            pixels,
+            rotation,
+            intensity,
+            distribution: Distribution2D::new(&func, width),
+            average: sum / weights,
+        }
+    }
+
+    // Image coordinates in [0, 1)^2 of a unit direction, (0, 0) being the
+    // top left corner
+    fn direction_to_uv(&self, direction: &Vec3) -> (f64, f64) {
+        let theta = direction.y().clamp(-1.0, 1.0).acos();
+        let phi = (-direction.z()).atan2(direction.x()) + PI - self.rotation;
+
+        (phi.rem_euclid(2.0 * PI) / (2.0 * PI), theta / PI)
+    }
+
+    fn uv_to_direction(&self, u: f64, v: f64) -> Vec3 {
+        let (theta, phi) = (v * PI, u * 2.0 * PI + self.rotation);
+
+        Vec3::new(
+            -theta.sin() * phi.cos(),
+            theta.cos(),
+            theta.sin() * phi.sin(),
+        )
+    }
+
+    // Density of `direction` with respect to the solid angle
+    fn direction_pdf(&self, direction: &Vec3) -> f64 {
+        let (u, v) = self.direction_to_uv(&direction.to_unit_vector());
+        let sin_theta = (v * PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+
+        self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+impl Background for EnvironmentMap {
+    fn value(&self, direction: &Vec3) -> Color {
+        let (u, v) = self.direction_to_uv(&direction.to_unit_vector());
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+
+        self.intensity * self.pixels[j * self.width + i]
+    }
+}
+
+// One of the lights of the scene, infinitely far: directions are sampled
+// toward its bright parts
+impl Light for EnvironmentMap {
+    fn sample(&self, _origin: &Point3, _time: f64) -> Option<LightSample> {
+        let ((u, v), _) = self.distribution.sample(random_double(), random_double());
+        let direction = self.uv_to_direction(u, v);
+        let pdf = self.direction_pdf(&direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            direction,
+            distance: f64::INFINITY,
+            radiance: self.value(&direction),
+            pdf,
+            delta: false,
+        })
+    }
+
+    fn pdf(&self, _origin: &Point3, direction: &Vec3) -> f64 {
+        self.direction_pdf(direction)
+    }
+
+    // Through the disk of the scene, lit from all sides by the average radiance
+    fn power(&self, scene_radius: f64) -> Color {
+        PI * scene_radius * scene_radius * PI * self.intensity * self.average
+    }
+}
+
+fn load_hdr(filename: &str) -> Result<(usize, usize, Vec<Color>), String> {
+    let file = File::open(filename).map_err(|e| e.to_string())?;
+    let decoder = HdrDecoder::new(BufReader::new(file)).map_err(|e| e.to_string())?;
+    let metadata = decoder.metadata();
+    let pixels = decoder
+        .read_image_hdr()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(|p| Color::new(p[0] as f64, p[1] as f64, p[2] as f64))
+        .collect();
+
+    Ok((metadata.width as usize, metadata.height as usize, pixels))
+}
+
+fn load_exr(filename: &str) -> Result<(usize, usize, Vec<Color>), String> {
+    let image = exr::prelude::read_first_rgba_layer_from_file(
+        filename,
+        |resolution, _| {
+            let (width, height) = (resolution.width(), resolution.height());
+            (width, height, vec![Color::zero(); width * height])
+        },
+        |(width, _, pixels), position, (r, g, b, _a): (f32, f32, f32, f32)| {
+            pixels[position.y() * *width + position.x()] = Color::new(r as f64, g as f64, b as f64)
+        },
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(image.layer_data.channel_data.pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{hash_seed, reseed};
+
+    #[test]
+    fn test_sampling_matches_the_map() {
+        // a dim sky with a bright patch
+        let (width, height) = (16, 8);
+        let mut pixels = vec![Color::new(0.1, 0.1, 0.1); width * height];
+        pixels[2 * width + 11] = Color::new(50.0, 40.0, 30.0);
+        let env = EnvironmentMap::new(width, height, pixels, 0.7, 2.0);
+
+        let direction = env.uv_to_direction(0.3, 0.6);
+        let (u, v) = env.direction_to_uv(&direction);
+        assert!((u - 0.3).abs() < 1E-9 && (v - 0.6).abs() < 1E-9);
+
+        // the estimate of the integral of the radiance over the sphere
+        reseed(hash_seed(&[4]));
+        let n = 100_000;
+        let mut estimate = Color::zero();
+        let mut bright = 0;
+        for _ in 0..n {
+            let sample = env.sample(&Point3::zero(), 0.0).unwrap();
+            let value = env.value(&sample.direction);
+            assert_eq!(value, sample.radiance);
+            estimate += value / sample.pdf;
+            if value.x() > 1.0 {
+                bright += 1;
+            }
+        }
+        estimate /= n as f64;
+
+        let exact = (0..width * height).fold(Color::zero(), |sum, idx| {
+            let (i, j) = (idx % width, idx / width);
+            let theta = |j: usize| PI * j as f64 / height as f64;
+            let solid_angle = 2.0 * PI / width as f64 * (theta(j).cos() - theta(j + 1).cos());
+            sum + env.value(&env.uv_to_direction(
+                (i as f64 + 0.5) / width as f64,
+                (j as f64 + 0.5) / height as f64,
+            )) * solid_angle
+        });
+        assert!((estimate - exact).length() < 0.01 * exact.length());
+        // most samples go toward the bright patch
+        assert!(bright > n / 2);
+    }
+}
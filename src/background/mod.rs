@@ -0,0 +1,16 @@
+// Radiance of the rays escaping the scene
+use crate::vec3::{Color, Vec3};
+
+pub mod environment;
+pub mod sky;
+
+pub trait Background: Send + Sync {
+    fn value(&self, direction: &Vec3) -> Color;
+}
+
+// The same colour in every direction
+impl Background for Color {
+    fn value(&self, _direction: &Vec3) -> Color {
+        *self
+    }
+}
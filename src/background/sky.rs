@@ -0,0 +1,257 @@
+// Analytic daylight: the Preetham et al. (1999) sky model and the sun disk
+// it is lit by, reddened by the atmosphere
+use std::f64::consts::PI;
+
+use crate::lights::{Light, LightSample};
+use crate::onb::OrthoNormalBasis;
+use crate::tools::random_double;
+use crate::vec3::{Color, Point3, Vec3};
+
+use super::Background;
+
+// the model gives luminances in kcd/m^2, a clear sky is about 0.5 with it
+const SKY_SCALE: f64 = 0.05;
+// luminance of the sun outside the atmosphere, in kcd/m^2
+const SUN_LUMINANCE: f64 = 2E6;
+// half the apparent diameter of the sun
+const SUN_RADIUS: f64 = 0.27 * PI / 180.0;
+// wavelengths of the red, green and blue channels, in micrometers
+const WAVELENGTHS: [f64; 3] = [0.68, 0.55, 0.44];
+
+// The sun disk, one of the lights of the scene
+#[derive(Debug, Clone, Copy)]
+pub struct Sun {
+    direction: Vec3,
+    cos_radius: f64,
+    radiance: Color,
+}
+
+impl Sun {
+    // Radiance toward the unit `direction`
+    fn value(&self, direction: &Vec3) -> Color {
+        if direction.dot(self.direction) >= self.cos_radius {
+            self.radiance
+        } else {
+            Color::zero()
+        }
+    }
+
+    fn solid_angle(&self) -> f64 {
+        2.0 * PI * (1.0 - self.cos_radius)
+    }
+}
+
+// Infinitely far, directions are sampled uniformly in its cone
+impl Light for Sun {
+    fn sample(&self, _origin: &Point3, _time: f64) -> Option<LightSample> {
+        let cos_theta = 1.0 - random_double() * (1.0 - self.cos_radius);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let phi = 2.0 * PI * random_double();
+
+        let uvw = OrthoNormalBasis::from(self.direction);
+        let direction = uvw.local(&Vec3::new(
+            phi.cos() * sin_theta,
+            phi.sin() * sin_theta,
+            cos_theta,
+        ));
+
+        Some(LightSample {
+            direction: direction.to_unit_vector(),
+            distance: f64::INFINITY,
+            radiance: self.radiance,
+            pdf: 1.0 / self.solid_angle(),
+            delta: false,
+        })
+    }
+
+    fn pdf(&self, _origin: &Point3, direction: &Vec3) -> f64 {
+        if direction.to_unit_vector().dot(self.direction) >= self.cos_radius {
+            1.0 / self.solid_angle()
+        } else {
+            0.0
+        }
+    }
+
+    fn power(&self, scene_radius: f64) -> Color {
+        PI * scene_radius * scene_radius * self.radiance * self.solid_angle()
+    }
+}
+
+// Coefficients of the Perez sky luminance distribution
+struct Perez([f64; 5]);
+
+impl Perez {
+    // Relative value at the zenith angle `theta` and the angle `gamma` from the sun
+    fn value(&self, theta: f64, gamma: f64) -> f64 {
+        let [a, b, c, d, e] = self.0;
+        let cos_gamma = gamma.cos();
+
+        (1.0 + a * (b / theta.cos().max(0.01)).exp())
+            * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+    }
+}
+
+pub struct Sky {
+    sun: Sun,
+    // zenith angle of the sun
+    theta_sun: f64,
+    // luminance and chromaticity: distributions and values at the zenith
+    perez: [Perez; 3],
+    zenith: [f64; 3],
+    intensity: f64,
+}
+
+impl Sky {
+    // The sun `elevation` above the horizon and its `azimuth` from +x toward
+    // +z are in degrees, `turbidity` goes from 2 (very clear) to 10 (hazy)
+    pub fn new(elevation: f64, azimuth: f64, turbidity: f64, intensity: f64) -> Sky {
+        let (elevation, azimuth) = (elevation.to_radians(), azimuth.to_radians());
+        let direction = Vec3::new(
+            elevation.cos() * azimuth.cos(),
+            elevation.sin(),
+            elevation.cos() * azimuth.sin(),
+        );
+        let theta_sun = PI / 2.0 - elevation;
+        let t = turbidity;
+
+        let perez = [
+            Perez([
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ]),
+            Perez([
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ]),
+            Perez([
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ]),
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+        let (s, s2, s3) = (theta_sun, theta_sun * theta_sun, theta_sun.powi(3));
+        let zenith = [
+            (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192,
+            t * t * (0.00166 * s3 - 0.00375 * s2 + 0.00209 * s)
+                + t * (-0.02903 * s3 + 0.06377 * s2 - 0.03202 * s + 0.00394)
+                + (0.11693 * s3 - 0.21196 * s2 + 0.06052 * s + 0.25886),
+            t * t * (0.00275 * s3 - 0.00610 * s2 + 0.00317 * s)
+                + t * (-0.04214 * s3 + 0.08970 * s2 - 0.04153 * s + 0.00516)
+                + (0.15346 * s3 - 0.26756 * s2 + 0.06670 * s + 0.26688),
+        ];
+
+        let sun = Sun {
+            direction,
+            cos_radius: SUN_RADIUS.cos(),
+            radiance: intensity * SKY_SCALE * SUN_LUMINANCE * transmittance(theta_sun, turbidity),
+        };
+
+        Sky {
+            sun,
+            theta_sun,
+            perez,
+            zenith,
+            intensity,
+        }
+    }
+
+    pub fn sun(&self) -> Sun {
+        self.sun
+    }
+
+    // Sky without the sun, the directions below the horizon get the colour of
+    // the horizon
+    fn sky_value(&self, direction: &Vec3) -> Color {
+        let theta = direction.y().clamp(0.0, 1.0).acos();
+        let gamma = direction.dot(self.sun.direction).clamp(-1.0, 1.0).acos();
+
+        let [big_y, x, y] = [0, 1, 2].map(|i| {
+            self.zenith[i] * self.perez[i].value(theta, gamma)
+                / self.perez[i].value(0.0, self.theta_sun)
+        });
+
+        self.intensity * SKY_SCALE * xyy_to_rgb(x, y, big_y)
+    }
+}
+
+impl Background for Sky {
+    fn value(&self, direction: &Vec3) -> Color {
+        let direction = direction.to_unit_vector();
+
+        self.sky_value(&direction) + self.sun.value(&direction)
+    }
+}
+
+// Linear sRGB of a CIE xyY colour
+fn xyy_to_rgb(x: f64, y: f64, big_y: f64) -> Color {
+    let big_x = x / y * big_y;
+    let big_z = (1.0 - x - y) / y * big_y;
+
+    Color::new(
+        3.2406 * big_x - 1.5372 * big_y - 0.4986 * big_z,
+        -0.9689 * big_x + 1.8758 * big_y + 0.0415 * big_z,
+        0.0557 * big_x - 0.2040 * big_y + 1.0570 * big_z,
+    )
+    .clamp(0.0, f64::INFINITY)
+}
+
+// Rayleigh and aerosol transmittance of the atmosphere toward the zenith
+// angle `theta` (appendix of the Preetham paper)
+fn transmittance(theta: f64, turbidity: f64) -> Color {
+    if theta >= PI / 2.0 {
+        return Color::zero();
+    }
+
+    // relative optical mass
+    let m = 1.0 / (theta.cos() + 0.15 * (93.885 - theta.to_degrees()).powf(-1.253));
+    let beta = 0.04608 * turbidity - 0.04586;
+
+    let [r, g, b] = WAVELENGTHS.map(|lambda: f64| {
+        let rayleigh = (-0.008735 * lambda.powf(-4.08) * m).exp();
+        let aerosol = (-beta * lambda.powf(-1.3) * m).exp();
+        rayleigh * aerosol
+    });
+
+    Color::new(r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sky_and_sun() {
+        let noon = Sky::new(60.0, 30.0, 3.0, 1.0);
+
+        // the luminance at the zenith is the one of the model
+        let zenith = noon.value(&Vec3::new(0.0, 1.0, 0.0));
+        assert!((zenith.luminance() - SKY_SCALE * noon.zenith[0]).abs() < 1E-3);
+        // deep blue above, paler at the horizon
+        assert!(zenith.z() > zenith.x());
+        let horizon = noon.value(&Vec3::new(-1.0, 0.05, 0.0));
+        assert!(horizon.z() / horizon.x() < zenith.z() / zenith.x());
+
+        // the sun is in the sky, and its samples toward itself
+        let sun = noon.sun();
+        assert!(noon.value(&sun.direction).luminance() > 1E4);
+        let sample = sun.sample(&Point3::zero(), 0.0).unwrap();
+        assert!(sun.pdf(&Point3::zero(), &sample.direction) > 0.0);
+        assert_eq!(sun.pdf(&Point3::zero(), &zenith), 0.0);
+
+        // redder at sunset
+        let sunset = Sky::new(3.0, 30.0, 3.0, 1.0).sun();
+        let ratio = |c: Color| c.x() / c.z();
+        assert!(ratio(sunset.radiance) > 2.0 * ratio(sun.radiance));
+        assert!(sunset.radiance.luminance() < sun.radiance.luminance());
+    }
+}
@@ -45,6 +45,36 @@ impl Aabb {
         true
     }
 
+    // Same as hit() with the inverse of the ray direction computed once by the caller
+    pub fn hit_inv(&self, origin: &Point3, inv_d: &Vec3, mut tmin: f64, mut tmax: f64) -> bool {
+        for a in 0..3 {
+            let mut t0 = (self.minimum[a] - origin[a]) * inv_d[a];
+            let mut t1 = (self.maximum[a] - origin[a]) * inv_d[a];
+
+            if inv_d[a] < 0.0f64 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            tmin = t0.max(tmin);
+            tmax = t1.min(tmax);
+            if tmax <= tmin {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        0.5 * (self.minimum + self.maximum)
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let d = self.maximum - self.minimum;
+
+        2.0 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+
     pub fn surrounding_box(box0: &Aabb, box1: &Aabb) -> Aabb {
         let small = Point3::new(
             box0.min().x().min(box1.min().x()),
@@ -34,7 +34,6 @@ impl Hittable for XyRect {
 
         let u = (x - self.x0) / (self.x1 - self.x0);
         let v = (y - self.y0) / (self.y1 - self.y0);
-        let t = t;
         let outward_normal = Vec3::new(0.0, 0.0, 1.0);
         let p = r.at(t);
 
@@ -55,7 +54,7 @@ impl Hittable for XyRect {
 
     fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
         let ray = Ray::new(*origin, *v, 0.0);
-        if let Some(hr) = self.hit(&ray, 0.001, std::f64::INFINITY) {
+        if let Some(hr) = self.hit(&ray, 0.001, f64::INFINITY) {
             let area = (self.x1 - self.x0) * (self.y1 - self.y0);
 
             let distance_squared = hr.get_t() * hr.get_t() * v.length_squared();
@@ -104,7 +103,6 @@ impl Hittable for XzRect {
 
         let u = (x - self.x0) / (self.x1 - self.x0);
         let v = (z - self.z0) / (self.z1 - self.z0);
-        let t = t;
         let outward_normal = Vec3::new(0.0, 1.0, 0.0);
         let p = r.at(t);
 
@@ -125,7 +123,7 @@ impl Hittable for XzRect {
 
     fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
         let ray = Ray::new(*origin, *v, 0.0);
-        if let Some(hr) = self.hit(&ray, 0.001, std::f64::INFINITY) {
+        if let Some(hr) = self.hit(&ray, 0.001, f64::INFINITY) {
             let area = (self.x1 - self.x0) * (self.z1 - self.z0);
 
             let distance_squared = hr.get_t() * hr.get_t() * v.length_squared();
@@ -174,7 +172,6 @@ impl Hittable for YzRect {
 
         let u = (y - self.y0) / (self.y1 - self.y0);
         let v = (z - self.z0) / (self.z1 - self.z0);
-        let t = t;
         let outward_normal = Vec3::new(1.0, 0.0, 0.0);
         let p = r.at(t);
 
@@ -189,13 +186,13 @@ impl Hittable for YzRect {
         // dimension a small amount.
         Some(Aabb::new(
             Point3::new(self.k - 0.0001, self.y0, self.z0),
-            Point3::new(self.k + 0.0001, self.y0, self.z1),
+            Point3::new(self.k + 0.0001, self.y1, self.z1),
         ))
     }
 
     fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
         let ray = Ray::new(*origin, *v, 0.0);
-        if let Some(hr) = self.hit(&ray, 0.001, std::f64::INFINITY) {
+        if let Some(hr) = self.hit(&ray, 0.001, f64::INFINITY) {
             let area = (self.y1 - self.y0) * (self.z1 - self.z0);
 
             let distance_squared = hr.get_t() * hr.get_t() * v.length_squared();
@@ -217,3 +214,32 @@ impl Hittable for YzRect {
         random_point - *origin
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian::Lambertian;
+    use crate::vec3::Color;
+
+    #[test]
+    fn test_yz_rect_bounding_box() {
+        let rect = YzRect {
+            y0: -1.0,
+            y1: 2.0,
+            z0: 0.0,
+            z1: 3.0,
+            k: 1.0,
+            material: Arc::new(Lambertian::from(Color::new(0.5, 0.5, 0.5))),
+        };
+
+        // the box spans the whole rectangle, which a ray hits near its top
+        let bbox = rect.bounding_box(0.0, 0.0).unwrap();
+        assert_eq!(bbox.min().y(), -1.0);
+        assert_eq!(bbox.max().y(), 2.0);
+        assert_eq!(bbox.max().z(), 3.0);
+
+        let r = Ray::new(Point3::new(0.0, 1.5, 1.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        let hr = rect.hit(&r, 0.001, f64::INFINITY).unwrap();
+        assert!(hr.get_p().y() <= bbox.max().y());
+    }
+}
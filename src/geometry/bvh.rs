@@ -1,18 +1,93 @@
 use std::cmp::Ordering;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use clap::{ArgEnum, PossibleValue};
+use serde::{Deserialize, Serialize};
 
 use crate::camera::ray::Ray;
 use crate::geometry::aabb::Aabb;
+use crate::geometry::sah_bvh::{INTERSECTION_COST, TRAVERSAL_COST};
+use crate::geometry::traversal::count_visits;
 use crate::hittable::{HitRecord, Hittable};
 use crate::tools::random_usize_range;
 
+// Builder used for the BVHs of a scene
+#[derive(ArgEnum, Serialize, Deserialize, Debug, Default, PartialEq, Clone, Copy)]
+pub enum BvhKind {
+    // random axis, split at the median, one node per object
+    Median,
+    // surface area heuristic, flattened nodes
+    #[default]
+    Sah,
+}
+
+impl FromStr for BvhKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        for variant in Self::value_variants() {
+            if variant.to_possible_value().unwrap().matches(s, false) {
+                return Ok(*variant);
+            }
+        }
+        Err(format!("Invalid variant: {}", s))
+    }
+}
+
+impl Display for BvhKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .fmt(f)
+    }
+}
+
+impl BvhKind {
+    pub fn possible_values() -> impl Iterator<Item = PossibleValue<'static>> {
+        Self::value_variants()
+            .iter()
+            .filter_map(ArgEnum::to_possible_value)
+    }
+}
+
+// Construction statistics, the SAH cost is relative to the root surface area
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct BvhStats {
+    pub node_count: usize,
+    pub leaf_count: usize,
+    pub max_depth: usize,
+    pub max_leaf_size: usize,
+    pub sah_cost: f64,
+}
+
+impl Display for BvhStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} nodes, {} leaves (max {} primitives), depth {}, SAH cost {:.2}",
+            self.node_count, self.leaf_count, self.max_leaf_size, self.max_depth, self.sah_cost
+        )
+    }
+}
+
 pub struct BvhNode {
     bbox: Aabb,
     left: Option<Box<dyn Hittable>>,
     right: Option<Box<dyn Hittable>>,
+    // children are primitives rather than other nodes
+    leaf: bool,
 }
 
 impl Hittable for BvhNode {
     fn hit(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<HitRecord> {
+        if self.leaf {
+            count_visits(1, self.left.is_some() as u64 + self.right.is_some() as u64);
+        } else {
+            count_visits(1, 0);
+        }
+
         if !self.bbox.hit(r, tmin, tmax) {
             return None;
         }
@@ -45,12 +120,7 @@ impl Hittable for BvhNode {
     }
 }
 
-fn get_aabbs(
-    left: &Box<dyn Hittable>,
-    right: &Box<dyn Hittable>,
-    time0: f64,
-    time1: f64,
-) -> (Aabb, Aabb) {
+fn get_aabbs(left: &dyn Hittable, right: &dyn Hittable, time0: f64, time1: f64) -> (Aabb, Aabb) {
     let left_box = left.bounding_box(time0, time1);
     let right_box = right.bounding_box(time0, time1);
 
@@ -67,8 +137,8 @@ fn get_aabbs(
 }
 
 fn compare_box_by_x_axis(
-    left: &Box<dyn Hittable>,
-    right: &Box<dyn Hittable>,
+    left: &dyn Hittable,
+    right: &dyn Hittable,
     time0: f64,
     time1: f64,
 ) -> Ordering {
@@ -83,8 +153,8 @@ fn compare_box_by_x_axis(
 }
 
 fn compare_box_by_y_axis(
-    left: &Box<dyn Hittable>,
-    right: &Box<dyn Hittable>,
+    left: &dyn Hittable,
+    right: &dyn Hittable,
     time0: f64,
     time1: f64,
 ) -> Ordering {
@@ -99,8 +169,8 @@ fn compare_box_by_y_axis(
 }
 
 fn compare_box_by_z_axis(
-    left: &Box<dyn Hittable>,
-    right: &Box<dyn Hittable>,
+    left: &dyn Hittable,
+    right: &dyn Hittable,
     time0: f64,
     time1: f64,
 ) -> Ordering {
@@ -110,12 +180,37 @@ fn compare_box_by_z_axis(
     left_box
         .min()
         .z()
-        .partial_cmp(&right_box.min().y())
+        .partial_cmp(&right_box.min().z())
         .unwrap()
 }
 
 impl BvhNode {
     pub fn new(objects: Vec<Box<dyn Hittable>>, time0: f64, time1: f64) -> BvhNode {
+        let mut stats = BvhStats::default();
+
+        BvhNode::build(objects, time0, time1, 1, &mut stats)
+    }
+
+    pub fn with_stats(
+        objects: Vec<Box<dyn Hittable>>,
+        time0: f64,
+        time1: f64,
+    ) -> (BvhNode, BvhStats) {
+        let mut stats = BvhStats::default();
+        let node = BvhNode::build(objects, time0, time1, 1, &mut stats);
+        stats.sah_cost /= node.bbox.surface_area();
+
+        (node, stats)
+    }
+
+    // `stats.sah_cost` is accumulated with absolute areas
+    fn build(
+        objects: Vec<Box<dyn Hittable>>,
+        time0: f64,
+        time1: f64,
+        depth: usize,
+        stats: &mut BvhStats,
+    ) -> BvhNode {
         let axis = random_usize_range(0, 2);
         let compare = match axis {
             0 => compare_box_by_x_axis,
@@ -124,7 +219,7 @@ impl BvhNode {
         };
 
         let mut objs: Vec<Box<dyn Hittable>> = objects;
-        objs.sort_unstable_by(|a, b| compare(a, b, time0, time1));
+        objs.sort_unstable_by(|a, b| compare(a.as_ref(), b.as_ref(), time0, time1));
 
         let len = objs.len();
 
@@ -138,8 +233,10 @@ impl BvhNode {
                 let left_objs = objs.drain(0..mid).collect();
                 let right_objs = objs;
 
-                let left: Box<dyn Hittable> = Box::new(BvhNode::new(left_objs, time0, time1));
-                let right: Box<dyn Hittable> = Box::new(BvhNode::new(right_objs, time0, time1));
+                let left: Box<dyn Hittable> =
+                    Box::new(BvhNode::build(left_objs, time0, time1, depth + 1, stats));
+                let right: Box<dyn Hittable> =
+                    Box::new(BvhNode::build(right_objs, time0, time1, depth + 1, stats));
 
                 (Some(left), Some(right))
             }
@@ -160,6 +257,21 @@ impl BvhNode {
             None => panic!("Geometries with no bounding boxes are not supported"),
         };
 
-        BvhNode { bbox, left, right }
+        let leaf = len <= 2;
+        stats.node_count += 1;
+        stats.max_depth = stats.max_depth.max(depth);
+        stats.sah_cost += TRAVERSAL_COST * bbox.surface_area();
+        if leaf {
+            stats.leaf_count += 1;
+            stats.max_leaf_size = stats.max_leaf_size.max(len);
+            stats.sah_cost += INTERSECTION_COST * len as f64 * bbox.surface_area();
+        }
+
+        BvhNode {
+            bbox,
+            left,
+            right,
+            leaf,
+        }
     }
 }
@@ -3,9 +3,10 @@ use std::sync::Arc;
 use crate::camera::ray::Ray;
 use crate::geometry::aabb::Aabb;
 use crate::geometry::aarect::{XyRect, XzRect, YzRect};
+use crate::geometry::flip_normals::FlipNormals;
 use crate::hittable::{HitRecord, Hittable};
 use crate::materials::{lambertian::Lambertian, Material};
-use crate::vec3::{Color, Point3};
+use crate::vec3::{Color, Point3, Vec3};
 
 pub struct Cube {
     p_min: Point3,
@@ -19,6 +20,8 @@ impl Cube {
         Cube::new_from_mat(p0, p1, mat)
     }
 
+    // The sides facing toward -x, -y and -z are flipped so that all of them
+    // face outward
     pub fn new_from_mat(p0: Point3, p1: Point3, mat: Arc<dyn Material>) -> Cube {
         let mut cube = Cube {
             p_min: p0,
@@ -34,14 +37,14 @@ impl Cube {
             k: p1.z(),
             material: mat.clone(),
         }));
-        cube.sides.push(Box::new(XyRect {
+        cube.sides.push(Box::new(FlipNormals::new(XyRect {
             x0: p0.x(),
             x1: p1.x(),
             y0: p0.y(),
             y1: p1.y(),
             k: p0.z(),
             material: mat.clone(),
-        }));
+        })));
 
         cube.sides.push(Box::new(XzRect {
             x0: p0.x(),
@@ -51,14 +54,14 @@ impl Cube {
             k: p1.y(),
             material: mat.clone(),
         }));
-        cube.sides.push(Box::new(XzRect {
+        cube.sides.push(Box::new(FlipNormals::new(XzRect {
             x0: p0.x(),
             x1: p1.x(),
             z0: p0.z(),
             z1: p1.z(),
             k: p0.y(),
             material: mat.clone(),
-        }));
+        })));
 
         cube.sides.push(Box::new(YzRect {
             y0: p0.y(),
@@ -68,14 +71,14 @@ impl Cube {
             k: p1.x(),
             material: mat.clone(),
         }));
-        cube.sides.push(Box::new(YzRect {
+        cube.sides.push(Box::new(FlipNormals::new(YzRect {
             y0: p0.y(),
             y1: p1.y(),
             z0: p0.z(),
             z1: p1.z(),
             k: p0.x(),
             material: mat.clone(),
-        }));
+        })));
 
         cube
     }
@@ -89,4 +92,45 @@ impl Hittable for Cube {
     fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<Aabb> {
         Some(Aabb::new(self.p_min, self.p_max))
     }
+
+    // one of the sides, each as likely
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        self.sides.pdf_value(origin, v)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.sides.random(origin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sides_face_outward() {
+        let cube = Cube::new(
+            Point3::new(-1.0, -1.0, -1.0),
+            Point3::new(1.0, 1.0, 1.0),
+            Color::new(0.5, 0.5, 0.5),
+        );
+
+        for axis in 0..3 {
+            for sign in [-1.0, 1.0] {
+                let mut direction = Vec3::zero();
+                direction[axis] = sign;
+
+                // entering from outside and leaving from the centre
+                let outside = Ray::new(-3.0 * direction, direction, 0.0);
+                let hr = cube.hit(&outside, 0.001, f64::INFINITY).unwrap();
+                assert!(hr.is_front());
+                assert_eq!(hr.get_outward_normal(), -direction);
+
+                let inside = Ray::new(Point3::zero(), direction, 0.0);
+                let hr = cube.hit(&inside, 0.001, f64::INFINITY).unwrap();
+                assert!(!hr.is_front());
+                assert_eq!(hr.get_outward_normal(), direction);
+            }
+        }
+    }
 }
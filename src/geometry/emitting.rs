@@ -0,0 +1,40 @@
+use crate::camera::ray::Ray;
+use crate::hittable::HitRecord;
+use crate::hittable::Hittable;
+use crate::vec3::{Point3, Vec3};
+
+use super::aabb::Aabb;
+
+// Emissive primitive which is one of the lights of the scene, its hits carry
+// the index of the light (for the MIS weight of the BSDF samples reaching it)
+pub struct Emitting<H: Hittable> {
+    hittable: H,
+    light: usize,
+}
+
+impl<H: Hittable> Emitting<H> {
+    pub fn new(hittable: H, light: usize) -> Self {
+        Self { hittable, light }
+    }
+}
+
+impl<H: Hittable> Hittable for Emitting<H> {
+    fn hit(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<HitRecord> {
+        let mut hr = self.hittable.hit(r, tmin, tmax)?;
+        hr.set_light(Some(self.light));
+
+        Some(hr)
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb> {
+        self.hittable.bounding_box(time0, time1)
+    }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        self.hittable.pdf_value(origin, v)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.hittable.random(origin)
+    }
+}
@@ -0,0 +1,42 @@
+use crate::camera::ray::Ray;
+use crate::hittable::HitRecord;
+use crate::hittable::Hittable;
+use crate::vec3::{Point3, Vec3};
+
+use super::aabb::Aabb;
+
+// Top level object of a scene, its hits carry its id (for the object ID AOV)
+pub struct Identified<H: Hittable> {
+    hittable: H,
+    object_id: u32,
+}
+
+impl<H: Hittable> Identified<H> {
+    pub fn new(hittable: H, object_id: u32) -> Self {
+        Self {
+            hittable,
+            object_id,
+        }
+    }
+}
+
+impl<H: Hittable> Hittable for Identified<H> {
+    fn hit(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<HitRecord> {
+        let mut hr = self.hittable.hit(r, tmin, tmax)?;
+        hr.set_object_id(self.object_id);
+
+        Some(hr)
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb> {
+        self.hittable.bounding_box(time0, time1)
+    }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        self.hittable.pdf_value(origin, v)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.hittable.random(origin)
+    }
+}
@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use crate::camera::ray::Ray;
+use crate::geometry::aabb::Aabb;
+use crate::geometry::transform::Transform;
+use crate::hittable::{HitRecord, Hittable};
+use crate::materials::Material;
+use crate::matrix::Matrix4;
+use crate::vec3::{Point3, Vec3};
+
+// A placed copy of a shared prototype (a mesh, a sub-BVH...), the prototype is
+// stored once whatever the number of instances. Instances are meant to be
+// gathered in a top-level BVH.
+pub struct Instance {
+    transform: Transform<Arc<dyn Hittable>>,
+    // replaces the materials of the prototype
+    material: Option<Arc<dyn Material>>,
+    // index among the lights of the scene of the first emitter of this copy,
+    // None when none of its primitives emits
+    first_light: Option<usize>,
+    // index among the emitters of this copy of the light carried by the hits
+    // on the prototype, when they are not the same
+    light_table: Option<Arc<[Option<usize>]>>,
+}
+
+impl Instance {
+    pub fn new(
+        prototype: Arc<dyn Hittable>,
+        matrix: Matrix4,
+        material: Option<Arc<dyn Material>>,
+    ) -> Result<Instance, String> {
+        Ok(Instance {
+            transform: Transform::new(prototype, matrix)?,
+            material,
+            first_light: None,
+            light_table: None,
+        })
+    }
+
+    // The emitters of the prototype are numbered from 0 among its own lights
+    pub fn with_lights(
+        mut self,
+        first_light: usize,
+        light_table: Option<Arc<[Option<usize>]>>,
+    ) -> Instance {
+        self.first_light = Some(first_light);
+        self.light_table = light_table;
+        self
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<HitRecord> {
+        let mut hr = self.transform.hit(r, tmin, tmax)?;
+        if let Some(material) = &self.material {
+            hr.material = material.clone();
+        }
+        let light = match (self.first_light, hr.get_light()) {
+            (Some(first), Some(light)) => match &self.light_table {
+                Some(table) => table.get(light).copied().flatten(),
+                None => Some(light),
+            }
+            .map(|light| first + light),
+            _ => None,
+        };
+        hr.set_light(light);
+
+        Some(hr)
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb> {
+        self.transform.bounding_box(time0, time1)
+    }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        self.transform.pdf_value(origin, v)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.transform.random(origin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::sah_bvh::SahBvh;
+    use crate::geometry::sphere::Sphere;
+    use crate::materials::lambertian::Lambertian;
+    use crate::vec3::Color;
+
+    #[test]
+    fn test_instances_share_the_prototype() {
+        let white: Arc<dyn Material> = Arc::new(Lambertian::from(Color::new(0.7, 0.7, 0.7)));
+        let red: Arc<dyn Material> = Arc::new(Lambertian::from(Color::new(0.7, 0.1, 0.1)));
+        let prototype: Arc<dyn Hittable> = Arc::new(Sphere {
+            center: Point3::zero(),
+            radius: 1.0,
+            material: white.clone(),
+        });
+
+        // a row of spheres along x, every other one red
+        let instances: Vec<Box<dyn Hittable>> = (0..100)
+            .map(|i| {
+                let matrix = Matrix4::translation(Vec3::new(3.0 * i as f64, 0.0, 0.0));
+                let material = if i % 2 == 1 { Some(red.clone()) } else { None };
+                let instance: Box<dyn Hittable> =
+                    Box::new(Instance::new(prototype.clone(), matrix, material).unwrap());
+                instance
+            })
+            .collect();
+        assert_eq!(Arc::strong_count(&prototype), 101);
+        let top = SahBvh::new(instances, 0.0, 1.0);
+
+        for i in [0, 7, 42] {
+            let x = 3.0 * i as f64;
+            let r = Ray::new(Point3::new(x, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+            let hr = top.hit(&r, 0.001, f64::INFINITY).unwrap();
+
+            assert!((hr.get_p() - Point3::new(x, 1.0, 0.0)).length() < 1E-9);
+            let expected = if i % 2 == 1 { &red } else { &white };
+            assert!(Arc::ptr_eq(&hr.material, expected));
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use crate::geometry::triangle::Triangle;
+use crate::materials::Material;
+use crate::vec3::{Point3, Vec3};
+
+// Indexed triangle mesh, the vertex buffer is shared by all its triangles.
+// `normals` and `uvs` are either empty or hold one entry per position.
+#[derive(Debug, Default)]
+pub struct Mesh {
+    pub positions: Vec<Point3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<(f64, f64)>,
+    pub indices: Vec<[usize; 3]>,
+}
+
+impl Mesh {
+    pub fn has_normals(&self) -> bool {
+        !self.normals.is_empty()
+    }
+
+    pub fn has_uvs(&self) -> bool {
+        !self.uvs.is_empty()
+    }
+
+    pub fn check(&self) -> Result<(), String> {
+        let len = self.positions.len();
+
+        if self.has_normals() && self.normals.len() != len {
+            return Err(format!(
+                "Mesh has {} normals for {} positions",
+                self.normals.len(),
+                len
+            ));
+        }
+        if self.has_uvs() && self.uvs.len() != len {
+            return Err(format!(
+                "Mesh has {} uvs for {} positions",
+                self.uvs.len(),
+                len
+            ));
+        }
+        if let Some(face) = self.indices.iter().find(|f| f.iter().any(|&i| i >= len)) {
+            return Err(format!(
+                "Mesh face {:?} is out of bounds ({} positions)",
+                face, len
+            ));
+        }
+
+        Ok(())
+    }
+
+    // One triangle per face, all of them referencing this mesh
+    pub fn triangles(mesh: &Arc<Mesh>, material: Arc<dyn Material>) -> Vec<Triangle> {
+        mesh.indices
+            .iter()
+            .map(|&indices| Triangle::new(mesh.clone(), indices, material.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian::Lambertian;
+    use crate::vec3::Color;
+
+    #[test]
+    fn test_check_and_triangles() {
+        // a unit square of two triangles
+        let mut mesh = Mesh {
+            positions: vec![
+                Point3::zero(),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(1.0, 1.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ],
+            indices: vec![[0, 1, 2], [0, 2, 3]],
+            ..Default::default()
+        };
+        assert!(mesh.check().is_ok());
+
+        let material: Arc<dyn Material> = Arc::new(Lambertian::from(Color::new(0.5, 0.5, 0.5)));
+        let triangles = Mesh::triangles(&Arc::new(mesh), material);
+        assert_eq!(triangles.len(), 2);
+        assert_eq!(
+            triangles[1].vertices(),
+            (
+                Point3::zero(),
+                Point3::new(1.0, 1.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0)
+            )
+        );
+
+        mesh = Mesh {
+            positions: vec![Point3::zero(); 3],
+            normals: vec![Vec3::new(0.0, 0.0, 1.0); 2],
+            indices: vec![[0, 1, 2]],
+            ..Default::default()
+        };
+        assert!(mesh.check().is_err());
+        mesh.normals.clear();
+        mesh.uvs = vec![(0.0, 0.0)];
+        assert!(mesh.check().is_err());
+        mesh.uvs.clear();
+        mesh.indices = vec![[0, 1, 3]];
+        assert!(mesh.check().is_err());
+    }
+}
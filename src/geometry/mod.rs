@@ -2,9 +2,17 @@ pub mod aabb;
 pub mod aarect;
 pub mod bvh;
 pub mod cube;
+pub mod emitting;
 pub mod flip_normals;
-pub mod medium;
+pub mod identified;
+pub mod instance;
+pub mod mesh;
 pub mod moving_sphere;
+pub mod obj;
 pub mod rotate;
+pub mod sah_bvh;
 pub mod sphere;
+pub mod transform;
 pub mod translate;
+pub mod traversal;
+pub mod triangle;
@@ -20,6 +20,16 @@ impl MovingSphere {
         self.center0
             + ((time - self.time0) / (self.time1 - self.time0)) * (self.center1 - self.center0)
     }
+
+    // Sphere around the whole motion between time0 and time1, the densities
+    // have no time so the directions are sampled toward it
+    fn swept_sphere(&self) -> Sphere {
+        Sphere {
+            center: 0.5 * (self.center0 + self.center1),
+            radius: self.radius + 0.5 * (self.center1 - self.center0).length(),
+            material: self.material.clone(),
+        }
+    }
 }
 
 impl Hittable for MovingSphere {
@@ -69,4 +79,12 @@ impl Hittable for MovingSphere {
 
         Some(Aabb::surrounding_box(&box0, &box1))
     }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        self.swept_sphere().pdf_value(origin, v)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.swept_sphere().random(origin)
+    }
 }
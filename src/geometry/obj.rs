@@ -0,0 +1,213 @@
+// Wavefront OBJ/MTL importer
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::geometry::{mesh::Mesh, triangle::Triangle};
+use crate::materials::{
+    dielectric::Dielectric, diffuse_light::DiffuseLight, lambertian::Lambertian, metal::Metal,
+    Material,
+};
+use crate::texture::image::ImageTexture;
+use crate::vec3::{Color, Point3, Vec3};
+
+fn to_color(c: &[f32; 3]) -> Color {
+    Color::new(c[0] as f64, c[1] as f64, c[2] as f64)
+}
+
+// Parse a "r g b" MTL parameter tobj does not know about (e.g. Ke)
+fn parse_color(value: &str) -> Option<Color> {
+    let c: Vec<f64> = value
+        .split_whitespace()
+        .map(|s| s.parse::<f64>())
+        .collect::<Result<_, _>>()
+        .ok()?;
+
+    match c.as_slice() {
+        [r, g, b] => Some(Color::new(*r, *g, *b)),
+        [v] => Some(Color::new(*v, *v, *v)),
+        _ => None,
+    }
+}
+
+// Map a MTL entry onto the closest of our materials:
+// - emissive (Ke) entries become DiffuseLight
+// - transparent entries (d < 1 or illum 4, 6, 7) become Dielectric using Ni
+// - reflective entries (illum 3 or 5) become Metal using Ks, fuzz is derived from Ns
+// - anything else is Lambertian using Kd or map_Kd
+fn convert_material(mtl: &tobj::Material, dir: &Path) -> Arc<dyn Material> {
+    if let Some(emit) = mtl.unknown_param.get("Ke").and_then(|ke| parse_color(ke)) {
+        if emit.length_squared() > 0.0 {
+            return Arc::new(DiffuseLight::from(emit));
+        }
+    }
+
+    match mtl.illumination_model {
+        Some(4) | Some(6) | Some(7) => {
+            return Arc::new(Dielectric::new(mtl.optical_density as f64));
+        }
+        _ if mtl.dissolve < 1.0 => {
+            return Arc::new(Dielectric::new(mtl.optical_density as f64));
+        }
+        Some(3) | Some(5) => {
+            let fuzz = (2.0 / (mtl.shininess as f64 + 2.0)).sqrt();
+            return Arc::new(Metal::new(to_color(&mtl.specular), fuzz));
+        }
+        _ => {}
+    }
+
+    if !mtl.diffuse_texture.is_empty() {
+        let texture = dir.join(&mtl.diffuse_texture);
+        return Arc::new(Lambertian {
+            albedo: Box::new(ImageTexture::new(&texture.to_string_lossy())),
+        });
+    }
+
+    Arc::new(Lambertian::from(to_color(&mtl.diffuse)))
+}
+
+// Load every model of an OBJ file as triangles. When `material` is given it
+// overrides the MTL materials.
+pub fn load_obj(
+    filename: &str,
+    material: Option<Arc<dyn Material>>,
+) -> Result<Vec<Triangle>, String> {
+    let (models, materials) = tobj::load_obj(filename, &tobj::GPU_LOAD_OPTIONS)
+        .map_err(|e| format!("Failed to load OBJ file {}: {}", filename, e))?;
+
+    let dir = Path::new(filename)
+        .parent()
+        .unwrap_or_else(|| Path::new(""));
+    let materials: Vec<Arc<dyn Material>> = match (&material, materials) {
+        (None, Ok(materials)) => materials
+            .iter()
+            .map(|mtl| convert_material(mtl, dir))
+            .collect(),
+        (None, Err(e)) => {
+            eprintln!("Failed to load MTL for {}: {}", filename, e);
+            Vec::new()
+        }
+        (Some(_), _) => Vec::new(),
+    };
+    let default: Arc<dyn Material> =
+        material.unwrap_or_else(|| Arc::new(Lambertian::from(Color::new(0.73, 0.73, 0.73))));
+
+    let mut triangles = Vec::new();
+    for model in models {
+        let m = &model.mesh;
+
+        let mesh = Mesh {
+            positions: m
+                .positions
+                .chunks(3)
+                .map(|p| Point3::new(p[0] as f64, p[1] as f64, p[2] as f64))
+                .collect(),
+            normals: m
+                .normals
+                .chunks(3)
+                .map(|n| Vec3::new(n[0] as f64, n[1] as f64, n[2] as f64))
+                .collect(),
+            uvs: m
+                .texcoords
+                .chunks(2)
+                .map(|uv| (uv[0] as f64, uv[1] as f64))
+                .collect(),
+            indices: m
+                .indices
+                .chunks(3)
+                .map(|f| [f[0] as usize, f[1] as usize, f[2] as usize])
+                .collect(),
+        };
+        mesh.check()
+            .map_err(|e| format!("Invalid model {} in {}: {}", model.name, filename, e))?;
+
+        let material = m
+            .material_id
+            .and_then(|id| materials.get(id))
+            .unwrap_or(&default)
+            .clone();
+
+        triangles.append(&mut Mesh::triangles(&Arc::new(mesh), material));
+    }
+
+    Ok(triangles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::ray::Ray;
+    use crate::hittable::Hittable;
+    use crate::materials::ScatterRecord;
+
+    #[test]
+    fn test_mtl_materials_are_mapped() {
+        let triangles = load_obj("data/obj/materials.obj", None).unwrap();
+        assert_eq!(triangles.len(), 5);
+
+        // the triangles are in the order of the file, each at its own depth
+        let scatter = |z: f64| {
+            let r = Ray::new(
+                Point3::new(0.2, 0.2, z + 0.5),
+                Vec3::new(0.0, 0.0, -1.0),
+                0.0,
+            );
+            let hr = triangles
+                .iter()
+                .find_map(|t| t.hit(&r, 0.001, 0.6))
+                .unwrap();
+            (
+                hr.material
+                    .emitted(&r, &hr, hr.get_u(), hr.get_v(), &hr.get_p()),
+                hr.material.scatter(&r, &hr),
+            )
+        };
+
+        // no material, the default grey
+        match scatter(4.0) {
+            (_, Some(ScatterRecord::Diffuse { attenuation, .. })) => {
+                assert_eq!(attenuation, Color::new(0.73, 0.73, 0.73))
+            }
+            _ => panic!("plain is not diffuse"),
+        }
+        // Ke
+        let (emission, _) = scatter(0.0);
+        assert_eq!(emission, Color::new(4.0, 3.0, 2.0));
+        // illum 7, a clear dielectric
+        match scatter(1.0) {
+            (_, Some(ScatterRecord::Specular { attenuation, .. })) => {
+                assert_eq!(attenuation, Color::new(1.0, 1.0, 1.0))
+            }
+            _ => panic!("glass is not specular"),
+        }
+        // illum 3, a metal of the Ks colour
+        match scatter(2.0) {
+            (_, Some(ScatterRecord::Specular { attenuation, .. })) => {
+                assert!((attenuation - Color::new(0.8, 0.6, 0.2)).length() < 1E-6)
+            }
+            _ => panic!("mirror is not specular"),
+        }
+        // Kd
+        match scatter(3.0) {
+            (_, Some(ScatterRecord::Diffuse { attenuation, .. })) => {
+                assert!((attenuation - Color::new(0.2, 0.4, 0.6)).length() < 1E-6)
+            }
+            _ => panic!("matte is not diffuse"),
+        }
+
+        // the material given overrides the MTL ones
+        let white: Arc<dyn Material> = Arc::new(Lambertian::from(Color::new(1.0, 1.0, 1.0)));
+        let triangles = load_obj("data/obj/materials.obj", Some(white)).unwrap();
+        let r = Ray::new(Point3::new(0.2, 0.2, 0.5), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let hr = triangles
+            .iter()
+            .find_map(|t| t.hit(&r, 0.001, 0.6))
+            .unwrap();
+        assert_eq!(
+            hr.material
+                .emitted(&r, &hr, hr.get_u(), hr.get_v(), &hr.get_p()),
+            Color::zero()
+        );
+
+        assert!(load_obj("data/obj/missing.obj", None).is_err());
+    }
+}
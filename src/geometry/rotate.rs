@@ -73,17 +73,21 @@ impl<H: Hittable> Hittable for RotateY<H> {
 
         if let Some(hr) = self.hittable.hit(&rotated_r, tmin, tmax) {
             let mut p = hr.get_p();
-            let mut normal = hr.get_normal();
+            // the outward normal, for the face to be kept
+            let outward_normal = hr.get_outward_normal();
+            let mut normal = outward_normal;
 
             p[0] = self.cos_theta * hr.get_p()[0] + self.sin_theta * hr.get_p()[2];
             p[2] = -self.sin_theta * hr.get_p()[0] + self.cos_theta * hr.get_p()[2];
 
-            normal[0] = self.cos_theta * hr.get_normal()[0] + self.sin_theta * hr.get_normal()[2];
-            normal[2] = -self.sin_theta * hr.get_normal()[0] + self.cos_theta * hr.get_normal()[2];
+            normal[0] = self.cos_theta * outward_normal[0] + self.sin_theta * outward_normal[2];
+            normal[2] = -self.sin_theta * outward_normal[0] + self.cos_theta * outward_normal[2];
 
+            let light = hr.get_light();
             let mut hr_ret =
                 HitRecord::new(p, normal, hr.get_t(), hr.get_u(), hr.get_v(), hr.material);
-            hr_ret.set_front_face(&rotated_r, normal);
+            hr_ret.set_front_face(r, normal);
+            hr_ret.set_light(light);
 
             return Some(hr_ret);
         }
@@ -95,3 +99,39 @@ impl<H: Hittable> Hittable for RotateY<H> {
         self.bbox
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::geometry::sphere::Sphere;
+    use crate::materials::lambertian::Lambertian;
+    use crate::vec3::{Color, Vec3};
+
+    #[test]
+    fn test_faces_are_kept() {
+        let sphere = Sphere {
+            center: Point3::new(2.0, 0.0, 0.0),
+            radius: 1.0,
+            material: Arc::new(Lambertian::from(Color::new(0.5, 0.5, 0.5))),
+        };
+        // the centre goes to -z
+        let rotated = RotateY::new(sphere, 90.0);
+        let center = Point3::new(0.0, 0.0, -2.0);
+
+        for direction in [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.6, 0.0, 0.8)] {
+            let outside = Ray::new(center - 3.0 * direction, direction, 0.0);
+            let hr = rotated.hit(&outside, 0.001, f64::INFINITY).unwrap();
+            assert!(hr.is_front());
+            let outward = (hr.get_p() - center).to_unit_vector();
+            assert!((hr.get_outward_normal() - outward).length() < 1E-9);
+
+            let inside = Ray::new(center, direction, 0.0);
+            let hr = rotated.hit(&inside, 0.001, f64::INFINITY).unwrap();
+            assert!(!hr.is_front());
+            assert!((hr.get_outward_normal() - direction).length() < 1E-9);
+            assert!(hr.get_normal().dot(direction) < 0.0);
+        }
+    }
+}
@@ -0,0 +1,440 @@
+// Bounding Volume Hierarchy built with the Surface Area Heuristic (binned) and
+// flattened into a depth first array of nodes. Only the primitives are boxed,
+// traversal itself does not go through dynamic dispatch.
+use crate::camera::ray::Ray;
+use crate::geometry::aabb::Aabb;
+use crate::geometry::bvh::BvhStats;
+use crate::geometry::traversal::count_visits;
+use crate::hittable::{HitRecord, Hittable};
+use crate::vec3::{Point3, Vec3};
+
+const BINS: usize = 12;
+const MAX_LEAF_SIZE: usize = 4;
+// beyond that depth splits are forced at the median to bound the traversal stack
+const MAX_SAH_DEPTH: usize = 48;
+const STACK_SIZE: usize = 128;
+
+// SAH cost of traversing a node, relative to the cost of intersecting a primitive
+pub const TRAVERSAL_COST: f64 = 0.125;
+pub const INTERSECTION_COST: f64 = 1.0;
+
+#[derive(Clone, Copy)]
+struct LinearNode {
+    bbox: Aabb,
+    // leaf: index of the first primitive, interior: index of the second child
+    // (the first child always follows its parent)
+    offset: usize,
+    // number of primitives, 0 for interior nodes
+    count: usize,
+    // split axis of interior nodes
+    axis: usize,
+}
+
+#[derive(Clone, Copy)]
+struct PrimitiveInfo {
+    index: usize,
+    bbox: Aabb,
+    centroid: Point3,
+}
+
+#[derive(Clone, Copy)]
+struct Bin {
+    count: usize,
+    bbox: Option<Aabb>,
+}
+
+fn union(a: Option<Aabb>, b: &Aabb) -> Option<Aabb> {
+    match a {
+        None => Some(*b),
+        Some(a) => Some(Aabb::surrounding_box(&a, b)),
+    }
+}
+
+pub struct SahBvh {
+    nodes: Vec<LinearNode>,
+    objects: Vec<Box<dyn Hittable>>,
+    stats: BvhStats,
+}
+
+impl SahBvh {
+    pub fn new(objects: Vec<Box<dyn Hittable>>, time0: f64, time1: f64) -> SahBvh {
+        let mut infos: Vec<PrimitiveInfo> = objects
+            .iter()
+            .enumerate()
+            .map(|(index, obj)| {
+                let bbox = match obj.bounding_box(time0, time1) {
+                    Some(bbox) => bbox,
+                    None => panic!("Hittable with no bounding boxes are not supported"),
+                };
+
+                PrimitiveInfo {
+                    index,
+                    bbox,
+                    centroid: bbox.centroid(),
+                }
+            })
+            .collect();
+
+        let mut bvh = SahBvh {
+            nodes: Vec::with_capacity(2 * objects.len()),
+            objects: Vec::with_capacity(objects.len()),
+            stats: BvhStats::default(),
+        };
+
+        let mut ordered = Vec::with_capacity(objects.len());
+        if !infos.is_empty() {
+            bvh.build(&mut infos, 1, &mut ordered);
+        }
+
+        // store the primitives in leaf order
+        let mut objects: Vec<Option<Box<dyn Hittable>>> = objects.into_iter().map(Some).collect();
+        bvh.objects = ordered
+            .iter()
+            .map(|&index| objects[index].take().unwrap())
+            .collect();
+
+        bvh.compute_stats();
+
+        bvh
+    }
+
+    pub fn stats(&self) -> &BvhStats {
+        &self.stats
+    }
+
+    // Build the subtree of `infos` and return the index of its root node
+    fn build(
+        &mut self,
+        infos: &mut [PrimitiveInfo],
+        depth: usize,
+        ordered: &mut Vec<usize>,
+    ) -> usize {
+        self.stats.max_depth = self.stats.max_depth.max(depth);
+
+        let bbox = infos
+            .iter()
+            .fold(None, |acc, info| union(acc, &info.bbox))
+            .unwrap();
+        let node_index = self.nodes.len();
+        self.nodes.push(LinearNode {
+            bbox,
+            offset: 0,
+            count: 0,
+            axis: 0,
+        });
+
+        let len = infos.len();
+        if len == 1 {
+            self.make_leaf(node_index, infos, ordered);
+            return node_index;
+        }
+
+        let centroid_bounds = infos
+            .iter()
+            .fold(None, |acc, info| {
+                union(acc, &Aabb::new(info.centroid, info.centroid))
+            })
+            .unwrap();
+        let extent = centroid_bounds.max() - centroid_bounds.min();
+        let widest = (0..3)
+            .max_by(|&a, &b| extent[a].partial_cmp(&extent[b]).unwrap())
+            .unwrap();
+
+        if extent[widest] <= 0.0 {
+            // all the centroids are at the same place, nothing to split
+            self.make_leaf(node_index, infos, ordered);
+            return node_index;
+        }
+
+        let split = if depth < MAX_SAH_DEPTH {
+            self.find_sah_split(infos, &bbox, &centroid_bounds)
+        } else {
+            None
+        };
+
+        let mid = match split {
+            Some((axis, bin, cost)) => {
+                if len <= MAX_LEAF_SIZE && len as f64 * INTERSECTION_COST <= cost {
+                    self.make_leaf(node_index, infos, ordered);
+                    return node_index;
+                }
+
+                self.nodes[node_index].axis = axis;
+                let min = centroid_bounds.min()[axis];
+                let mut mid = 0;
+                for i in 0..len {
+                    if bin_index(infos[i].centroid[axis], min, extent[axis]) <= bin {
+                        infos.swap(i, mid);
+                        mid += 1;
+                    }
+                }
+                mid
+            }
+            None => 0,
+        };
+
+        let mid = if mid == 0 || mid == len {
+            // no usable SAH split: split at the median along the widest axis
+            self.nodes[node_index].axis = widest;
+            let mid = len / 2;
+            infos.select_nth_unstable_by(mid, |a, b| {
+                a.centroid[widest].partial_cmp(&b.centroid[widest]).unwrap()
+            });
+            mid
+        } else {
+            mid
+        };
+
+        let (left, right) = infos.split_at_mut(mid);
+        self.build(left, depth + 1, ordered);
+        let second = self.build(right, depth + 1, ordered);
+        self.nodes[node_index].offset = second;
+
+        node_index
+    }
+
+    // Best (axis, last bin of the first child, cost) over the three axes
+    fn find_sah_split(
+        &self,
+        infos: &[PrimitiveInfo],
+        bbox: &Aabb,
+        centroid_bounds: &Aabb,
+    ) -> Option<(usize, usize, f64)> {
+        let area = bbox.surface_area();
+        let extent = centroid_bounds.max() - centroid_bounds.min();
+        let mut best: Option<(usize, usize, f64)> = None;
+
+        for axis in 0..3 {
+            if extent[axis] <= 0.0 {
+                continue;
+            }
+
+            let mut bins = [Bin {
+                count: 0,
+                bbox: None,
+            }; BINS];
+            let min = centroid_bounds.min()[axis];
+            for info in infos {
+                let b = bin_index(info.centroid[axis], min, extent[axis]);
+                bins[b].count += 1;
+                bins[b].bbox = union(bins[b].bbox, &info.bbox);
+            }
+
+            // sweep from the right to get the cost of every second child
+            let mut right_area = [0.0; BINS];
+            let mut right_count = [0; BINS];
+            let mut acc_box: Option<Aabb> = None;
+            let mut acc_count = 0;
+            for b in (1..BINS).rev() {
+                if let Some(bin_box) = &bins[b].bbox {
+                    acc_box = union(acc_box, bin_box);
+                }
+                acc_count += bins[b].count;
+                right_area[b] = acc_box.map_or(0.0, |b| b.surface_area());
+                right_count[b] = acc_count;
+            }
+
+            // then from the left, evaluating the split after each bin
+            let mut acc_box: Option<Aabb> = None;
+            let mut acc_count = 0;
+            for b in 0..BINS - 1 {
+                if let Some(bin_box) = &bins[b].bbox {
+                    acc_box = union(acc_box, bin_box);
+                }
+                acc_count += bins[b].count;
+                if acc_count == 0 || right_count[b + 1] == 0 {
+                    continue;
+                }
+
+                let left_area = acc_box.map_or(0.0, |b| b.surface_area());
+                let cost = TRAVERSAL_COST
+                    + INTERSECTION_COST
+                        * (acc_count as f64 * left_area
+                            + right_count[b + 1] as f64 * right_area[b + 1])
+                        / area;
+
+                if best.is_none_or(|(_, _, best_cost)| cost < best_cost) {
+                    best = Some((axis, b, cost));
+                }
+            }
+        }
+
+        best
+    }
+
+    fn make_leaf(&mut self, node_index: usize, infos: &[PrimitiveInfo], ordered: &mut Vec<usize>) {
+        let node = &mut self.nodes[node_index];
+        node.offset = ordered.len();
+        node.count = infos.len();
+
+        ordered.extend(infos.iter().map(|info| info.index));
+    }
+
+    fn compute_stats(&mut self) {
+        self.stats.node_count = self.nodes.len();
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let root_area = self.nodes[0].bbox.surface_area();
+        for node in self.nodes.iter() {
+            let relative_area = node.bbox.surface_area() / root_area;
+
+            if node.count > 0 {
+                self.stats.leaf_count += 1;
+                self.stats.max_leaf_size = self.stats.max_leaf_size.max(node.count);
+                self.stats.sah_cost += INTERSECTION_COST * node.count as f64 * relative_area;
+            } else {
+                self.stats.sah_cost += TRAVERSAL_COST * relative_area;
+            }
+        }
+    }
+}
+
+fn bin_index(centroid: f64, min: f64, extent: f64) -> usize {
+    let b = (BINS as f64 * (centroid - min) / extent) as usize;
+
+    b.min(BINS - 1)
+}
+
+impl Hittable for SahBvh {
+    fn hit(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<HitRecord> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let origin = r.origin();
+        let direction = r.direction();
+        let inv_d = Vec3::new(
+            1.0 / direction.x(),
+            1.0 / direction.y(),
+            1.0 / direction.z(),
+        );
+        let dir_is_neg = [inv_d.x() < 0.0, inv_d.y() < 0.0, inv_d.z() < 0.0];
+
+        let mut closest = None;
+        let mut closest_so_far = tmax;
+
+        let mut stack = [0usize; STACK_SIZE];
+        let mut stack_len = 0;
+        let mut current = 0;
+        let mut visited_nodes = 0;
+        let mut tested_primitives = 0;
+
+        loop {
+            let node = &self.nodes[current];
+            visited_nodes += 1;
+
+            if node.bbox.hit_inv(&origin, &inv_d, tmin, closest_so_far) {
+                if node.count > 0 {
+                    for obj in self.objects[node.offset..node.offset + node.count].iter() {
+                        tested_primitives += 1;
+                        if let Some(hr) = obj.hit(r, tmin, closest_so_far) {
+                            closest_so_far = hr.get_t();
+                            closest = Some(hr);
+                        }
+                    }
+                } else {
+                    // visit the nearest child first, keep the other one for later
+                    if dir_is_neg[node.axis] {
+                        stack[stack_len] = current + 1;
+                        current = node.offset;
+                    } else {
+                        stack[stack_len] = node.offset;
+                        current += 1;
+                    }
+                    stack_len += 1;
+                    continue;
+                }
+            }
+
+            if stack_len == 0 {
+                break;
+            }
+            stack_len -= 1;
+            current = stack[stack_len];
+        }
+
+        count_visits(visited_nodes, tested_primitives);
+
+        closest
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<Aabb> {
+        self.nodes.first().map(|node| node.bbox)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::sphere::Sphere;
+    use crate::geometry::traversal::{enable_visit_counts, thread_stats};
+    use crate::materials::lambertian::Lambertian;
+    use crate::materials::Material;
+    use crate::tools::random_double_range;
+    use crate::vec3::Color;
+    use std::sync::Arc;
+
+    fn spheres(n: usize) -> Vec<Box<dyn Hittable>> {
+        let material: Arc<dyn Material> = Arc::new(Lambertian::from(Color::new(0.5, 0.5, 0.5)));
+
+        (0..n)
+            .map(|_| -> Box<dyn Hittable> {
+                Box::new(Sphere {
+                    center: Point3::new(
+                        random_double_range(-10.0, 10.0),
+                        random_double_range(-10.0, 10.0),
+                        random_double_range(-10.0, 10.0),
+                    ),
+                    radius: random_double_range(0.1, 1.5),
+                    material: material.clone(),
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_same_hits_as_brute_force() {
+        enable_visit_counts();
+
+        // overlapping spheres, so the closest hit is often not in the first leaf
+        let bvh = SahBvh::new(spheres(300), 0.0, 1.0);
+        // the same primitives, tested one after the other
+        let objects = &bvh.objects;
+
+        let mut hits = 0;
+        for _ in 0..2000 {
+            let origin = Vec3::random_range(-15.0, 15.0);
+            let r = Ray::new(origin, Vec3::random_unit_vector(), 0.0);
+
+            let expected = objects
+                .iter()
+                .filter_map(|obj| obj.hit(&r, 0.001, f64::INFINITY))
+                .min_by(|a, b| a.get_t().partial_cmp(&b.get_t()).unwrap());
+            let before = thread_stats();
+            let hit = bvh.hit(&r, 0.001, f64::INFINITY);
+            assert!(thread_stats().nodes > before.nodes);
+
+            match (hit, expected) {
+                (None, None) => {}
+                (Some(hit), Some(expected)) => {
+                    assert_eq!(hit.get_t(), expected.get_t());
+                    assert_eq!(hit.get_p(), expected.get_p());
+                    hits += 1;
+                }
+                _ => panic!("the BVH and the objects disagree"),
+            }
+        }
+        // about a quarter of the rays hit something
+        assert!(hits > 300, "{}", hits);
+
+        assert!(SahBvh::new(Vec::new(), 0.0, 1.0)
+            .hit(
+                &Ray::new(Point3::zero(), Vec3::new(1.0, 0.0, 0.0), 0.0),
+                0.001,
+                1.0
+            )
+            .is_none());
+    }
+}
@@ -15,6 +15,10 @@ pub struct Sphere {
 }
 
 impl Sphere {
+    fn contains(&self, p: &Point3) -> bool {
+        (*p - self.center).length_squared() <= self.radius * self.radius
+    }
+
     pub fn get_uv(p: &Point3) -> (f64, f64) {
         // p: a given point on the sphere of radius one, centered at the origin.
         // u: returned value [0,1] of angle around the Y axis from X=-1.
@@ -77,7 +81,11 @@ impl Hittable for Sphere {
 
     fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
         let ray = Ray::new(*origin, *v, 0.0);
-        if let Some(_hr) = self.hit(&ray, 0.001, std::f64::INFINITY) {
+        if let Some(_hr) = self.hit(&ray, 0.001, f64::INFINITY) {
+            // seen from inside, the sphere is all around
+            if self.contains(origin) {
+                return 1.0 / (4.0 * PI);
+            }
             let cos_theta_max =
                 (1.0 - self.radius.powi(2) / (self.center - *origin).length_squared()).sqrt();
             let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
@@ -89,6 +97,9 @@ impl Hittable for Sphere {
     }
 
     fn random(&self, origin: &Point3) -> Vec3 {
+        if self.contains(origin) {
+            return Vec3::random_unit_vector();
+        }
         let direction = self.center - *origin;
         let distance_squared = direction.length_squared();
 
@@ -97,3 +108,49 @@ impl Hittable for Sphere {
         uvw.local(&Vec3::random_to_sphere(self.radius, distance_squared))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::moving_sphere::MovingSphere;
+    use crate::materials::lambertian::Lambertian;
+    use crate::vec3::Color;
+
+    #[test]
+    fn test_sampled_from_inside() {
+        let material: Arc<dyn Material> = Arc::new(Lambertian::from(Color::new(0.5, 0.5, 0.5)));
+        let sphere = Sphere {
+            center: Point3::new(1.0, 2.0, 3.0),
+            radius: 2.0,
+            material: material.clone(),
+        };
+        let moving = MovingSphere {
+            center0: Point3::new(1.0, 2.0, 3.0),
+            center1: Point3::new(1.0, 3.0, 3.0),
+            time0: 0.0,
+            time1: 1.0,
+            radius: 2.0,
+            material,
+        };
+
+        // the centre, a point near the surface and one inside the swept sphere
+        let hittables: [&dyn Hittable; 2] = [&sphere, &moving];
+        for shape in hittables {
+            for origin in [
+                Point3::new(1.0, 2.0, 3.0),
+                Point3::new(2.9, 2.0, 3.0),
+                Point3::new(1.0, 4.5, 3.0),
+            ] {
+                for _ in 0..100 {
+                    let v = shape.random(&origin);
+                    let pdf = shape.pdf_value(&origin, &v);
+                    assert!(pdf.is_finite() && pdf > 0.0, "{}", pdf);
+                }
+            }
+        }
+        assert_eq!(
+            sphere.pdf_value(&Point3::new(1.0, 4.5, 3.0), &Vec3::new(0.0, 1.0, 0.0)),
+            0.0
+        );
+    }
+}
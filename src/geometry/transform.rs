@@ -0,0 +1,210 @@
+use crate::camera::ray::Ray;
+use crate::geometry::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::matrix::Matrix4;
+use crate::vec3::{Point3, Vec3};
+
+// Any hittable placed with an affine transform (object to world matrix)
+pub struct Transform<H: Hittable> {
+    hittable: H,
+    matrix: Matrix4,
+    inverse: Matrix4,
+    // transposed inverse, maps normals to world space
+    normal_matrix: Matrix4,
+}
+
+impl<H: Hittable> Transform<H> {
+    pub fn new(hittable: H, matrix: Matrix4) -> Result<Transform<H>, String> {
+        let inverse = matrix
+            .inverse()
+            .ok_or_else(|| format!("Transform matrix {:?} is not invertible", matrix))?;
+
+        Ok(Transform {
+            hittable,
+            matrix,
+            inverse,
+            normal_matrix: inverse.transpose(),
+        })
+    }
+}
+
+impl<H: Hittable> Hittable for Transform<H> {
+    fn hit(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<HitRecord> {
+        // the direction is not normalized so t is the same in both spaces
+        let object_r = Ray::new(
+            self.inverse.transform_point(r.origin()),
+            self.inverse.transform_vector(r.direction()),
+            r.time(),
+        );
+
+        let hr = self.hittable.hit(&object_r, tmin, tmax)?;
+
+        let outward_normal = if hr.is_front() {
+            hr.get_normal()
+        } else {
+            -hr.get_normal()
+        };
+        let normal = self
+            .normal_matrix
+            .transform_vector(outward_normal)
+            .to_unit_vector();
+
+        let light = hr.get_light();
+        let mut hr_ret = HitRecord::new(
+            self.matrix.transform_point(hr.get_p()),
+            normal,
+            hr.get_t(),
+            hr.get_u(),
+            hr.get_v(),
+            hr.material,
+        );
+        hr_ret.set_front_face(r, normal);
+        hr_ret.set_light(light);
+
+        Some(hr_ret)
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb> {
+        let bbox = self.hittable.bounding_box(time0, time1)?;
+
+        // box around the transformed corners
+        let mut min = Point3::max();
+        let mut max = Point3::min();
+        for i in 0..8 {
+            let corner = Point3::new(
+                if i & 1 == 0 {
+                    bbox.min().x()
+                } else {
+                    bbox.max().x()
+                },
+                if i & 2 == 0 {
+                    bbox.min().y()
+                } else {
+                    bbox.max().y()
+                },
+                if i & 4 == 0 {
+                    bbox.min().z()
+                } else {
+                    bbox.max().z()
+                },
+            );
+            let p = self.matrix.transform_point(corner);
+
+            for c in 0..3 {
+                min[c] = min[c].min(p[c]);
+                max[c] = max[c].max(p[c]);
+            }
+        }
+
+        Some(Aabb::new(min, max))
+    }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        let object_origin = self.inverse.transform_point(*origin);
+        let object_v = self.inverse.transform_vector(*v);
+
+        // the solid angle pdf changes with the mapping of directions
+        // w -> A w / |A w|, whose jacobian is |det A| / |A w|^3 for unit w
+        let stretch = object_v.length() / v.length();
+        let jacobian = self.inverse.determinant3().abs() / (stretch * stretch * stretch);
+
+        self.hittable.pdf_value(&object_origin, &object_v) * jacobian
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        let object_origin = self.inverse.transform_point(*origin);
+
+        self.matrix
+            .transform_vector(self.hittable.random(&object_origin))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::geometry::aarect::XzRect;
+    use crate::geometry::rotate::RotateY;
+    use crate::geometry::sphere::Sphere;
+    use crate::materials::metal::Metal;
+    use crate::materials::Material;
+    use crate::vec3::Color;
+
+    fn material() -> Arc<dyn Material> {
+        Arc::new(Metal::new(Color::new(0.5, 0.5, 0.5), 0.0))
+    }
+
+    fn rect(x0: f64, x1: f64, z0: f64, z1: f64, k: f64) -> XzRect {
+        XzRect {
+            x0,
+            x1,
+            z0,
+            z1,
+            k,
+            material: material(),
+        }
+    }
+
+    #[test]
+    fn test_scaled_sphere() {
+        // ellipsoid x^2/4 + y^2 + z^2/9 = 1 centred at (0, 5, 0)
+        let sphere = Sphere {
+            center: Point3::zero(),
+            radius: 1.0,
+            material: material(),
+        };
+        let m = Matrix4::translation(Vec3::new(0.0, 5.0, 0.0))
+            * Matrix4::scaling(Vec3::new(2.0, 1.0, 3.0));
+        let ellipsoid = Transform::new(sphere, m).unwrap();
+
+        let r = Ray::new(Point3::new(-10.0, 5.5, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        let hr = ellipsoid.hit(&r, 0.001, f64::INFINITY).unwrap();
+
+        let x = -2.0 * 0.75_f64.sqrt();
+        assert!((hr.get_p() - Point3::new(x, 5.5, 0.0)).length() < 1E-9);
+        assert!((hr.get_t() - (x + 10.0)).abs() < 1E-9);
+        // gradient of the implicit surface
+        let normal = Vec3::new(x / 4.0, 0.5, 0.0).to_unit_vector();
+        assert!((hr.get_normal() - normal).length() < 1E-9);
+        assert!(hr.is_front());
+    }
+
+    #[test]
+    fn test_transformed_light_pdf() {
+        let m = Matrix4::translation(Vec3::new(0.0, 5.0, 0.0))
+            * Matrix4::scaling(Vec3::new(2.0, 1.0, 3.0));
+        let transformed = Transform::new(rect(-1.0, 1.0, -1.0, 1.0, 0.0), m).unwrap();
+        let light = rect(-2.0, 2.0, -3.0, 3.0, 5.0);
+
+        let origin = Point3::new(0.5, 1.0, -0.3);
+        for v in &[
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.3, 1.0, 0.4),
+            Vec3::new(-1.0, 2.0, 1.5),
+        ] {
+            let expected = light.pdf_value(&origin, v);
+            assert!(expected > 0.0);
+            assert!((transformed.pdf_value(&origin, v) - expected).abs() < 1E-9 * expected);
+        }
+
+        for _ in 0..100 {
+            let v = transformed.random(&origin);
+            assert!(light
+                .hit(&Ray::new(origin, v, 0.0), 0.001, f64::INFINITY)
+                .is_some());
+        }
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let rotated = RotateY::new(rect(-1.0, 2.0, -3.0, 1.0, 0.5), 30.0);
+        let transformed =
+            Transform::new(rect(-1.0, 2.0, -3.0, 1.0, 0.5), Matrix4::rotation_y(30.0)).unwrap();
+
+        let expected = rotated.bounding_box(0.0, 1.0).unwrap();
+        let bbox = transformed.bounding_box(0.0, 1.0).unwrap();
+        assert!((bbox.min() - expected.min()).length() < 1E-9);
+        assert!((bbox.max() - expected.max()).length() < 1E-9);
+    }
+}
@@ -0,0 +1,102 @@
+// BVH traversal counters
+//
+// Counting happens in thread local storage so it stays cheap, the totals are
+// regularly flushed into global counters readable from any thread, and by the
+// renderers once a tile is done. The BVH visits are only counted once enabled,
+// with --bvh-stats or for the BVH cost view, the rays always are.
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+const FLUSH_EVERY_RAYS: u64 = 1024;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TraversalStats {
+    pub rays: u64,
+    pub nodes: u64,
+    pub primitives: u64,
+}
+
+impl TraversalStats {
+    pub fn nodes_per_ray(&self) -> f64 {
+        self.nodes as f64 / self.rays.max(1) as f64
+    }
+
+    pub fn primitives_per_ray(&self) -> f64 {
+        self.primitives as f64 / self.rays.max(1) as f64
+    }
+}
+
+static RAYS: AtomicU64 = AtomicU64::new(0);
+static NODES: AtomicU64 = AtomicU64::new(0);
+static PRIMITIVES: AtomicU64 = AtomicU64::new(0);
+static COUNT_VISITS: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    // counters of this thread since it started
+    static TOTAL: Cell<TraversalStats> = Cell::new(TraversalStats::default());
+    // part of TOTAL not yet added to the global counters
+    static PENDING: Cell<TraversalStats> = Cell::new(TraversalStats::default());
+}
+
+fn add(rays: u64, nodes: u64, primitives: u64) {
+    TOTAL.with(|total| {
+        let mut t = total.get();
+        t.rays += rays;
+        t.nodes += nodes;
+        t.primitives += primitives;
+        total.set(t);
+    });
+
+    PENDING.with(|pending| {
+        let mut p = pending.get();
+        p.rays += rays;
+        p.nodes += nodes;
+        p.primitives += primitives;
+
+        pending.set(p);
+    });
+
+    if PENDING.with(|pending| pending.get().rays) >= FLUSH_EVERY_RAYS {
+        flush();
+    }
+}
+
+// Add the counters of the calling thread not yet flushed to the global ones
+pub fn flush() {
+    let p = PENDING.with(|pending| pending.replace(TraversalStats::default()));
+
+    RAYS.fetch_add(p.rays, Ordering::Relaxed);
+    NODES.fetch_add(p.nodes, Ordering::Relaxed);
+    PRIMITIVES.fetch_add(p.primitives, Ordering::Relaxed);
+}
+
+// One ray was traced through the scene
+pub fn count_ray() {
+    add(1, 0, 0);
+}
+
+// Start counting the nodes and primitives visited by the BVHs
+pub fn enable_visit_counts() {
+    COUNT_VISITS.store(true, Ordering::Relaxed);
+}
+
+// A BVH visited `nodes` nodes and tested `primitives` primitives for the current ray
+pub fn count_visits(nodes: u64, primitives: u64) {
+    if COUNT_VISITS.load(Ordering::Relaxed) {
+        add(0, nodes, primitives);
+    }
+}
+
+// Counters of the calling thread, always exact
+pub fn thread_stats() -> TraversalStats {
+    TOTAL.with(|total| total.get())
+}
+
+// Counters of all threads, up to what they have not flushed yet
+pub fn global_stats() -> TraversalStats {
+    TraversalStats {
+        rays: RAYS.load(Ordering::Relaxed),
+        nodes: NODES.load(Ordering::Relaxed),
+        primitives: PRIMITIVES.load(Ordering::Relaxed),
+    }
+}
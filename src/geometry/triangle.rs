@@ -0,0 +1,249 @@
+use std::sync::Arc;
+
+use crate::camera::ray::Ray;
+use crate::geometry::aabb::Aabb;
+use crate::geometry::mesh::Mesh;
+use crate::hittable::{HitRecord, Hittable};
+use crate::materials::Material;
+use crate::tools::random_double;
+use crate::vec3::{Point3, Vec3};
+
+// Triangle of a Mesh
+pub struct Triangle {
+    mesh: Arc<Mesh>,
+    indices: [usize; 3],
+    pub material: Arc<dyn Material>,
+}
+
+impl Triangle {
+    pub fn new(mesh: Arc<Mesh>, indices: [usize; 3], material: Arc<dyn Material>) -> Triangle {
+        Triangle {
+            mesh,
+            indices,
+            material,
+        }
+    }
+
+    // Standalone triangle with its own single face mesh
+    pub fn from_vertices(
+        v0: Point3,
+        v1: Point3,
+        v2: Point3,
+        material: Arc<dyn Material>,
+    ) -> Triangle {
+        let mesh = Mesh {
+            positions: vec![v0, v1, v2],
+            indices: vec![[0, 1, 2]],
+            ..Default::default()
+        };
+
+        Triangle::new(Arc::new(mesh), [0, 1, 2], material)
+    }
+
+    pub fn vertices(&self) -> (Point3, Point3, Point3) {
+        (
+            self.mesh.positions[self.indices[0]],
+            self.mesh.positions[self.indices[1]],
+            self.mesh.positions[self.indices[2]],
+        )
+    }
+
+    fn geometric_normal(&self) -> Vec3 {
+        let (p0, p1, p2) = self.vertices();
+
+        (p1 - p0).cross(p2 - p0).to_unit_vector()
+    }
+
+    fn area(&self) -> f64 {
+        let (p0, p1, p2) = self.vertices();
+
+        0.5 * (p1 - p0).cross(p2 - p0).length()
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<HitRecord> {
+        // Möller–Trumbore intersection
+        let (p0, p1, p2) = self.vertices();
+        let e1 = p1 - p0;
+        let e2 = p2 - p0;
+
+        let pvec = r.direction().cross(e2);
+        let det = e1.dot(pvec);
+        if det.abs() < 1E-12 {
+            // ray is parallel to the triangle
+            return None;
+        }
+        let inv_det = 1.0 / det;
+
+        let tvec = r.origin() - p0;
+        let b1 = tvec.dot(pvec) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+
+        let qvec = tvec.cross(e1);
+        let b2 = r.direction().dot(qvec) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+
+        let t = e2.dot(qvec) * inv_det;
+        if t < tmin || t > tmax {
+            return None;
+        }
+
+        // barycentric coordinates are used when the mesh has no texture coordinates
+        let b0 = 1.0 - b1 - b2;
+        let [i0, i1, i2] = self.indices;
+        let (u, v) = if self.mesh.has_uvs() {
+            let uvs = &self.mesh.uvs;
+            (
+                b0 * uvs[i0].0 + b1 * uvs[i1].0 + b2 * uvs[i2].0,
+                b0 * uvs[i0].1 + b1 * uvs[i1].1 + b2 * uvs[i2].1,
+            )
+        } else {
+            (b1, b2)
+        };
+
+        // interpolated vertex normals for smooth shading
+        let outward_normal = if self.mesh.has_normals() {
+            let normals = &self.mesh.normals;
+            (b0 * normals[i0] + b1 * normals[i1] + b2 * normals[i2]).to_unit_vector()
+        } else {
+            e1.cross(e2).to_unit_vector()
+        };
+
+        let mut hr = HitRecord::new(r.at(t), Vec3::zero(), t, u, v, self.material.clone());
+        hr.set_front_face(r, outward_normal);
+
+        Some(hr)
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<Aabb> {
+        let (p0, p1, p2) = self.vertices();
+
+        // The bounding box must have non-zero width in each dimension, so pad it a small amount.
+        let mut min = p0;
+        let mut max = p0;
+        for p in [p1, p2] {
+            for c in 0..3 {
+                min[c] = min[c].min(p[c]);
+                max[c] = max[c].max(p[c]);
+            }
+        }
+        let pad = Vec3::new(0.0001, 0.0001, 0.0001);
+
+        Some(Aabb::new(min - pad, max + pad))
+    }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        let ray = Ray::new(*origin, *v, 0.0);
+        if let Some(hr) = self.hit(&ray, 0.001, f64::INFINITY) {
+            let distance_squared = hr.get_t() * hr.get_t() * v.length_squared();
+            let cosine = (v.dot(self.geometric_normal()) / v.length()).abs();
+
+            return distance_squared / (cosine * self.area());
+        }
+
+        0.0
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        // uniform sampling of the triangle area
+        let (p0, p1, p2) = self.vertices();
+        let s = random_double().sqrt();
+        let b0 = 1.0 - s;
+        let b1 = random_double() * s;
+
+        let random_point = b0 * p0 + b1 * p1 + (1.0 - b0 - b1) * p2;
+
+        random_point - *origin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian::Lambertian;
+    use crate::vec3::Color;
+
+    fn material() -> Arc<dyn Material> {
+        Arc::new(Lambertian::from(Color::new(0.5, 0.5, 0.5)))
+    }
+
+    // Straight down on (x, y) of the plane z = 0
+    fn down(x: f64, y: f64) -> Ray {
+        Ray::new(Point3::new(x, y, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0)
+    }
+
+    #[test]
+    fn test_barycentrics_and_edges() {
+        let triangle = Triangle::from_vertices(
+            Point3::zero(),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+            material(),
+        );
+
+        // without texture coordinates (u, v) are the barycentrics of p1 and p2
+        let hr = triangle
+            .hit(&down(0.25, 0.5), 0.001, f64::INFINITY)
+            .unwrap();
+        assert_eq!(hr.get_t(), 1.0);
+        assert_eq!((hr.get_u(), hr.get_v()), (0.25, 0.5));
+        assert_eq!(hr.get_p(), Point3::new(0.25, 0.5, 0.0));
+        // the geometric normal follows the winding, toward +z
+        assert!(hr.is_front());
+        assert_eq!(hr.get_normal(), Vec3::new(0.0, 0.0, 1.0));
+
+        // on the edges and the vertices, not beyond them
+        for (x, y) in [(0.5, 0.5), (0.0, 0.3), (0.3, 0.0), (0.0, 0.0), (1.0, 0.0)] {
+            assert!(triangle.hit(&down(x, y), 0.001, f64::INFINITY).is_some());
+        }
+        for (x, y) in [(0.5 + 1E-9, 0.5), (-1E-9, 0.3), (0.3, -1E-9)] {
+            assert!(triangle.hit(&down(x, y), 0.001, f64::INFINITY).is_none());
+        }
+
+        // behind the origin, beyond tmax and parallel
+        assert!(triangle.hit(&down(0.2, 0.2), 1.5, f64::INFINITY).is_none());
+        assert!(triangle.hit(&down(0.2, 0.2), 0.001, 0.5).is_none());
+        let parallel = Ray::new(Point3::new(-1.0, 0.2, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        assert!(triangle.hit(&parallel, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let normals = [
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(1.0, 0.0, 1.0).to_unit_vector(),
+            Vec3::new(0.0, 1.0, 1.0).to_unit_vector(),
+        ];
+        let mesh = Arc::new(Mesh {
+            positions: vec![
+                Point3::zero(),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ],
+            normals: normals.to_vec(),
+            uvs: vec![(0.0, 0.0), (1.0, 0.0), (0.5, 1.0)],
+            indices: vec![[0, 1, 2]],
+        });
+        let triangle = Triangle::new(mesh, [0, 1, 2], material());
+
+        // barycentrics (0.25, 0.25, 0.5)
+        let hr = triangle
+            .hit(&down(0.25, 0.5), 0.001, f64::INFINITY)
+            .unwrap();
+        assert!((hr.get_u() - 0.5).abs() < 1E-12);
+        assert!((hr.get_v() - 0.5).abs() < 1E-12);
+
+        let normal = (0.25 * normals[0] + 0.25 * normals[1] + 0.5 * normals[2]).to_unit_vector();
+        assert!((hr.get_normal() - normal).length() < 1E-12);
+
+        // at a vertex, its own attributes
+        let hr = triangle.hit(&down(1.0, 0.0), 0.001, f64::INFINITY).unwrap();
+        assert!((hr.get_u() - 1.0).abs() < 1E-12 && hr.get_v().abs() < 1E-12);
+        assert!((hr.get_normal() - normals[1]).length() < 1E-12);
+    }
+}
@@ -1,9 +1,9 @@
-use rand::seq::SliceRandom;
 use std::sync::Arc;
 
 use crate::camera::ray::Ray;
 use crate::geometry::aabb::Aabb;
 use crate::materials::Material;
+use crate::tools::random_usize_range;
 use crate::vec3::{Point3, Vec3};
 
 #[derive(Clone)]
@@ -14,6 +14,10 @@ pub struct HitRecord {
     u: f64,
     v: f64,
     front_face: bool,
+    // top level object of the scene, 0 when not set
+    object_id: u32,
+    // index among the lights of the scene of the emitter hit, when it is one
+    light: Option<usize>,
     pub material: Arc<dyn Material>,
 }
 
@@ -33,6 +37,8 @@ impl HitRecord {
             u,
             v,
             front_face: true,
+            object_id: 0,
+            light: None,
             material: mat,
         }
     }
@@ -58,6 +64,15 @@ impl HitRecord {
         self.normal
     }
 
+    // Normal of the surface itself, whichever side the ray comes from
+    pub fn get_outward_normal(&self) -> Vec3 {
+        if self.front_face {
+            self.normal
+        } else {
+            -self.normal
+        }
+    }
+
     pub fn get_t(&self) -> f64 {
         self.t
     }
@@ -70,6 +85,22 @@ impl HitRecord {
         self.v
     }
 
+    pub fn get_object_id(&self) -> u32 {
+        self.object_id
+    }
+
+    pub fn set_object_id(&mut self, object_id: u32) {
+        self.object_id = object_id;
+    }
+
+    pub fn get_light(&self) -> Option<usize> {
+        self.light
+    }
+
+    pub fn set_light(&mut self, light: Option<usize>) {
+        self.light = light;
+    }
+
     pub fn translate(&mut self, offset: Vec3) {
         self.p += offset;
     }
@@ -83,7 +114,7 @@ impl HitRecord {
     }
 }
 
-pub trait Hittable: Sync {
+pub trait Hittable: Send + Sync {
     fn hit(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<HitRecord>;
     fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb>;
 
@@ -144,8 +175,11 @@ impl Hittable for Vec<Box<dyn Hittable>> {
     }
 
     fn random(&self, origin: &Point3) -> Vec3 {
-        self.choose(&mut rand::thread_rng())
-            .map_or(Vec3::new(1.0, 0.0, 0.0), |obj| obj.random(origin))
+        if self.is_empty() {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+
+        self[random_usize_range(0, self.len() - 1)].random(origin)
     }
 }
 
@@ -166,3 +200,39 @@ impl Hittable for &Vec<Box<dyn Hittable>> {
         (*self).random(origin)
     }
 }
+
+impl Hittable for Box<dyn Hittable> {
+    fn hit(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<HitRecord> {
+        self.as_ref().hit(r, tmin, tmax)
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb> {
+        self.as_ref().bounding_box(time0, time1)
+    }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        self.as_ref().pdf_value(origin, v)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.as_ref().random(origin)
+    }
+}
+
+impl Hittable for Arc<dyn Hittable> {
+    fn hit(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<HitRecord> {
+        self.as_ref().hit(r, tmin, tmax)
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb> {
+        self.as_ref().bounding_box(time0, time1)
+    }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        self.as_ref().pdf_value(origin, v)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.as_ref().random(origin)
+    }
+}
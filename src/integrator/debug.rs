@@ -0,0 +1,155 @@
+// Debug views of the first surface hit by camera rays, black where they
+// escape the scene
+use crate::camera::ray::Ray;
+use crate::geometry::traversal::{count_ray, thread_stats};
+use crate::hittable::{HitRecord, Hittable};
+use crate::onb::OrthoNormalBasis;
+use crate::scene::Scene;
+use crate::vec3::{Color, Vec3};
+
+use super::Integrator;
+
+// BVH nodes and primitives visited by a ray at the red end of the heatmap
+const BVH_COST_MAX: f64 = 100.0;
+
+fn first_hit(r: &Ray, scene: &Scene) -> Option<HitRecord> {
+    count_ray();
+
+    scene.world.hit(r, 0.001, f64::INFINITY)
+}
+
+// Blue to red colour ramp of `t` in [0, 1]
+fn heat(t: f64) -> Color {
+    let band = |center: f64| (1.5 - (4.0 * t - center).abs()).clamp(0.0, 1.0);
+
+    Color::new(band(3.0), band(2.0), band(1.0))
+}
+
+// Outward surface normal, mapped from [-1, 1] to [0, 1]
+pub struct Normals;
+
+impl Integrator for Normals {
+    fn ray_color(&self, r: &Ray, scene: &Scene) -> Color {
+        first_hit(r, scene).map_or(Color::zero(), |hr| {
+            0.5 * (hr.get_outward_normal() + Color::new(1.0, 1.0, 1.0))
+        })
+    }
+}
+
+// Texture coordinates in the red and green channels
+pub struct Uv;
+
+impl Integrator for Uv {
+    fn ray_color(&self, r: &Ray, scene: &Scene) -> Color {
+        first_hit(r, scene).map_or(Color::zero(), |hr| Color::new(hr.get_u(), hr.get_v(), 0.0))
+    }
+}
+
+// Distance from the camera, in scene units
+pub struct Depth;
+
+impl Integrator for Depth {
+    fn ray_color(&self, r: &Ray, scene: &Scene) -> Color {
+        first_hit(r, scene).map_or(Color::zero(), |hr| {
+            let distance = hr.get_t() * r.direction().length();
+
+            Color::new(distance, distance, distance)
+        })
+    }
+}
+
+// Attenuation of the material, the emitters are black
+pub struct Albedo;
+
+impl Integrator for Albedo {
+    fn ray_color(&self, r: &Ray, scene: &Scene) -> Color {
+        first_hit(r, scene)
+            .and_then(|hr| hr.material.scatter(r, &hr))
+            .map_or(Color::zero(), |srec| srec.attenuation())
+    }
+}
+
+// Heatmap of the BVH nodes and primitives visited by the camera ray
+pub struct BvhCost;
+
+impl Integrator for BvhCost {
+    fn ray_color(&self, r: &Ray, scene: &Scene) -> Color {
+        let before = thread_stats();
+        first_hit(r, scene);
+        let after = thread_stats();
+
+        let cost = (after.nodes - before.nodes) + (after.primitives - before.primitives);
+
+        heat(cost as f64 / BVH_COST_MAX)
+    }
+}
+
+// White where a cosine distributed ray from the hit point escapes within
+// `distance`, black where it is occluded
+pub struct AmbientOcclusion {
+    pub distance: f64,
+}
+
+impl Integrator for AmbientOcclusion {
+    fn ray_color(&self, r: &Ray, scene: &Scene) -> Color {
+        let hr = match first_hit(r, scene) {
+            Some(hr) => hr,
+            None => return Color::zero(),
+        };
+
+        let uvw = OrthoNormalBasis::from(hr.get_normal());
+        let direction = uvw.local(&Vec3::random_cosine_direction());
+        let occlusion = Ray::new(hr.get_p(), direction, r.time());
+
+        count_ray();
+        match scene.world.hit(&occlusion, 0.001, self.distance) {
+            Some(_) => Color::zero(),
+            None => Color::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::description::SceneDescription;
+    use crate::scene::SceneKind;
+    use crate::vec3::Point3;
+
+    #[test]
+    fn test_first_hit_views() {
+        let scene = SceneDescription::from_kind(SceneKind::CornellBox, false, "")
+            .build()
+            .unwrap();
+
+        // straight into the back wall above the boxes, 800 + 555 units away
+        let r = Ray::new(
+            Point3::new(278.0, 500.0, -800.0),
+            Vec3::new(0.0, 0.0, 2.0),
+            0.0,
+        );
+        let depth = Depth.ray_color(&r, &scene);
+        assert!((depth.x() - 1355.0).abs() < 1E-9);
+
+        // the wall is a rectangle of normal +z, seen from behind
+        let normal = Normals.ray_color(&r, &scene);
+        assert!((normal - Color::new(0.5, 0.5, 1.0)).length() < 1E-9);
+
+        let albedo = Albedo.ray_color(&r, &scene);
+        assert!((albedo - Color::new(0.73, 0.73, 0.73)).length() < 1E-9);
+
+        // nothing is close to the middle of the wall
+        let ao = AmbientOcclusion { distance: 50.0 };
+        assert_eq!(ao.ray_color(&r, &scene).x(), 1.0);
+
+        let away = Ray::new(r.origin(), -r.direction(), 0.0);
+        assert_eq!(Depth.ray_color(&away, &scene).x(), 0.0);
+    }
+
+    #[test]
+    fn test_heat() {
+        assert_eq!(heat(0.0), Color::new(0.0, 0.0, 0.5));
+        assert_eq!(heat(0.5), Color::new(0.5, 1.0, 0.5));
+        assert_eq!(heat(1.0), Color::new(0.5, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,101 @@
+// Integrators turn a camera ray into the colour of its sample: the radiance
+// for the path tracer, a quantity of the first hit for the debug ones.
+use std::fmt::Display;
+use std::str::FromStr;
+
+use clap::{ArgEnum, PossibleValue};
+use serde::{Deserialize, Serialize};
+
+use crate::camera::ray::Ray;
+use crate::geometry::traversal::enable_visit_counts;
+use crate::hittable::Hittable;
+use crate::scene::{Config, Scene};
+use crate::vec3::Color;
+
+use debug::{Albedo, AmbientOcclusion, BvhCost, Depth, Normals, Uv};
+use path::PathTracer;
+
+pub mod debug;
+pub mod path;
+
+pub trait Integrator: Send + Sync {
+    fn ray_color(&self, r: &Ray, scene: &Scene) -> Color;
+}
+
+#[derive(ArgEnum, Serialize, Deserialize, Debug, Default, PartialEq, Clone, Copy)]
+pub enum IntegratorKind {
+    #[default]
+    PathTracer,
+    // emitters and a single bounce of light
+    DirectLighting,
+    Normals,
+    Uv,
+    Depth,
+    Albedo,
+    BvhCost,
+    AmbientOcclusion,
+}
+
+impl FromStr for IntegratorKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        for variant in Self::value_variants() {
+            if variant.to_possible_value().unwrap().matches(s, false) {
+                return Ok(*variant);
+            }
+        }
+        Err(format!("Invalid variant: {}", s))
+    }
+}
+
+impl Display for IntegratorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .fmt(f)
+    }
+}
+
+impl IntegratorKind {
+    pub fn possible_values() -> impl Iterator<Item = PossibleValue<'static>> {
+        Self::value_variants()
+            .iter()
+            .filter_map(ArgEnum::to_possible_value)
+    }
+}
+
+// The integrator selected by the configuration of a scene
+pub fn build(cfg: &Config, world: &Vec<Box<dyn Hittable>>) -> Box<dyn Integrator> {
+    match cfg.integrator {
+        IntegratorKind::PathTracer => Box::new(PathTracer {
+            max_depth: cfg.max_depth,
+            rr_min_depth: cfg.rr_min_depth,
+            max_luminance: cfg.max_luminance,
+        }),
+        IntegratorKind::DirectLighting => Box::new(PathTracer {
+            max_depth: cfg.max_depth.min(2),
+            rr_min_depth: 2,
+            max_luminance: cfg.max_luminance,
+        }),
+        IntegratorKind::Normals => Box::new(Normals),
+        IntegratorKind::Uv => Box::new(Uv),
+        IntegratorKind::Depth => Box::new(Depth),
+        IntegratorKind::Albedo => Box::new(Albedo),
+        IntegratorKind::BvhCost => {
+            enable_visit_counts();
+            Box::new(BvhCost)
+        }
+        IntegratorKind::AmbientOcclusion => {
+            // a tenth of the scene size by default
+            let distance = cfg.ao_distance.unwrap_or_else(|| {
+                world
+                    .bounding_box(cfg.time0, cfg.time1)
+                    .map_or(f64::INFINITY, |b| 0.1 * (b.max() - b.min()).length())
+            });
+
+            Box::new(AmbientOcclusion { distance })
+        }
+    }
+}
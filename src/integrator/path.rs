@@ -0,0 +1,371 @@
+// Unidirectional path tracer with next event estimation
+use crate::background::Background;
+use crate::camera::ray::Ray;
+use crate::geometry::traversal::count_ray;
+use crate::hittable::Hittable;
+use crate::lights::Lights;
+use crate::materials::ScatterRecord;
+use crate::media::Media;
+use crate::scene::Scene;
+use crate::tools::random_double;
+use crate::vec3::Color;
+
+use super::Integrator;
+
+// shadow rays stop short of the sampled point, not to hit the light itself
+const SHADOW_EPSILON: f64 = 1E-4;
+
+// Weight of a sample drawn with `pdf` when `other` could also have produced it
+fn power_heuristic(pdf: f64, other: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other * other);
+
+    a / (a + b)
+}
+
+pub struct PathTracer {
+    // hard cap on the number of bounces
+    pub max_depth: u32,
+    // paths may be stopped by Russian roulette from this bounce on
+    pub rr_min_depth: u32,
+    // samples brighter than this are scaled down
+    pub max_luminance: Option<f64>,
+}
+
+impl PathTracer {
+    // Radiance along `r`, traced iteratively with the path throughput. The
+    // lights are sampled at every diffuse bounce (next event estimation) and
+    // combined with the BSDF samples by multiple importance sampling; emitters
+    // reached after a specular bounce are never sampled. The background is an
+    // emitter too, sampled when it is one of the `lights`. The collisions in
+    // the `media` before the surfaces scatter like them, and the light
+    // sampled is attenuated by the media it goes through.
+    pub fn radiance(
+        &self,
+        r: &Ray,
+        background: &dyn Background,
+        world: &Vec<Box<dyn Hittable>>,
+        lights: &Lights,
+        media: &Media,
+    ) -> Color {
+        let mut color = Color::zero();
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = *r;
+        // density of the BSDF sample that produced `ray`
+        let mut bsdf_pdf: Option<f64> = None;
+
+        for depth in 0..self.max_depth {
+            count_ray();
+            let mut hit = world.hit(&ray, 0.001, f64::INFINITY);
+            if !media.is_empty() {
+                let tmax = hit.as_ref().map_or(f64::INFINITY, |hr| hr.get_t());
+                let (collision, weight) = media.sample(&ray, 0.001, tmax);
+                throughput = throughput * weight;
+                if throughput.max_component() <= 0.0 {
+                    break;
+                }
+                if collision.is_some() {
+                    hit = collision;
+                }
+            }
+
+            let mut emitted = match &hit {
+                Some(hr) => hr
+                    .material
+                    .emitted(&ray, hr, hr.get_u(), hr.get_v(), &hr.get_p()),
+                None => background.value(&ray.direction()),
+            };
+            if let Some(bsdf_pdf) = bsdf_pdf {
+                // the light sampling at the previous bounce also found this
+                // emitter, by sampling this very light
+                let light_pdf = match &hit {
+                    Some(hr) => hr.get_light().map_or(0.0, |light| {
+                        lights.light_pdf(light, &ray.origin(), &ray.direction())
+                    }),
+                    // only the lights at infinity are in this direction
+                    None => lights.pdf(&ray.origin(), &ray.direction()),
+                };
+                emitted *= power_heuristic(bsdf_pdf, light_pdf);
+            }
+            color += throughput * emitted;
+
+            let hr = match hit {
+                Some(hr) => hr,
+                None => break,
+            };
+
+            match hr.material.scatter(&ray, &hr) {
+                None => break,
+                Some(ScatterRecord::Specular {
+                    attenuation,
+                    ray: scattered,
+                }) => {
+                    throughput = throughput * attenuation;
+                    ray = scattered;
+                    bsdf_pdf = None;
+                }
+                Some(ScatterRecord::Diffuse { attenuation, pdf }) => {
+                    // next event estimation, for the same path lengths as the
+                    // BSDF samples
+                    if depth + 1 < self.max_depth {
+                        if let Some(ls) = lights.sample(&hr.get_p(), ray.time()) {
+                            let shadow = Ray::new(hr.get_p(), ls.direction, ray.time());
+                            let tmax = ls.distance * (1.0 - SHADOW_EPSILON);
+
+                            count_ray();
+                            if world.hit(&shadow, 0.001, tmax).is_none() {
+                                let tr = media.transmittance(&shadow, 0.001, tmax);
+                                let f =
+                                    tr * hr.material.scattering(&ray, &hr, &shadow, &attenuation);
+                                // no BSDF sample finds the delta lights
+                                let weight = if ls.delta {
+                                    1.0
+                                } else {
+                                    power_heuristic(ls.pdf, pdf.value(&shadow.direction()))
+                                };
+
+                                color += throughput * f * ls.radiance * weight / ls.pdf;
+                            }
+                        }
+                    }
+
+                    let scattered = Ray::new(hr.get_p(), pdf.generate(), ray.time());
+                    let pdf_val = pdf.value(&scattered.direction());
+                    // a direction the material can't scatter to (a reflection
+                    // below the surface) carries no light
+                    if pdf_val <= 0.0 {
+                        break;
+                    }
+
+                    throughput = throughput
+                        * hr.material.scattering(&ray, &hr, &scattered, &attenuation)
+                        / pdf_val;
+                    ray = scattered;
+                    bsdf_pdf = Some(pdf_val);
+                }
+            }
+
+            if depth + 1 >= self.rr_min_depth {
+                // survive with a probability following the throughput, capped so
+                // that bright paths still end
+                let survival = throughput.max_component().min(0.95);
+                if random_double() >= survival {
+                    break;
+                }
+                throughput /= survival;
+            }
+        }
+
+        color
+    }
+}
+
+impl Integrator for PathTracer {
+    fn ray_color(&self, r: &Ray, scene: &Scene) -> Color {
+        let mut sample = self.radiance(
+            r,
+            scene.background.as_ref(),
+            &scene.world,
+            &scene.lights,
+            &scene.media,
+        );
+        if let Some(max) = self.max_luminance {
+            let luminance = sample.luminance();
+            if luminance > max {
+                sample *= max / luminance;
+            }
+        }
+
+        sample
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::geometry::aarect::XzRect;
+    use crate::geometry::emitting::Emitting;
+    use crate::geometry::flip_normals::FlipNormals;
+    use crate::geometry::sphere::Sphere;
+    use crate::lights::area::AreaLight;
+    use crate::lights::{Light, LightSamplerKind};
+    use crate::materials::diffuse_light::DiffuseLight;
+    use crate::materials::lambertian::Lambertian;
+    use crate::materials::Material;
+    use crate::scene::description::SceneDescription;
+    use crate::scene::SceneKind;
+    use crate::tools::{hash_seed, reseed};
+    use crate::vec3::{Point3, Vec3};
+
+    #[test]
+    fn test_direct_lighting_converges() {
+        let rect = |k: f64, half: f64, material: Arc<dyn Material>| XzRect {
+            x0: -half,
+            x1: half,
+            z0: -half,
+            z1: half,
+            k,
+            material,
+        };
+        let emit: Arc<dyn Material> = Arc::new(DiffuseLight::from(Color::new(4.0, 4.0, 4.0)));
+        let floor: Arc<dyn Material> = Arc::new(Lambertian::from(Color::new(0.5, 0.5, 0.5)));
+
+        // a floor lit by a 2x2 square facing down, one unit above it
+        let square: Arc<dyn Hittable> = Arc::new(FlipNormals::new(rect(1.0, 1.0, emit.clone())));
+        let world: Vec<Box<dyn Hittable>> = vec![
+            Box::new(rect(0.0, 100.0, floor)),
+            Box::new(Emitting::new(square.clone(), 0)),
+        ];
+        let light = Lights::new(
+            vec![Box::new(AreaLight::new(square, emit.emission(), 4.0))],
+            LightSamplerKind::Uniform,
+            1.0,
+            0.0,
+            0.0,
+        );
+
+        // form factor from the point below the centre, as four unit squares
+        // with a corner above it, times the albedo and the radiance
+        let a = 1.0 / 2f64.sqrt();
+        let corner = 2.0 * a * a.atan() / (2.0 * std::f64::consts::PI);
+        let expected = 0.5 * 4.0 * 4.0 * corner;
+
+        let r = Ray::new(Point3::new(0.0, 0.5, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let integrator = PathTracer {
+            max_depth: 2,
+            rr_min_depth: 2,
+            max_luminance: None,
+        };
+        let n = 100_000;
+        for lights in [&light, &Lights::default()] {
+            reseed(hash_seed(&[3]));
+            let mut sum = 0.0;
+            for _ in 0..n {
+                sum += integrator
+                    .radiance(&r, &Color::zero(), &world, lights, &Media::default())
+                    .x();
+            }
+            let estimate = sum / n as f64;
+
+            assert!(
+                (estimate - expected).abs() < 0.01 * expected,
+                "{} lights: {} != {}",
+                lights.len(),
+                estimate,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_overlapping_lights_converge() {
+        let sphere = |y: f64, radius: f64, material: Arc<dyn Material>| Sphere {
+            center: Point3::new(0.0, y, 0.0),
+            radius,
+            material,
+        };
+        let bright: Arc<dyn Material> = Arc::new(DiffuseLight::from(Color::new(4.0, 4.0, 4.0)));
+        let dim: Arc<dyn Material> = Arc::new(DiffuseLight::from(Color::new(1.0, 1.0, 1.0)));
+        let floor: Arc<dyn Material> = Arc::new(Lambertian::from(Color::new(0.5, 0.5, 0.5)));
+
+        // a small bright sphere above the floor hides the middle of a larger
+        // dim one, so the directions toward it reach both lights
+        let small: Arc<dyn Hittable> = Arc::new(sphere(2.0, 0.5, bright.clone()));
+        let large: Arc<dyn Hittable> = Arc::new(sphere(5.0, 2.0, dim.clone()));
+        let world: Vec<Box<dyn Hittable>> = vec![
+            Box::new(XzRect {
+                x0: -100.0,
+                x1: 100.0,
+                z0: -100.0,
+                z1: 100.0,
+                k: 0.0,
+                material: floor,
+            }),
+            Box::new(Emitting::new(small.clone(), 0)),
+            Box::new(Emitting::new(large.clone(), 1)),
+        ];
+        let area = |radius: f64| 4.0 * std::f64::consts::PI * radius * radius;
+        let lights = |kind: LightSamplerKind| {
+            let lights: Vec<Box<dyn Light>> = vec![
+                Box::new(AreaLight::new(small.clone(), bright.emission(), area(0.5))),
+                Box::new(AreaLight::new(large.clone(), dim.emission(), area(2.0))),
+            ];
+            Lights::new(lights, kind, 10.0, 0.0, 0.0)
+        };
+
+        // irradiance of the caps of the spheres, the squared sines of their
+        // half angles from the point below them, times the albedo
+        let (small_cap, large_cap) = (0.25f64 / 4.0, 0.16);
+        let expected = 0.5 * (4.0 * small_cap + 1.0 * (large_cap - small_cap));
+
+        let r = Ray::new(Point3::new(0.0, 0.5, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let integrator = PathTracer {
+            max_depth: 2,
+            rr_min_depth: 2,
+            max_luminance: None,
+        };
+        let n = 100_000;
+        for kind in [
+            LightSamplerKind::Uniform,
+            LightSamplerKind::Power,
+            LightSamplerKind::Tree,
+        ] {
+            reseed(hash_seed(&[kind as u64]));
+            let lights = lights(kind);
+            let mut sum = 0.0;
+            for _ in 0..n {
+                sum += integrator
+                    .radiance(&r, &Color::zero(), &world, &lights, &Media::default())
+                    .x();
+            }
+            let estimate = sum / n as f64;
+
+            assert!(
+                (estimate - expected).abs() < 0.01 * expected,
+                "{}: {} != {}",
+                kind,
+                estimate,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_russian_roulette_is_unbiased() {
+        reseed(hash_seed(&[17]));
+        let scene = SceneDescription::from_kind(SceneKind::CornellBox, false, "")
+            .build()
+            .unwrap();
+        let max_depth = 8;
+
+        // mean and standard error of the radiance seen at the centre of the
+        // image, with and without Russian roulette
+        let estimate = |rr_min_depth: u32| {
+            let integrator = PathTracer {
+                max_depth,
+                rr_min_depth,
+                max_luminance: None,
+            };
+            let n = 20_000;
+            let (mut sum, mut sum_squares) = (0.0, 0.0);
+            for _ in 0..n {
+                let r = scene.camera.get_ray(0.5, 0.5);
+                let l = integrator.ray_color(&r, &scene).luminance();
+                sum += l;
+                sum_squares += l * l;
+            }
+            let mean = sum / n as f64;
+
+            (
+                mean,
+                ((sum_squares / n as f64 - mean * mean) / n as f64).sqrt(),
+            )
+        };
+
+        let (full, full_error) = estimate(max_depth);
+        let (rr, rr_error) = estimate(1);
+        let error = (full_error * full_error + rr_error * rr_error).sqrt();
+        assert!((full - rr).abs() < 4.0 * error, "{} != {}", full, rr);
+    }
+}
@@ -0,0 +1,65 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::camera::ray::Ray;
+use crate::geometry::aabb::Aabb;
+use crate::hittable::Hittable;
+use crate::vec3::{Color, Point3, Vec3};
+
+use super::{Light, LightSample};
+
+// An emissive object of the world, sampled with its `random` and `pdf_value`.
+// The radiance is the one of its material where the sample hits it, so
+// textured emitters are lit correctly.
+pub struct AreaLight {
+    shape: Arc<dyn Hittable>,
+    // average radiance of the front faces
+    emission: Color,
+    area: f64,
+}
+
+impl AreaLight {
+    pub fn new(shape: Arc<dyn Hittable>, emission: Color, area: f64) -> AreaLight {
+        AreaLight {
+            shape,
+            emission,
+            area,
+        }
+    }
+}
+
+impl Light for AreaLight {
+    fn sample(&self, origin: &Point3, time: f64) -> Option<LightSample> {
+        let direction = self.shape.random(origin);
+        let pdf = self.shape.pdf_value(origin, &direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        let ray = Ray::new(*origin, direction, time);
+        let hr = self.shape.hit(&ray, 0.001, f64::INFINITY)?;
+        let radiance = hr
+            .material
+            .emitted(&ray, &hr, hr.get_u(), hr.get_v(), &hr.get_p());
+
+        Some(LightSample {
+            direction: direction.to_unit_vector(),
+            distance: hr.get_t() * direction.length(),
+            radiance,
+            pdf,
+            delta: false,
+        })
+    }
+
+    fn pdf(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.shape.pdf_value(origin, direction)
+    }
+
+    fn power(&self, _scene_radius: f64) -> Color {
+        PI * self.area * self.emission
+    }
+
+    fn bounds(&self, time0: f64, time1: f64) -> Option<Aabb> {
+        self.shape.bounding_box(time0, time1)
+    }
+}
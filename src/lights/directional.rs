@@ -0,0 +1,44 @@
+use std::f64::consts::PI;
+
+use crate::vec3::{Color, Point3, Vec3};
+
+use super::{Light, LightSample};
+
+// A distant light arriving from a single direction, `irradiance` is received
+// by the surfaces facing it
+pub struct DirectionalLight {
+    // toward the light
+    to_light: Vec3,
+    irradiance: Color,
+}
+
+impl DirectionalLight {
+    // The light travels along `direction`
+    pub fn new(direction: Vec3, irradiance: Color) -> DirectionalLight {
+        DirectionalLight {
+            to_light: -direction.to_unit_vector(),
+            irradiance,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _origin: &Point3, _time: f64) -> Option<LightSample> {
+        Some(LightSample {
+            direction: self.to_light,
+            distance: f64::INFINITY,
+            radiance: self.irradiance,
+            pdf: 1.0,
+            delta: true,
+        })
+    }
+
+    fn pdf(&self, _origin: &Point3, _direction: &Vec3) -> f64 {
+        0.0
+    }
+
+    // Through the disk of the scene facing the light
+    fn power(&self, scene_radius: f64) -> Color {
+        PI * scene_radius * scene_radius * self.irradiance
+    }
+}
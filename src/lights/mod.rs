@@ -0,0 +1,375 @@
+// Lights sampled by the path tracer for next event estimation: emissive
+// objects of the world, lights without any geometry and the lights at infinity
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use clap::{ArgEnum, PossibleValue};
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::aabb::Aabb;
+use crate::pdf::distribution::Distribution1D;
+use crate::tools::{random_double, random_usize_range};
+use crate::vec3::{Color, Point3, Vec3};
+
+use tree::LightTree;
+
+pub mod area;
+pub mod directional;
+pub mod point;
+pub mod spot;
+pub mod tree;
+
+// How the light sampled at a bounce is chosen
+#[derive(ArgEnum, Serialize, Deserialize, Debug, Default, PartialEq, Clone, Copy)]
+pub enum LightSamplerKind {
+    Uniform,
+    // following the emitted power
+    Power,
+    // following the power and the distance, with a BVH over the lights
+    #[default]
+    Tree,
+}
+
+impl FromStr for LightSamplerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        for variant in Self::value_variants() {
+            if variant.to_possible_value().unwrap().matches(s, false) {
+                return Ok(*variant);
+            }
+        }
+        Err(format!("Invalid variant: {}", s))
+    }
+}
+
+impl Display for LightSamplerKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .fmt(f)
+    }
+}
+
+impl LightSamplerKind {
+    pub fn possible_values() -> impl Iterator<Item = PossibleValue<'static>> {
+        Self::value_variants()
+            .iter()
+            .filter_map(ArgEnum::to_possible_value)
+    }
+}
+
+// A direction toward a light from a point of the scene
+#[derive(Debug, Clone, Copy)]
+pub struct LightSample {
+    // unit vector
+    pub direction: Vec3,
+    // to the sampled point of the light, infinite for the lights at infinity
+    pub distance: f64,
+    // arriving from the light, the irradiance for the delta lights
+    pub radiance: Color,
+    // with respect to the solid angle, a mere probability for the delta lights
+    pub pdf: f64,
+    // a point or a single direction, no BSDF sample finds it
+    pub delta: bool,
+}
+
+pub trait Light: Send + Sync {
+    fn sample(&self, origin: &Point3, time: f64) -> Option<LightSample>;
+
+    // Density of `sample` drawing `direction` from `origin`, 0 for the delta
+    // lights
+    fn pdf(&self, origin: &Point3, direction: &Vec3) -> f64;
+
+    // Emitted power, `scene_radius` bounds the scene for the lights at infinity
+    fn power(&self, scene_radius: f64) -> Color;
+
+    // Over the shutter interval, None for the lights at infinity
+    fn bounds(&self, _time0: f64, _time1: f64) -> Option<Aabb> {
+        None
+    }
+}
+
+impl Light for Arc<dyn Light> {
+    fn sample(&self, origin: &Point3, time: f64) -> Option<LightSample> {
+        self.as_ref().sample(origin, time)
+    }
+
+    fn pdf(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.as_ref().pdf(origin, direction)
+    }
+
+    fn power(&self, scene_radius: f64) -> Color {
+        self.as_ref().power(scene_radius)
+    }
+
+    fn bounds(&self, time0: f64, time1: f64) -> Option<Aabb> {
+        self.as_ref().bounds(time0, time1)
+    }
+}
+
+enum Selection {
+    Uniform,
+    Power(Distribution1D),
+    Tree(LightTree),
+}
+
+// The lights of a scene, one of them is chosen for each sample
+pub struct Lights {
+    lights: Vec<Box<dyn Light>>,
+    selection: Selection,
+}
+
+impl Default for Lights {
+    fn default() -> Lights {
+        Lights::new(Vec::new(), LightSamplerKind::Uniform, 0.0, 0.0, 0.0)
+    }
+}
+
+impl Lights {
+    // `scene_radius` bounds the scene, for the power of the lights at infinity,
+    // and the moving lights are bounded over the shutter interval `time0..time1`
+    pub fn new(
+        lights: Vec<Box<dyn Light>>,
+        kind: LightSamplerKind,
+        scene_radius: f64,
+        time0: f64,
+        time1: f64,
+    ) -> Lights {
+        let powers: Vec<_> = lights
+            .iter()
+            .map(|light| light.power(scene_radius).luminance())
+            .collect();
+        let selection = match kind {
+            _ if lights.is_empty() => Selection::Uniform,
+            LightSamplerKind::Uniform => Selection::Uniform,
+            LightSamplerKind::Power => Selection::Power(Distribution1D::new(powers)),
+            LightSamplerKind::Tree => {
+                Selection::Tree(LightTree::new(&lights, &powers, time0, time1))
+            }
+        };
+
+        Lights { lights, selection }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    // Light chosen for the point `p`, and the probability to choose it
+    fn choose(&self, p: &Point3) -> Option<(usize, f64)> {
+        if self.lights.is_empty() {
+            return None;
+        }
+
+        match &self.selection {
+            Selection::Uniform => Some((
+                random_usize_range(0, self.lights.len() - 1),
+                1.0 / self.lights.len() as f64,
+            )),
+            Selection::Power(distribution) => Some(distribution.sample_discrete(random_double())),
+            Selection::Tree(tree) => tree.choose(p),
+        }
+    }
+
+    // A sample of one of the lights, its density includes the choice of the light
+    pub fn sample(&self, origin: &Point3, time: f64) -> Option<LightSample> {
+        let (light, probability) = self.choose(origin)?;
+        let mut sample = self.lights[light].sample(origin, time)?;
+        sample.pdf *= probability;
+
+        Some(sample)
+    }
+
+    // Density of `sample` drawing `direction` from `origin` with `light`, for a
+    // direction known to reach that light first: the other lights behind it
+    // are hidden
+    pub fn light_pdf(&self, light: usize, origin: &Point3, direction: &Vec3) -> f64 {
+        // not sampled at all
+        if light >= self.lights.len() {
+            return 0.0;
+        }
+
+        let probability = match &self.selection {
+            Selection::Uniform => 1.0 / self.lights.len() as f64,
+            Selection::Power(distribution) => distribution.discrete_pdf(light),
+            Selection::Tree(tree) => tree.probability(light, origin),
+        };
+
+        probability * self.lights[light].pdf(origin, direction)
+    }
+
+    // Density of `sample` drawing `direction` from `origin`, over all the lights
+    pub fn pdf(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        if self.lights.is_empty() {
+            return 0.0;
+        }
+
+        match &self.selection {
+            Selection::Uniform => {
+                let sum: f64 = self.lights.iter().map(|l| l.pdf(origin, direction)).sum();
+                sum / self.lights.len() as f64
+            }
+            Selection::Power(distribution) => self
+                .lights
+                .iter()
+                .enumerate()
+                .map(|(i, l)| distribution.discrete_pdf(i) * l.pdf(origin, direction))
+                .sum(),
+            Selection::Tree(tree) => tree.pdf(&self.lights, origin, direction),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::moving_sphere::MovingSphere;
+    use crate::geometry::sphere::Sphere;
+    use crate::materials::diffuse_light::DiffuseLight;
+    use crate::materials::Material;
+    use crate::tools::{hash_seed, reseed};
+    use area::AreaLight;
+    use point::PointLight;
+
+    // A 16x16 grid of point lights above the origin, a few of them bright
+    fn grid() -> Vec<Box<dyn Light>> {
+        (0..256)
+            .map(|i| {
+                let intensity = if i % 37 == 0 { 500.0 } else { 1.0 };
+                Box::new(PointLight {
+                    position: Point3::new((i % 16) as f64 - 7.5, 1.0, (i / 16) as f64 - 7.5),
+                    intensity: Color::new(intensity, intensity, intensity),
+                }) as Box<dyn Light>
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_selection_follows_the_power_and_distance() {
+        let p = Point3::new(0.3, 0.0, -0.2);
+        let irradiance = |light: &LightSample| light.radiance.x() * light.direction.y();
+        let exact: f64 = grid()
+            .iter()
+            .map(|l| irradiance(&l.sample(&p, 0.0).unwrap()))
+            .sum();
+
+        // mean and variance of the irradiance estimates at p
+        let estimate = |kind: LightSamplerKind| {
+            reseed(hash_seed(&[kind as u64]));
+            let lights = Lights::new(grid(), kind, 10.0, 0.0, 0.0);
+            let n = 20_000;
+            let (mut sum, mut sum_squares) = (0.0, 0.0);
+            for _ in 0..n {
+                let sample = lights.sample(&p, 0.0).unwrap();
+                let e = irradiance(&sample) / sample.pdf;
+                sum += e;
+                sum_squares += e * e;
+            }
+            let mean = sum / n as f64;
+
+            (mean, sum_squares / n as f64 - mean * mean)
+        };
+
+        let (uniform, uniform_variance) = estimate(LightSamplerKind::Uniform);
+        let (power, power_variance) = estimate(LightSamplerKind::Power);
+        let (tree, tree_variance) = estimate(LightSamplerKind::Tree);
+        for (mean, variance) in [
+            (uniform, uniform_variance),
+            (power, power_variance),
+            (tree, tree_variance),
+        ] {
+            let error = (variance / 20_000.0).sqrt();
+            assert!((mean - exact).abs() < 4.0 * error, "{} != {}", mean, exact);
+        }
+        // the bright lights dominate, the nearby ones among them most
+        assert!(power_variance < 0.5 * uniform_variance);
+        assert!(tree_variance < 0.5 * power_variance);
+    }
+
+    #[test]
+    fn test_tree_pdf_matches_its_samples() {
+        // small spheres in a plane above p, no ray from p goes through two of them
+        let emission = Color::new(1.0, 2.0, 3.0);
+        let emit: Arc<dyn Material> = Arc::new(DiffuseLight::from(emission));
+        let lights: Vec<Box<dyn Light>> = (0..64)
+            .map(|i| {
+                let radius = 0.1 + 0.02 * (i % 5) as f64;
+                let sphere = Sphere {
+                    center: Point3::new((i % 8) as f64 * 2.0, 3.0, (i / 8) as f64 * 2.0),
+                    radius,
+                    material: emit.clone(),
+                };
+                let area = 4.0 * std::f64::consts::PI * radius * radius;
+                Box::new(AreaLight::new(Arc::new(sphere), emission, area)) as Box<dyn Light>
+            })
+            .collect();
+        let lights = Lights::new(lights, LightSamplerKind::Tree, 10.0, 0.0, 0.0);
+
+        reseed(hash_seed(&[5]));
+        let p = Point3::new(5.0, 0.0, 6.0);
+        for _ in 0..1000 {
+            let sample = lights.sample(&p, 0.0).unwrap();
+            let pdf = lights.pdf(&p, &sample.direction);
+            assert!(
+                (pdf - sample.pdf).abs() < 1E-9 * sample.pdf,
+                "{} != {}",
+                pdf,
+                sample.pdf
+            );
+        }
+        assert_eq!(lights.pdf(&p, &Vec3::new(0.0, -1.0, 0.0)), 0.0);
+
+        // and so does the density of each light, found from its leaf
+        for _ in 0..1000 {
+            let (light, probability) = lights.choose(&p).unwrap();
+            let sample = lights.lights[light].sample(&p, 0.0).unwrap();
+            let pdf = lights.light_pdf(light, &p, &sample.direction);
+            assert!(
+                (pdf - probability * sample.pdf).abs() < 1E-9 * pdf,
+                "{} != {}",
+                pdf,
+                probability * sample.pdf
+            );
+        }
+    }
+
+    #[test]
+    fn test_moving_lights_are_bounded_over_the_shutter() {
+        let emission = Color::new(1.0, 1.0, 1.0);
+        let emit: Arc<dyn Material> = Arc::new(DiffuseLight::from(emission));
+        // a sphere moving along x during the shutter, and a point light apart
+        let sphere = MovingSphere {
+            center0: Point3::new(0.0, 5.0, 0.0),
+            center1: Point3::new(10.0, 5.0, 0.0),
+            time0: 0.0,
+            time1: 1.0,
+            radius: 1.0,
+            material: emit,
+        };
+        let lights: Vec<Box<dyn Light>> = vec![
+            Box::new(AreaLight::new(
+                Arc::new(sphere),
+                emission,
+                4.0 * std::f64::consts::PI,
+            )),
+            Box::new(PointLight {
+                position: Point3::new(-10.0, 5.0, 0.0),
+                intensity: emission,
+            }),
+        ];
+        let lights = Lights::new(lights, LightSamplerKind::Tree, 10.0, 0.0, 1.0);
+
+        // toward where the sphere is at the end of the shutter
+        let p = Point3::zero();
+        let direction = Vec3::new(10.0, 5.0, 0.0);
+        assert!(lights.pdf(&p, &direction) > 0.0);
+        assert!(lights.light_pdf(0, &p, &direction) > 0.0);
+    }
+}
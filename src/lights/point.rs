@@ -0,0 +1,42 @@
+use std::f64::consts::PI;
+
+use crate::geometry::aabb::Aabb;
+use crate::vec3::{Color, Point3, Vec3};
+
+use super::{Light, LightSample};
+
+// Emits the same intensity, in W/sr, in every direction
+pub struct PointLight {
+    pub position: Point3,
+    pub intensity: Color,
+}
+
+impl Light for PointLight {
+    fn sample(&self, origin: &Point3, _time: f64) -> Option<LightSample> {
+        let to_light = self.position - *origin;
+        let distance = to_light.length();
+        if distance <= 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            direction: to_light / distance,
+            distance,
+            radiance: self.intensity / (distance * distance),
+            pdf: 1.0,
+            delta: true,
+        })
+    }
+
+    fn pdf(&self, _origin: &Point3, _direction: &Vec3) -> f64 {
+        0.0
+    }
+
+    fn power(&self, _scene_radius: f64) -> Color {
+        4.0 * PI * self.intensity
+    }
+
+    fn bounds(&self, _time0: f64, _time1: f64) -> Option<Aabb> {
+        Some(Aabb::new(self.position, self.position))
+    }
+}
@@ -0,0 +1,122 @@
+use std::f64::consts::PI;
+
+use crate::geometry::aabb::Aabb;
+use crate::vec3::{Color, Point3, Vec3};
+
+use super::{Light, LightSample};
+
+// A point light restricted to a cone: full intensity up to `cos_falloff`
+// from its axis, fading smoothly to nothing at `cos_total`
+pub struct SpotLight {
+    position: Point3,
+    direction: Vec3,
+    intensity: Color,
+    cos_total: f64,
+    cos_falloff: f64,
+}
+
+impl SpotLight {
+    // Angles in degrees, from the axis of the cone
+    pub fn new(
+        position: Point3,
+        direction: Vec3,
+        intensity: Color,
+        angle: f64,
+        falloff_start: f64,
+    ) -> SpotLight {
+        SpotLight {
+            position,
+            direction: direction.to_unit_vector(),
+            intensity,
+            cos_total: angle.to_radians().cos(),
+            cos_falloff: falloff_start.min(angle).to_radians().cos(),
+        }
+    }
+
+    // Fraction of the intensity emitted toward the unit vector `w`
+    fn falloff(&self, w: &Vec3) -> f64 {
+        let cos_theta = w.dot(self.direction);
+        if cos_theta < self.cos_total {
+            return 0.0;
+        }
+        if cos_theta >= self.cos_falloff {
+            return 1.0;
+        }
+
+        let delta = (cos_theta - self.cos_total) / (self.cos_falloff - self.cos_total);
+        delta.powi(4)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, origin: &Point3, _time: f64) -> Option<LightSample> {
+        let to_light = self.position - *origin;
+        let distance = to_light.length();
+        if distance <= 0.0 {
+            return None;
+        }
+        let direction = to_light / distance;
+        let falloff = self.falloff(&-direction);
+        if falloff <= 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: falloff * self.intensity / (distance * distance),
+            pdf: 1.0,
+            delta: true,
+        })
+    }
+
+    fn pdf(&self, _origin: &Point3, _direction: &Vec3) -> f64 {
+        0.0
+    }
+
+    // The falloff is counted as a linear ramp of the cosine
+    fn power(&self, _scene_radius: f64) -> Color {
+        2.0 * PI * self.intensity * (1.0 - 0.5 * (self.cos_falloff + self.cos_total))
+    }
+
+    fn bounds(&self, _time0: f64, _time1: f64) -> Option<Aabb> {
+        Some(Aabb::new(self.position, self.position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cone_falloff() {
+        let spot = SpotLight::new(
+            Point3::new(0.0, 2.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            Color::new(8.0, 8.0, 8.0),
+            30.0,
+            20.0,
+        );
+
+        // right below, in the falloff and outside the cone
+        let below = spot.sample(&Point3::zero(), 0.0).unwrap();
+        assert_eq!(below.radiance, Color::new(2.0, 2.0, 2.0));
+        assert_eq!(below.direction, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(below.distance, 2.0);
+
+        // 8 / distance^2 without the falloff
+        let angle = 25f64.to_radians();
+        let faded = spot
+            .sample(&Point3::new(2.0 * angle.tan(), 0.0, 0.0), 0.0)
+            .unwrap();
+        let full = 2.0 * angle.cos() * angle.cos();
+        assert!(faded.radiance.x() > 0.0 && faded.radiance.x() < full);
+
+        let outside = 2.0 * 35f64.to_radians().tan();
+        assert!(spot.sample(&Point3::new(0.0, 0.0, outside), 0.0).is_none());
+
+        // between a cone of 20 and one of 30 degrees
+        let power = spot.power(0.0).x() / (2.0 * PI * 8.0);
+        assert!(power > 1.0 - 20f64.to_radians().cos() && power < 1.0 - 30f64.to_radians().cos());
+    }
+}
@@ -0,0 +1,246 @@
+// Bounding volume hierarchy over the lights, walked down from the root toward
+// the lights that matter most for the shaded point: each child is chosen with
+// a probability following its power over its squared distance. The lights
+// at infinity have no bounds and are chosen apart, following their power.
+use crate::camera::ray::Ray;
+use crate::geometry::aabb::Aabb;
+use crate::pdf::distribution::Distribution1D;
+use crate::tools::random_double;
+use crate::vec3::{Point3, Vec3};
+
+use super::Light;
+
+enum Content {
+    Light(usize),
+    // the first child follows its parent
+    Children { second: usize },
+}
+
+struct Node {
+    bounds: Aabb,
+    power: f64,
+    content: Content,
+    parent: Option<usize>,
+}
+
+impl Node {
+    // Contribution estimated from `p`, the distance is not taken below the
+    // size of the node not to favour the ones containing `p`
+    fn importance(&self, p: &Point3) -> f64 {
+        let half_diagonal = 0.5 * (self.bounds.max() - self.bounds.min()).length();
+        let distance_squared = (self.bounds.centroid() - *p)
+            .length_squared()
+            .max(half_diagonal * half_diagonal)
+            .max(f64::EPSILON);
+
+        self.power / distance_squared
+    }
+}
+
+pub struct LightTree {
+    nodes: Vec<Node>,
+    // node of each light, None for the lights at infinity
+    leaves: Vec<Option<usize>>,
+    // the lights at infinity and their distribution, when there are some
+    infinite: Vec<usize>,
+    infinite_distribution: Option<Distribution1D>,
+    // of choosing one of the lights at infinity rather than one of the tree
+    infinite_probability: f64,
+}
+
+impl LightTree {
+    // `powers` are the luminances of the powers of the lights, and the bounds of
+    // the moving ones span `time0..time1`
+    pub fn new(lights: &[Box<dyn Light>], powers: &[f64], time0: f64, time1: f64) -> LightTree {
+        let (mut bounded, mut infinite) = (Vec::new(), Vec::new());
+        for (i, light) in lights.iter().enumerate() {
+            match light.bounds(time0, time1) {
+                Some(bounds) => bounded.push((i, bounds, powers[i])),
+                None => infinite.push(i),
+            }
+        }
+
+        let mut tree = LightTree {
+            nodes: Vec::new(),
+            leaves: vec![None; lights.len()],
+            infinite_distribution: (!infinite.is_empty())
+                .then(|| Distribution1D::new(infinite.iter().map(|&i| powers[i]).collect())),
+            infinite_probability: 0.0,
+            infinite: Vec::new(),
+        };
+        if !bounded.is_empty() {
+            tree.build(&mut bounded, None);
+        }
+
+        let infinite_power: f64 = infinite.iter().map(|&i| powers[i]).sum();
+        tree.infinite_probability = match (infinite.is_empty(), tree.nodes.first()) {
+            (true, _) => 0.0,
+            (false, None) => 1.0,
+            (false, Some(root)) if infinite_power + root.power > 0.0 => {
+                infinite_power / (infinite_power + root.power)
+            }
+            // nothing emits, as likely as the lights of the tree
+            (false, Some(_)) => infinite.len() as f64 / lights.len() as f64,
+        };
+        tree.infinite = infinite;
+
+        tree
+    }
+
+    // Lights split at the middle of their centroids along the widest axis
+    fn build(&mut self, lights: &mut [(usize, Aabb, f64)], parent: Option<usize>) -> usize {
+        let idx = self.nodes.len();
+        let bounds = lights
+            .iter()
+            .skip(1)
+            .fold(lights[0].1, |b, (_, l, _)| Aabb::surrounding_box(&b, l));
+        let power = lights.iter().map(|(_, _, power)| power).sum();
+
+        if let [(light, _, _)] = lights {
+            self.nodes.push(Node {
+                bounds,
+                power,
+                content: Content::Light(*light),
+                parent,
+            });
+            self.leaves[*light] = Some(idx);
+            return idx;
+        }
+
+        self.nodes.push(Node {
+            bounds,
+            power,
+            content: Content::Children { second: 0 },
+            parent,
+        });
+
+        let (min, max) = lights
+            .iter()
+            .fold((Vec3::max(), Vec3::min()), |(min, max), (_, b, _)| {
+                let c = b.centroid();
+                (
+                    Vec3::new(min.x().min(c.x()), min.y().min(c.y()), min.z().min(c.z())),
+                    Vec3::new(max.x().max(c.x()), max.y().max(c.y()), max.z().max(c.z())),
+                )
+            });
+        let extent = max - min;
+        let axis = (0..3)
+            .max_by(|&a, &b| extent[a].total_cmp(&extent[b]))
+            .unwrap_or(0);
+        lights.sort_by(|a, b| a.1.centroid()[axis].total_cmp(&b.1.centroid()[axis]));
+
+        let mid = lights.len() / 2;
+        self.build(&mut lights[..mid], Some(idx));
+        let second = self.build(&mut lights[mid..], Some(idx));
+        self.nodes[idx].content = Content::Children { second };
+
+        idx
+    }
+
+    // Probability of the first child of `idx`, seen from `p`
+    fn first_probability(&self, idx: usize, second: usize, p: &Point3) -> f64 {
+        let first = self.nodes[idx + 1].importance(p);
+        let second = self.nodes[second].importance(p);
+
+        if first + second > 0.0 {
+            first / (first + second)
+        } else {
+            0.5
+        }
+    }
+
+    // A light for the point `p`, and the probability to choose it
+    pub fn choose(&self, p: &Point3) -> Option<(usize, f64)> {
+        if let Some(distribution) = &self.infinite_distribution {
+            if random_double() < self.infinite_probability {
+                let (i, probability) = distribution.sample_discrete(random_double());
+                return Some((self.infinite[i], self.infinite_probability * probability));
+            }
+        }
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let mut idx = 0;
+        let mut probability = 1.0 - self.infinite_probability;
+        loop {
+            match self.nodes[idx].content {
+                Content::Light(light) => return Some((light, probability)),
+                Content::Children { second } => {
+                    let first = self.first_probability(idx, second, p);
+                    if random_double() < first {
+                        idx += 1;
+                        probability *= first;
+                    } else {
+                        idx = second;
+                        probability *= 1.0 - first;
+                    }
+                }
+            }
+        }
+    }
+
+    // Probability of `choose` to choose `light` for the point `p`, found from
+    // its leaf up to the root
+    pub fn probability(&self, light: usize, p: &Point3) -> f64 {
+        let mut idx = match self.leaves[light] {
+            Some(idx) => idx,
+            None => {
+                let i = self.infinite.iter().position(|&l| l == light);
+                return match (i, &self.infinite_distribution) {
+                    (Some(i), Some(distribution)) => {
+                        self.infinite_probability * distribution.discrete_pdf(i)
+                    }
+                    _ => 0.0,
+                };
+            }
+        };
+
+        let mut probability = 1.0 - self.infinite_probability;
+        while let Some(parent) = self.nodes[idx].parent {
+            if let Content::Children { second } = self.nodes[parent].content {
+                let first = self.first_probability(parent, second, p);
+                probability *= if idx == second { 1.0 - first } else { first };
+            }
+            idx = parent;
+        }
+
+        probability
+    }
+
+    // Density of sampling `direction` from `origin` with `choose` and the
+    // sample of the light. Only the nodes on the way of the ray are visited.
+    pub fn pdf(&self, lights: &[Box<dyn Light>], origin: &Point3, direction: &Vec3) -> f64 {
+        let mut pdf = 0.0;
+        if let Some(distribution) = &self.infinite_distribution {
+            for (i, &light) in self.infinite.iter().enumerate() {
+                pdf += self.infinite_probability
+                    * distribution.discrete_pdf(i)
+                    * lights[light].pdf(origin, direction);
+            }
+        }
+        if self.nodes.is_empty() {
+            return pdf;
+        }
+
+        let ray = Ray::new(*origin, *direction, 0.0);
+        let mut stack = vec![(0, 1.0 - self.infinite_probability)];
+        while let Some((idx, probability)) = stack.pop() {
+            let node = &self.nodes[idx];
+            if probability <= 0.0 || !node.bounds.hit(&ray, 0.001, f64::INFINITY) {
+                continue;
+            }
+
+            match node.content {
+                Content::Light(light) => pdf += probability * lights[light].pdf(origin, direction),
+                Content::Children { second } => {
+                    let first = self.first_probability(idx, second, origin);
+                    stack.push((idx + 1, probability * first));
+                    stack.push((second, probability * (1.0 - first)));
+                }
+            }
+        }
+
+        pdf
+    }
+}
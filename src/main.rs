@@ -7,12 +7,18 @@
 use clap::Parser;
 use indicatif::HumanDuration;
 
-use std::time::Instant;
+use std::process;
+use std::time::{Duration, Instant};
 
+mod background;
 mod camera;
 mod geometry;
 mod hittable;
+mod integrator;
+mod lights;
 mod materials;
+mod matrix;
+mod media;
 mod onb;
 mod pdf;
 mod renderer;
@@ -21,8 +27,23 @@ mod texture;
 mod tools;
 mod vec3;
 
-use renderer::{render, RendererKind};
-use scene::{Scene, SceneKind};
+use geometry::{bvh::BvhKind, traversal};
+use integrator::IntegratorKind;
+use lights::LightSamplerKind;
+use renderer::{
+    checkpoint::{checkpoint_path, Checkpoint},
+    denoise::denoise,
+    install_interrupt_handler, render,
+    tiles::TileOrder,
+    tonemap::{Oetf, ToneMapper, ToneMapping},
+    RenderOptions, RendererKind,
+};
+use scene::{
+    description::{EnvironmentDescription, SceneDescription, SkyDescription},
+    Overrides, Scene, SceneKind,
+};
+
+const EARTH_TEXTURE: &str = "data/1k/earth.jpg";
 
 #[derive(Parser, Debug)]
 #[clap(about, version, author)]
@@ -32,13 +53,33 @@ struct Args {
     output: String,
 
     /// Name of the renderer to use
-    #[clap(short, long, arg_enum, help_heading = "CONFIG", default_value = "parallel-crossbeam")]
+    #[clap(
+        short,
+        long,
+        arg_enum,
+        help_heading = "CONFIG",
+        default_value = "parallel-crossbeam"
+    )]
     renderer: RendererKind,
 
     /// Name of the scene
-    #[clap(short, long, arg_enum, help_heading = "CONFIG", default_value = "cornell-box-glass-sphere")]
+    #[clap(
+        short,
+        long,
+        arg_enum,
+        help_heading = "CONFIG",
+        default_value = "cornell-box-glass-sphere"
+    )]
     scene: SceneKind,
 
+    /// Scene file (RON) to render instead of a built-in scene
+    #[clap(long, help_heading = "CONFIG")]
+    scene_file: Option<String>,
+
+    /// Export the selected built-in scene to a scene file (RON) and exit
+    #[clap(long, help_heading = "CONFIG")]
+    export_scene: Option<String>,
+
     /// Make objects move
     #[clap(short, long)]
     moving: bool,
@@ -54,24 +95,254 @@ struct Args {
     /// Number of samples per pixel
     #[clap(short = 'S', long, help_heading = "OVERRIDE")]
     samples_per_pixel: Option<u32>,
+
+    /// BVH builder
+    #[clap(long, arg_enum, help_heading = "OVERRIDE")]
+    bvh: Option<BvhKind>,
+
+    /// Integrator computing the samples: the path tracer or a debug view
+    #[clap(long, arg_enum, help_heading = "OVERRIDE")]
+    integrator: Option<IntegratorKind>,
+
+    /// Occlusion distance of the ambient-occlusion integrator
+    #[clap(long, help_heading = "OVERRIDE")]
+    ao_distance: Option<f64>,
+
+    /// Choice of the light sampled at each bounce: uniform, following the power, or with a light BVH
+    #[clap(long, arg_enum, help_heading = "OVERRIDE")]
+    light_sampler: Option<LightSamplerKind>,
+
+    /// Bounce from which paths may be stopped by Russian roulette
+    #[clap(long, help_heading = "OVERRIDE")]
+    rr_min_depth: Option<u32>,
+
+    /// Scale down samples brighter than this luminance to remove fireflies (biased)
+    #[clap(long, help_heading = "OVERRIDE")]
+    max_luminance: Option<f64>,
+
+    /// Light the scene with an equirectangular .hdr or .exr environment map
+    #[clap(long, help_heading = "OVERRIDE")]
+    environment: Option<String>,
+
+    /// Rotation of the environment map around the vertical axis, in degrees
+    #[clap(
+        long,
+        help_heading = "OVERRIDE",
+        default_value = "0",
+        allow_hyphen_values = true
+    )]
+    environment_rotation: f64,
+
+    /// Scale of the radiance of the environment map
+    #[clap(long, help_heading = "OVERRIDE", default_value = "1")]
+    environment_intensity: f64,
+
+    /// Light the scene with a physical sky and its sun
+    #[clap(long, help_heading = "OVERRIDE")]
+    sky: bool,
+
+    /// Elevation of the sun above the horizon, in degrees
+    #[clap(long, help_heading = "OVERRIDE", default_value = "45")]
+    sun_elevation: f64,
+
+    /// Azimuth of the sun around the vertical axis, from +x toward +z, in degrees
+    #[clap(
+        long,
+        help_heading = "OVERRIDE",
+        default_value = "0",
+        allow_hyphen_values = true
+    )]
+    sun_azimuth: f64,
+
+    /// Haziness of the sky, from 2 (very clear) to 10
+    #[clap(long, help_heading = "OVERRIDE", default_value = "3")]
+    turbidity: f64,
+
+    /// Scale of the radiance of the sky and the sun
+    #[clap(long, help_heading = "OVERRIDE", default_value = "1")]
+    sky_intensity: f64,
+
+    /// Render progressively in passes of this many samples per pixel, writing the output after each pass
+    #[clap(long, help_heading = "CONFIG")]
+    pass_samples: Option<u32>,
+
+    /// Stop rendering after this many seconds and keep the image rendered so far
+    #[clap(long, help_heading = "CONFIG")]
+    time_limit: Option<f64>,
+
+    /// Tone mapping operator for 8 bit outputs
+    #[clap(long, arg_enum, help_heading = "OUTPUT", default_value = "clamp")]
+    tonemap: ToneMapper,
+
+    /// Exposure in stops (EV) applied before tone mapping
+    #[clap(
+        long,
+        help_heading = "OUTPUT",
+        default_value = "0",
+        allow_hyphen_values = true
+    )]
+    exposure: f64,
+
+    /// White point of the reinhard-extended operator
+    #[clap(long, help_heading = "OUTPUT", default_value = "4")]
+    white: f64,
+
+    /// Transfer function applied after tone mapping
+    #[clap(long, arg_enum, help_heading = "OUTPUT", default_value = "srgb")]
+    oetf: Oetf,
+
+    /// Also write the albedo, normal, depth, object and material ids: EXR layers or sibling files
+    #[clap(long, help_heading = "OUTPUT")]
+    aovs: bool,
+
+    /// Denoise the image guided by the AOVs (which are written too) once rendered
+    #[clap(long, help_heading = "OUTPUT")]
+    denoise: bool,
+
+    /// Width and height of the tiles rendered by each job
+    #[clap(long, help_heading = "CONFIG", default_value = "16")]
+    tile_size: usize,
+
+    /// Order in which the tiles are rendered
+    #[clap(long, arg_enum, help_heading = "CONFIG", default_value = "hilbert")]
+    tile_order: TileOrder,
+
+    /// Show the render in progress in a window (needs the preview feature)
+    #[clap(long, help_heading = "CONFIG")]
+    preview: bool,
+
+    /// Write a preview frame to this directory after each pass
+    #[clap(long, help_heading = "CONFIG")]
+    preview_dump: Option<String>,
+
+    /// Seed of the scene construction and of the samples, random by default
+    #[clap(long, help_heading = "CONFIG")]
+    seed: Option<u64>,
+
+    /// Continue the render saved in the checkpoint file next to the output
+    #[clap(long, help_heading = "CONFIG")]
+    resume: bool,
+
+    /// Print BVH construction and traversal statistics
+    #[clap(long)]
+    bvh_stats: bool,
 }
 
 fn main() {
     let args = Args::parse();
 
-    let scene = Scene::new(
-        args.moving,
-        args.scene,
-        "data/1k/earth.jpg",
-        args.width,
-        args.height,
-        args.samples_per_pixel,
-    );
+    let seed = match (args.seed, args.resume) {
+        (Some(seed), _) => seed,
+        (None, true) => Checkpoint::read_seed(&checkpoint_path(&args.output)).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        }),
+        (None, false) => tools::random_u64(),
+    };
+    println!("Seed: {}", seed);
+    // random scenes, Perlin noise and BVH split axes depend on it too
+    tools::reseed(tools::hash_seed(&[seed]));
+
+    if let Some(export) = &args.export_scene {
+        let desc = SceneDescription::from_kind(args.scene, args.moving, EARTH_TEXTURE);
+        if let Err(e) = desc.save(export) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    let overrides = Overrides {
+        width: args.width,
+        height: args.height,
+        samples_per_pixel: args.samples_per_pixel,
+        bvh: args.bvh,
+        rr_min_depth: args.rr_min_depth,
+        max_luminance: args.max_luminance,
+        integrator: args.integrator,
+        light_sampler: args.light_sampler,
+        ao_distance: args.ao_distance,
+        environment: args
+            .environment
+            .as_ref()
+            .map(|filename| EnvironmentDescription {
+                filename: filename.clone(),
+                rotation: args.environment_rotation,
+                intensity: args.environment_intensity,
+            }),
+        sky: args.sky.then_some(SkyDescription {
+            elevation: args.sun_elevation,
+            azimuth: args.sun_azimuth,
+            turbidity: args.turbidity,
+            intensity: args.sky_intensity,
+        }),
+    };
+    let scene = match &args.scene_file {
+        Some(scene_file) => Scene::from_file(scene_file, &overrides),
+        None => Scene::new(args.moving, args.scene, EARTH_TEXTURE, &overrides),
+    }
+    .unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+
+    if args.bvh_stats {
+        traversal::enable_visit_counts();
+        println!("BVH builder: {}", scene.cfg.bvh);
+        for (i, stats) in scene.bvh_stats.iter().enumerate() {
+            println!("BVH #{}: {}", i, stats);
+        }
+    }
+
+    if let Err(e) = install_interrupt_handler() {
+        eprintln!("{}", e);
+    }
+    let options = RenderOptions {
+        pass_samples: args.pass_samples,
+        time_limit: args.time_limit.map(Duration::from_secs_f64),
+        resume: args.resume,
+        tone_mapping: ToneMapping {
+            operator: args.tonemap,
+            exposure: args.exposure,
+            white: args.white,
+            oetf: args.oetf,
+        },
+        seed,
+        tile_size: args.tile_size,
+        tile_order: args.tile_order,
+        preview: args.preview,
+        preview_dump: args.preview_dump.clone(),
+        aovs: args.aovs || args.denoise,
+    };
 
     let start = Instant::now();
-    render(&scene, args.renderer, &args.output);
+    let mut fb = render(&scene, args.renderer, &args.output, &options).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
     println!(
         "Time elapsed rendering  scene is: {}",
         HumanDuration(start.elapsed())
     );
+
+    // the checkpoint keeps the noisy image
+    if args.denoise {
+        let start = Instant::now();
+        if let Err(e) = denoise(&mut fb).and_then(|_| fb.save(&args.output, &options.tone_mapping))
+        {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        println!("Denoised in {}", HumanDuration(start.elapsed()));
+    }
+
+    if args.bvh_stats {
+        let stats = traversal::global_stats();
+        println!(
+            "BVH traversal: {} rays, {:.2} nodes/ray, {:.2} primitives/ray",
+            stats.rays,
+            stats.nodes_per_ray(),
+            stats.primitives_per_ray()
+        );
+    }
 }
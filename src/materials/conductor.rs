@@ -0,0 +1,192 @@
+// Rough metal with a GGX microfacet distribution and the Fresnel term of its
+// complex index of refraction
+use serde::{Deserialize, Serialize};
+
+use crate::camera::ray::Ray;
+use crate::hittable::HitRecord;
+use crate::onb::OrthoNormalBasis;
+use crate::pdf::Pdf;
+use crate::tools::random_double;
+use crate::vec3::{Color, Vec3};
+
+use super::microfacet::{fr_conductor, Ggx};
+use super::{Material, ScatterRecord};
+
+// Complex index of refraction eta + i k, sampled at 650, 550 and 450 nm for
+// the presets
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ComplexIor {
+    Gold,
+    Copper,
+    Aluminium,
+    Custom { eta: Color, k: Color },
+}
+
+impl ComplexIor {
+    pub fn eta_k(&self) -> (Color, Color) {
+        match self {
+            ComplexIor::Gold => (
+                Color::new(0.143, 0.374, 1.442),
+                Color::new(3.983, 2.386, 1.603),
+            ),
+            ComplexIor::Copper => (
+                Color::new(0.200, 0.924, 1.102),
+                Color::new(3.912, 2.452, 2.142),
+            ),
+            ComplexIor::Aluminium => (
+                Color::new(1.657, 0.880, 0.521),
+                Color::new(9.224, 6.270, 4.837),
+            ),
+            ComplexIor::Custom { eta, k } => (*eta, *k),
+        }
+    }
+}
+
+pub struct Conductor {
+    eta: Color,
+    k: Color,
+    ggx: Ggx,
+}
+
+impl Conductor {
+    pub fn new(ior: ComplexIor, roughness: f64) -> Conductor {
+        let (eta, k) = ior.eta_k();
+
+        Conductor {
+            eta,
+            k,
+            ggx: Ggx::from_roughness(roughness),
+        }
+    }
+
+    fn bsdf(&self, ray: &Ray, hr: &HitRecord) -> ConductorBsdf {
+        let frame = OrthoNormalBasis::from(hr.get_normal());
+        let wo = frame.to_local(&-ray.direction().to_unit_vector());
+
+        ConductorBsdf {
+            frame,
+            wo,
+            eta: self.eta,
+            k: self.k,
+            ggx: self.ggx,
+        }
+    }
+}
+
+impl Material for Conductor {
+    fn scatter(&self, ray: &Ray, hr: &HitRecord) -> Option<ScatterRecord> {
+        // the colour at normal incidence
+        let attenuation = fr_conductor(1.0, self.eta, self.k);
+
+        Some(ScatterRecord::diffuse(
+            &attenuation,
+            Box::new(self.bsdf(ray, hr)),
+        ))
+    }
+
+    fn scattering_pdf(&self, ray: &Ray, hr: &HitRecord, scattered: &Ray) -> f64 {
+        self.bsdf(ray, hr).value(&scattered.direction())
+    }
+
+    fn scattering(
+        &self,
+        ray: &Ray,
+        hr: &HitRecord,
+        scattered: &Ray,
+        _attenuation: &Color,
+    ) -> Color {
+        let bsdf = self.bsdf(ray, hr);
+
+        bsdf.eval(&bsdf.frame.to_local(&scattered.direction().to_unit_vector()))
+    }
+}
+
+// The conductor at a hit point, in the local frame of the shading normal
+pub struct ConductorBsdf {
+    frame: OrthoNormalBasis,
+    wo: Vec3,
+    eta: Color,
+    k: Color,
+    ggx: Ggx,
+}
+
+impl ConductorBsdf {
+    // BSDF times |cos| of `wi`
+    pub fn eval(&self, wi: &Vec3) -> Color {
+        let wo = &self.wo;
+        let (cos_o, cos_i) = (wo.z(), wi.z());
+        if cos_o <= 0.0 || cos_i <= 0.0 {
+            return Color::zero();
+        }
+        let wm = (*wo + *wi).to_unit_vector();
+
+        fr_conductor(wo.dot(wm), self.eta, self.k) * self.ggx.d(&wm) * self.ggx.g(wo, wi)
+            / (4.0 * cos_o)
+    }
+}
+
+impl Pdf for ConductorBsdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        if self.wo.z() <= 0.0 {
+            return 0.0;
+        }
+
+        self.ggx
+            .reflection_pdf(&self.wo, &self.frame.to_local(&direction.to_unit_vector()))
+    }
+
+    fn generate(&self) -> Vec3 {
+        let u = (random_double(), random_double());
+
+        self.frame.local(&self.ggx.sample_reflection(&self.wo, u))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampling_matches_eval() {
+        let wo = Vec3::new(0.4, -0.3, 0.7).to_unit_vector();
+
+        for roughness in [0.4, 0.8] {
+            let (eta, k) = ComplexIor::Gold.eta_k();
+            let bsdf = ConductorBsdf {
+                frame: OrthoNormalBasis::from(Vec3::new(0.0, 0.0, 1.0)),
+                wo,
+                eta,
+                k,
+                ggx: Ggx::from_roughness(roughness),
+            };
+
+            // albedo from importance sampling and from uniform directions
+            let n = 200_000;
+            let mut sampled = Color::zero();
+            let mut uniform = Color::zero();
+            for _ in 0..n {
+                let wi = bsdf.generate();
+                let pdf = bsdf.value(&wi);
+                if pdf > 0.0 {
+                    sampled += bsdf.eval(&bsdf.frame.to_local(&wi)) / pdf;
+                }
+                let w = Vec3::random_unit_vector();
+                uniform += bsdf.eval(&w) * 4.0 * std::f64::consts::PI;
+            }
+            let (sampled, uniform) = (sampled / n as f64, uniform / n as f64);
+
+            for c in 0..3 {
+                assert!(sampled[c] < 1.0);
+                assert!(
+                    (sampled[c] - uniform[c]).abs() < 0.03,
+                    "roughness {}: {:?} {:?}",
+                    roughness,
+                    sampled,
+                    uniform
+                );
+            }
+            // gold is yellow
+            assert!(sampled.x() > sampled.z());
+        }
+    }
+}
@@ -5,6 +5,9 @@ use crate::vec3::{Color, Point3};
 
 use super::Material;
 
+// texture lookups per side of the grid averaging the emission
+const EMISSION_GRID: usize = 8;
+
 pub struct DiffuseLight {
     pub emit: Box<dyn Texture>,
 }
@@ -25,4 +28,17 @@ impl Material for DiffuseLight {
             Color::zero()
         }
     }
+
+    // Over a grid of texture coordinates, which is also a grid of the unit
+    // cube for the solid textures (checker, noise)
+    fn emission(&self) -> Color {
+        let n = EMISSION_GRID;
+        let at = |i: usize| (i as f64 + 0.5) / n as f64;
+        let sum = (0..n * n * n).fold(Color::zero(), |sum, idx| {
+            let (u, v, w) = (at(idx % n), at(idx / n % n), at(idx / (n * n)));
+            sum + self.emit.value(u, v, &Point3::new(u, v, w))
+        });
+
+        sum / (n * n * n) as f64
+    }
 }
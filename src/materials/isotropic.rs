@@ -9,7 +9,7 @@ use crate::vec3::Color;
 use super::{Material, ScatterRecord};
 
 pub struct Isotropic {
-    albedo: Box<dyn Texture>,
+    pub albedo: Box<dyn Texture>,
 }
 
 impl From<Color> for Isotropic {
@@ -0,0 +1,373 @@
+// GGX (Trowbridge-Reitz) microfacet distribution and Fresnel terms.
+//
+// Directions are in the local shading frame, the normal being +z, and point
+// away from the surface.
+use std::f64::consts::PI;
+
+use crate::vec3::{Color, Vec3};
+
+// below, the distribution is too sharp to be evaluated in floating point
+const MIN_ALPHA: f64 = 1E-3;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Ggx {
+    alpha: f64,
+}
+
+impl Ggx {
+    // Perceptual roughness in [0, 1], alpha = roughness^2
+    pub fn from_roughness(roughness: f64) -> Ggx {
+        Ggx {
+            alpha: (roughness * roughness).max(MIN_ALPHA),
+        }
+    }
+
+    // Density of the microfacet normals, all of them face up
+    pub fn d(&self, wm: &Vec3) -> f64 {
+        if wm.z() <= 0.0 {
+            return 0.0;
+        }
+        let cos2 = wm.z() * wm.z();
+        let tan2 = (1.0 - cos2) / cos2;
+        let a2 = self.alpha * self.alpha;
+        let e = 1.0 + tan2 / a2;
+
+        1.0 / (PI * a2 * cos2 * cos2 * e * e)
+    }
+
+    fn lambda(&self, w: &Vec3) -> f64 {
+        let cos2 = w.z() * w.z();
+        if cos2 <= 0.0 {
+            return 0.0;
+        }
+        let tan2 = (1.0 - cos2) / cos2;
+
+        ((1.0 + self.alpha * self.alpha * tan2).sqrt() - 1.0) / 2.0
+    }
+
+    // Masking of one direction
+    pub fn g1(&self, w: &Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    // Height correlated masking-shadowing
+    pub fn g(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    // Density of the normals visible from `w`, `sample_wm` samples it
+    pub fn pdf(&self, w: &Vec3, wm: &Vec3) -> f64 {
+        if w.z() == 0.0 {
+            return 0.0;
+        }
+
+        self.g1(w) / w.z().abs() * self.d(wm) * w.dot(*wm).abs()
+    }
+
+    // Visible normal sampling (Heitz 2018), `u` uniform in [0, 1)^2
+    pub fn sample_wm(&self, w: &Vec3, u: (f64, f64)) -> Vec3 {
+        // stretch to the hemisphere configuration
+        let mut wh = Vec3::new(self.alpha * w.x(), self.alpha * w.y(), w.z()).to_unit_vector();
+        if wh.z() < 0.0 {
+            wh = -wh;
+        }
+        let t1 = if wh.z() < 0.99999 {
+            Vec3::new(0.0, 0.0, 1.0).cross(wh).to_unit_vector()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = wh.cross(t1);
+
+        // uniform point on the disk, warped to the visible half
+        let r = u.0.sqrt();
+        let phi = 2.0 * PI * u.1;
+        let px = r * phi.cos();
+        let h = (1.0 - px * px).sqrt();
+        let s = (1.0 + wh.z()) / 2.0;
+        let py = (1.0 - s) * h + s * r * phi.sin();
+        let pz = (1.0 - px * px - py * py).max(0.0).sqrt();
+        let nh = px * t1 + py * t2 + pz * wh;
+
+        // back to the ellipsoid configuration
+        Vec3::new(self.alpha * nh.x(), self.alpha * nh.y(), nh.z().max(1E-6)).to_unit_vector()
+    }
+
+    // Density of `reflect(wo, sample_wm(wo))`
+    pub fn reflection_pdf(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        if wi.z() <= 0.0 {
+            return 0.0;
+        }
+        let wm = (*wo + *wi).to_unit_vector();
+
+        self.pdf(wo, &wm) / (4.0 * wo.dot(wm).abs())
+    }
+
+    pub fn sample_reflection(&self, wo: &Vec3, u: (f64, f64)) -> Vec3 {
+        reflect(wo, &self.sample_wm(wo, u))
+    }
+
+    // Microfacet normal between `wo` and `wi` for a dielectric interface and
+    // the relative index of `wi` side, None when the microfacet faces away
+    // from one of the directions
+    fn dielectric_normal(&self, wo: &Vec3, wi: &Vec3, eta: f64) -> Option<(Vec3, f64)> {
+        let etap = if wi.z() > 0.0 { 1.0 } else { eta };
+
+        let wm = *wi * etap + *wo;
+        if wm.length_squared() == 0.0 {
+            return None;
+        }
+        let mut wm = wm.to_unit_vector();
+        if wm.z() < 0.0 {
+            wm = -wm;
+        }
+        if wm.dot(*wi) * wi.z() < 0.0 || wm.dot(*wo) * wo.z() < 0.0 {
+            return None;
+        }
+
+        Some((wm, etap))
+    }
+
+    // Rough dielectric BSDF (Walter 2007), a reflection when `wi` is on the
+    // side of `wo` and a transmission otherwise. `wo` is above the surface and
+    // `eta` is the index of the other side over the index of its side.
+    pub fn dielectric(&self, wo: &Vec3, wi: &Vec3, eta: f64) -> f64 {
+        let (wm, etap) = match self.dielectric_normal(wo, wi, eta) {
+            Some(n) => n,
+            None => return 0.0,
+        };
+        let (cos_o, cos_i) = (wo.z(), wi.z());
+
+        let fresnel = fr_dielectric(wo.dot(wm), eta);
+        let dg = self.d(&wm) * self.g(wo, wi);
+
+        if cos_i > 0.0 {
+            dg * fresnel / (4.0 * cos_i * cos_o)
+        } else {
+            let denom = (wi.dot(wm) + wo.dot(wm) / etap).powi(2);
+            // radiance is compressed in the denser medium
+            dg * (1.0 - fresnel) * (wi.dot(wm) * wo.dot(wm)).abs()
+                / (cos_i * cos_o * denom).abs()
+                / (etap * etap)
+        }
+    }
+
+    // Density of `sample_dielectric`. Rough microfacets send some reflections
+    // below the surface and some refractions above it, the density covers
+    // both mechanisms wherever they land to keep the estimates unbiased.
+    pub fn dielectric_pdf(&self, wo: &Vec3, wi: &Vec3, eta: f64) -> f64 {
+        let mut pdf = 0.0;
+
+        // reflection on the halfway microfacet
+        let wm = *wo + *wi;
+        if wm.length_squared() > 0.0 {
+            let wm = wm.to_unit_vector();
+            if wm.z() > 0.0 && wo.dot(wm) > 0.0 {
+                pdf += self.pdf(wo, &wm) / (4.0 * wo.dot(wm)) * fr_dielectric(wo.dot(wm), eta);
+            }
+        }
+
+        // refraction through the generalized halfway microfacet
+        let wm = *wo + *wi * eta;
+        if wm.length_squared() > 0.0 {
+            let mut wm = wm.to_unit_vector();
+            if wm.z() < 0.0 {
+                wm = -wm;
+            }
+            if wo.dot(wm) > 0.0 && wi.dot(wm) < 0.0 {
+                let denom = (wi.dot(wm) + wo.dot(wm) / eta).powi(2);
+                pdf += self.pdf(wo, &wm) * wi.dot(wm).abs() / denom
+                    * (1.0 - fr_dielectric(wo.dot(wm), eta));
+            }
+        }
+
+        pdf
+    }
+
+    // Reflection or transmission through a visible normal, chosen with the
+    // Fresnel term, `u_fresnel` uniform in [0, 1)
+    pub fn sample_dielectric(&self, wo: &Vec3, eta: f64, u: (f64, f64), u_fresnel: f64) -> Vec3 {
+        let wm = self.sample_wm(wo, u);
+        if u_fresnel < fr_dielectric(wo.dot(wm), eta) {
+            reflect(wo, &wm)
+        } else {
+            // the Fresnel term is 1 on total internal reflection
+            refract(wo, &wm, eta).unwrap_or_else(|| reflect(wo, &wm))
+        }
+    }
+}
+
+pub fn reflect(wo: &Vec3, wm: &Vec3) -> Vec3 {
+    -*wo + 2.0 * wo.dot(*wm) * *wm
+}
+
+// Refraction of `wo` through the microfacet `wm` (same side as `wo`), `eta`
+// being the ratio of the indices of the other side over the side of `wo`.
+// None on total internal reflection.
+pub fn refract(wo: &Vec3, wm: &Vec3, eta: f64) -> Option<Vec3> {
+    let cos_i = wo.dot(*wm);
+    let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+
+    Some(-*wo / eta + (cos_i / eta - cos_t) * *wm)
+}
+
+// Unpolarized Fresnel reflectance of a dielectric interface, `eta` as for
+// `refract` and `cos_i` measured on the side of the incident direction
+pub fn fr_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let cos_i = cos_i.clamp(-1.0, 1.0);
+    let (cos_i, eta) = if cos_i < 0.0 {
+        (-cos_i, 1.0 / eta)
+    } else {
+        (cos_i, eta)
+    };
+
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+
+    let r_parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+
+    (r_parallel * r_parallel + r_perpendicular * r_perpendicular) / 2.0
+}
+
+// Fresnel reflectance of a conductor with the complex index eta + i k, per
+// channel, from the air
+pub fn fr_conductor(cos_i: f64, eta: Color, k: Color) -> Color {
+    let cos2 = cos_i.clamp(0.0, 1.0).powi(2);
+    let sin2 = 1.0 - cos2;
+    let mut f = Color::zero();
+
+    for c in 0..3 {
+        let (eta2, k2) = (eta[c] * eta[c], k[c] * k[c]);
+
+        let t0 = eta2 - k2 - sin2;
+        let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+        let t1 = a2_plus_b2 + cos2;
+        let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+        let t2 = 2.0 * cos2.sqrt() * a;
+        let rs = (t1 - t2) / (t1 + t2);
+
+        let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let rp = rs * (t3 - t4) / (t3 + t4);
+
+        f[c] = 0.5 * (rp + rs);
+    }
+
+    f
+}
+
+pub fn fr_schlick(f0: Color, cos: f64) -> Color {
+    f0 + (Color::new(1.0, 1.0, 1.0) - f0) * schlick_weight(cos)
+}
+
+pub fn schlick_weight(cos: f64) -> f64 {
+    (1.0 - cos).clamp(0.0, 1.0).powi(5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::random_double;
+
+    // Midpoint quadrature over the sphere
+    fn integrate(f: impl Fn(&Vec3) -> f64) -> f64 {
+        let (n_theta, n_phi) = (800, 200);
+        let (d_theta, d_phi) = (PI / n_theta as f64, 2.0 * PI / n_phi as f64);
+
+        let mut sum = 0.0;
+        for i in 0..n_theta {
+            let theta = (i as f64 + 0.5) * d_theta;
+            for j in 0..n_phi {
+                let phi = (j as f64 + 0.5) * d_phi;
+                let w = Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                );
+                sum += f(&w) * theta.sin() * d_theta * d_phi;
+            }
+        }
+
+        sum
+    }
+
+    #[test]
+    fn test_ggx_normalization() {
+        for roughness in [0.3, 0.6, 1.0] {
+            let ggx = Ggx::from_roughness(roughness);
+            let wo = Vec3::new(0.5, -0.2, 0.6).to_unit_vector();
+
+            // projected microfacet area is the macro surface
+            let d = integrate(|wm| ggx.d(wm) * wm.z().max(0.0));
+            assert!((d - 1.0).abs() < 1E-2, "roughness {}: {}", roughness, d);
+            // the visible normals pdf integrates to one
+            let pdf = integrate(|wm| ggx.pdf(&wo, wm) * (wo.dot(*wm) > 0.0) as u8 as f64);
+            assert!((pdf - 1.0).abs() < 1E-2, "roughness {}: {}", roughness, pdf);
+        }
+    }
+
+    #[test]
+    fn test_vndf_sampling() {
+        let ggx = Ggx::from_roughness(0.5);
+        let wo = Vec3::new(0.3, 0.4, 0.5).to_unit_vector();
+
+        // the sampled normals have the moments of the pdf
+        let n = 100_000;
+        let mean = (0..n)
+            .map(|_| {
+                let wm = ggx.sample_wm(&wo, (random_double(), random_double()));
+                assert!(wm.z() > 0.0 && wo.dot(wm) > 0.0);
+                wm
+            })
+            .fold(Vec3::zero(), |sum, wm| sum + wm)
+            / n as f64;
+
+        for c in 0..3 {
+            let expected =
+                integrate(|wm| wm[c] * ggx.pdf(&wo, wm) * (wo.dot(*wm) > 0.0) as u8 as f64);
+            assert!((mean[c] - expected).abs() < 1E-2, "{:?}", mean);
+        }
+    }
+
+    #[test]
+    fn test_fresnel() {
+        // normal incidence on glass
+        assert!((fr_dielectric(1.0, 1.5) - 0.04).abs() < 1E-9);
+        // total internal reflection from inside
+        assert_eq!(fr_dielectric(0.1, 1.0 / 1.5), 1.0);
+        assert_eq!(fr_dielectric(-0.1, 1.5), 1.0);
+
+        let wo = Vec3::new(0.6, 0.0, 0.8);
+        let wm = Vec3::new(0.0, 0.0, 1.0);
+        let wi = refract(&wo, &wm, 1.5).unwrap();
+        // Snell's law
+        assert!((wi.length() - 1.0).abs() < 1E-9);
+        assert!((0.6 - 1.5 * wi.x().abs()).abs() < 1E-9);
+        assert!(wi.z() < 0.0);
+    }
+
+    #[test]
+    fn test_conductor_fresnel() {
+        let eta = Color::new(0.2, 0.9, 1.1);
+        let k = Color::new(3.9, 2.5, 2.1);
+
+        let f = fr_conductor(1.0, eta, k);
+        for c in 0..3 {
+            let expected =
+                ((eta[c] - 1.0).powi(2) + k[c] * k[c]) / ((eta[c] + 1.0).powi(2) + k[c] * k[c]);
+            assert!((f[c] - expected).abs() < 1E-9);
+        }
+        // mirror at grazing angles
+        assert!((fr_conductor(0.0, eta, k) - Color::new(1.0, 1.0, 1.0)).length() < 1E-9);
+        // no absorption is a dielectric
+        let dielectric = fr_conductor(0.6, Color::new(1.5, 1.5, 1.5), Color::zero());
+        assert!((dielectric.x() - fr_dielectric(0.6, 1.5)).abs() < 1E-9);
+    }
+}
@@ -3,11 +3,15 @@ use crate::hittable::HitRecord;
 use crate::pdf::Pdf;
 use crate::vec3::{Color, Point3};
 
+pub mod conductor;
 pub mod dielectric;
 pub mod diffuse_light;
 pub mod isotropic;
 pub mod lambertian;
 pub mod metal;
+pub mod microfacet;
+pub mod principled;
+pub mod rough_dielectric;
 
 pub enum ScatterRecord {
     Specular {
@@ -34,6 +38,13 @@ impl ScatterRecord {
             pdf,
         }
     }
+
+    pub fn attenuation(&self) -> Color {
+        match self {
+            ScatterRecord::Specular { attenuation, .. } => *attenuation,
+            ScatterRecord::Diffuse { attenuation, .. } => *attenuation,
+        }
+    }
 }
 
 pub trait Material: Send + Sync {
@@ -41,6 +52,12 @@ pub trait Material: Send + Sync {
         Color::zero()
     }
 
+    // Average radiance of the front faces, non-zero for the emitters sampled
+    // as area lights
+    fn emission(&self) -> Color {
+        Color::zero()
+    }
+
     fn scatter(&self, _ray: &Ray, _hr: &HitRecord) -> Option<ScatterRecord> {
         None
     }
@@ -48,4 +65,11 @@ pub trait Material: Send + Sync {
     fn scattering_pdf(&self, _ray: &Ray, _hr: &HitRecord, _scattered: &Ray) -> f64 {
         1.0
     }
+
+    // BSDF times the cosine of `scattered`, the weight of a direction sampled
+    // from `ScatterRecord::Diffuse`. Materials whose colour depends on the
+    // direction override it.
+    fn scattering(&self, ray: &Ray, hr: &HitRecord, scattered: &Ray, attenuation: &Color) -> Color {
+        *attenuation * self.scattering_pdf(ray, hr, scattered)
+    }
 }
@@ -0,0 +1,374 @@
+// Principled material in the spirit of the Disney BSDF: a diffuse base with
+// sheen, a GGX specular layer going from dielectric to metal, a clearcoat and
+// a rough transmission for glass-like surfaces.
+use std::f64::consts::PI;
+
+use crate::camera::ray::Ray;
+use crate::hittable::HitRecord;
+use crate::onb::OrthoNormalBasis;
+use crate::pdf::Pdf;
+use crate::texture::{solid::SolidTexture, Texture};
+use crate::tools::random_double;
+use crate::vec3::{Color, Vec3};
+
+use super::microfacet::{fr_schlick, schlick_weight, Ggx};
+use super::{Material, ScatterRecord};
+
+// roughness of the clearcoat layer
+const CLEARCOAT_ROUGHNESS: f64 = 0.2;
+
+// Scalar parameters are read from the red channel of their texture, all in [0, 1]
+pub struct Principled {
+    pub base_color: Box<dyn Texture>,
+    pub metallic: Box<dyn Texture>,
+    pub roughness: Box<dyn Texture>,
+    // reflectance of the dielectric base, 0.5 is 4%
+    pub specular: Box<dyn Texture>,
+    pub clearcoat: Box<dyn Texture>,
+    pub sheen: Box<dyn Texture>,
+    pub transmission: Box<dyn Texture>,
+    pub ior: f64,
+}
+
+impl From<Color> for Principled {
+    fn from(color: Color) -> Self {
+        let value = |v| -> Box<dyn Texture> { Box::new(SolidTexture::from(Color::new(v, v, v))) };
+
+        Principled {
+            base_color: Box::new(SolidTexture::from(color)),
+            metallic: value(0.0),
+            roughness: value(0.5),
+            specular: value(0.5),
+            clearcoat: value(0.0),
+            sheen: value(0.0),
+            transmission: value(0.0),
+            ior: 1.5,
+        }
+    }
+}
+
+impl Principled {
+    // The BSDF at a hit point, `ray` coming towards it
+    fn bsdf(&self, ray: &Ray, hr: &HitRecord) -> PrincipledBsdf {
+        let (u, v, p) = (hr.get_u(), hr.get_v(), hr.get_p());
+        let scalar = |t: &dyn Texture| t.value(u, v, &p).x().clamp(0.0, 1.0);
+
+        let frame = OrthoNormalBasis::from(hr.get_normal());
+        let wo = frame.to_local(&-ray.direction().to_unit_vector());
+
+        PrincipledBsdf::new(
+            frame,
+            wo,
+            self.base_color.value(u, v, &p),
+            [
+                scalar(self.metallic.as_ref()),
+                scalar(self.roughness.as_ref()),
+                scalar(self.specular.as_ref()),
+                scalar(self.clearcoat.as_ref()),
+                scalar(self.sheen.as_ref()),
+                scalar(self.transmission.as_ref()),
+            ],
+            if hr.is_front() {
+                self.ior
+            } else {
+                1.0 / self.ior
+            },
+        )
+    }
+}
+
+impl Material for Principled {
+    fn scatter(&self, ray: &Ray, hr: &HitRecord) -> Option<ScatterRecord> {
+        let bsdf = self.bsdf(ray, hr);
+        let base_color = bsdf.base_color;
+
+        Some(ScatterRecord::diffuse(&base_color, Box::new(bsdf)))
+    }
+
+    fn scattering_pdf(&self, ray: &Ray, hr: &HitRecord, scattered: &Ray) -> f64 {
+        self.bsdf(ray, hr).value(&scattered.direction())
+    }
+
+    fn scattering(
+        &self,
+        ray: &Ray,
+        hr: &HitRecord,
+        scattered: &Ray,
+        _attenuation: &Color,
+    ) -> Color {
+        let bsdf = self.bsdf(ray, hr);
+
+        bsdf.eval(&bsdf.frame.to_local(&scattered.direction().to_unit_vector()))
+    }
+}
+
+// Lobes of the BSDF, in sampling order
+const DIFFUSE: usize = 0;
+const SPECULAR: usize = 1;
+const CLEARCOAT: usize = 2;
+const TRANSMISSION: usize = 3;
+
+// The material evaluated at a hit point, directions are in the local frame
+// of the shading normal which is on the side of `wo`
+pub struct PrincipledBsdf {
+    frame: OrthoNormalBasis,
+    wo: Vec3,
+    base_color: Color,
+    metallic: f64,
+    roughness: f64,
+    specular: f64,
+    clearcoat: f64,
+    sheen: f64,
+    transmission: f64,
+    // index of the other side over the side of `wo`
+    eta: f64,
+    ggx: Ggx,
+    clearcoat_ggx: Ggx,
+    // probability of sampling each lobe
+    lobes: [f64; 4],
+}
+
+impl PrincipledBsdf {
+    // `parameters` are metallic, roughness, specular, clearcoat, sheen and transmission
+    fn new(
+        frame: OrthoNormalBasis,
+        wo: Vec3,
+        base_color: Color,
+        parameters: [f64; 6],
+        eta: f64,
+    ) -> PrincipledBsdf {
+        let [metallic, roughness, specular, clearcoat, sheen, transmission] = parameters;
+
+        let mut lobes = [0.0; 4];
+        lobes[DIFFUSE] = (1.0 - metallic) * (1.0 - transmission);
+        lobes[SPECULAR] = 1.0 - (1.0 - metallic) * transmission;
+        lobes[CLEARCOAT] = 0.25 * clearcoat;
+        lobes[TRANSMISSION] = (1.0 - metallic) * transmission;
+        let total: f64 = lobes.iter().sum();
+        for p in lobes.iter_mut() {
+            *p /= total;
+        }
+
+        PrincipledBsdf {
+            frame,
+            wo,
+            base_color,
+            metallic,
+            roughness,
+            specular,
+            clearcoat,
+            sheen,
+            transmission,
+            eta,
+            ggx: Ggx::from_roughness(roughness),
+            clearcoat_ggx: Ggx::from_roughness(CLEARCOAT_ROUGHNESS),
+            lobes,
+        }
+    }
+
+    // BSDF times |cos| of `wi`
+    pub fn eval(&self, wi: &Vec3) -> Color {
+        let wo = &self.wo;
+        let (cos_o, cos_i) = (wo.z(), wi.z());
+        if cos_o <= 0.0 || cos_i == 0.0 {
+            return Color::zero();
+        }
+
+        let mut f = Color::zero();
+        if cos_i > 0.0 {
+            let wm = (*wo + *wi).to_unit_vector();
+            let cos_d = wi.dot(wm);
+
+            // Burley diffuse with its retro-reflection, and sheen at grazing angles
+            let diffuse_weight = (1.0 - self.metallic) * (1.0 - self.transmission);
+            if diffuse_weight > 0.0 {
+                let fd90 = 0.5 + 2.0 * self.roughness * cos_d * cos_d;
+                let fd = (1.0 + (fd90 - 1.0) * schlick_weight(cos_i))
+                    * (1.0 + (fd90 - 1.0) * schlick_weight(cos_o));
+                let sheen = self.sheen * schlick_weight(cos_d);
+
+                f += diffuse_weight * (self.base_color * fd / PI + Color::new(sheen, sheen, sheen));
+            }
+
+            // specular, tinted by the base colour for metals
+            let specular_weight = 1.0 - (1.0 - self.metallic) * self.transmission;
+            if specular_weight > 0.0 {
+                let dielectric = 0.08 * self.specular;
+                let f0 = (1.0 - self.metallic) * Color::new(dielectric, dielectric, dielectric)
+                    + self.metallic * self.base_color;
+
+                f += specular_weight
+                    * fr_schlick(f0, wo.dot(wm))
+                    * self.ggx.d(&wm)
+                    * self.ggx.g(wo, wi)
+                    / (4.0 * cos_i * cos_o);
+            }
+
+            if self.clearcoat > 0.0 {
+                let fresnel = fr_schlick(Color::new(0.04, 0.04, 0.04), wo.dot(wm));
+
+                f += 0.25 * self.clearcoat * fresnel * self.clearcoat_ggx.d(&wm)
+                    / (4.0 * cos_i * cos_o)
+                    * self.clearcoat_ggx.g(wo, wi);
+            }
+        }
+
+        if self.lobes[TRANSMISSION] > 0.0 {
+            f += self.dielectric(wi);
+        }
+
+        f * cos_i.abs()
+    }
+
+    // Rough dielectric, the transmission is tinted by the base colour
+    fn dielectric(&self, wi: &Vec3) -> Color {
+        let f =
+            (1.0 - self.metallic) * self.transmission * self.ggx.dielectric(&self.wo, wi, self.eta);
+
+        if wi.z() > 0.0 {
+            Color::new(f, f, f)
+        } else {
+            f * self.base_color
+        }
+    }
+
+    fn pdf(&self, wi: &Vec3) -> f64 {
+        let wo = &self.wo;
+        if wo.z() <= 0.0 {
+            return 0.0;
+        }
+
+        let mut pdf = 0.0;
+        if wi.z() > 0.0 {
+            pdf += self.lobes[DIFFUSE] * wi.z() / PI
+                + self.lobes[SPECULAR] * self.ggx.reflection_pdf(wo, wi)
+                + self.lobes[CLEARCOAT] * self.clearcoat_ggx.reflection_pdf(wo, wi);
+        }
+        if self.lobes[TRANSMISSION] > 0.0 {
+            pdf += self.lobes[TRANSMISSION] * self.ggx.dielectric_pdf(wo, wi, self.eta);
+        }
+
+        pdf
+    }
+
+    fn sample(&self) -> Vec3 {
+        let wo = &self.wo;
+        let mut u = random_double();
+        let lobe = (0..self.lobes.len())
+            .find(|&i| {
+                u -= self.lobes[i];
+                u < 0.0
+            })
+            .unwrap_or(DIFFUSE);
+
+        let uv = (random_double(), random_double());
+        match lobe {
+            SPECULAR => self.ggx.sample_reflection(wo, uv),
+            CLEARCOAT => self.clearcoat_ggx.sample_reflection(wo, uv),
+            TRANSMISSION => self
+                .ggx
+                .sample_dielectric(wo, self.eta, uv, random_double()),
+            _ => Vec3::random_cosine_direction(),
+        }
+    }
+}
+
+impl Pdf for PrincipledBsdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        self.pdf(&self.frame.to_local(&direction.to_unit_vector()))
+    }
+
+    fn generate(&self) -> Vec3 {
+        self.frame.local(&self.sample())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{hash_seed, reseed};
+
+    fn bsdf(base_color: Color, parameters: [f64; 6], eta: f64) -> PrincipledBsdf {
+        let frame = OrthoNormalBasis::from(Vec3::new(0.0, 0.0, 1.0));
+        let wo = Vec3::new(0.4, -0.3, 0.7).to_unit_vector();
+
+        PrincipledBsdf::new(frame, wo, base_color, parameters, eta)
+    }
+
+    // Estimates of the albedo (integral of the BSDF times |cos|) and of the
+    // integral of the pdf from the importance sampling of the BSDF
+    fn estimate(bsdf: &PrincipledBsdf) -> (Color, f64) {
+        let n = 200_000;
+        let mut albedo = Color::zero();
+        let mut pdf_integral = 0.0;
+
+        for _ in 0..n {
+            let wi = bsdf.generate();
+            let pdf = bsdf.value(&wi);
+            if pdf > 0.0 {
+                albedo += bsdf.eval(&bsdf.frame.to_local(&wi)) / pdf;
+            }
+            // uniform directions
+            let w = Vec3::random_unit_vector();
+            pdf_integral += bsdf.value(&w) * 4.0 * PI;
+        }
+
+        (albedo / n as f64, pdf_integral / n as f64)
+    }
+
+    #[test]
+    fn test_energy_and_pdf() {
+        let grey = Color::new(0.8, 0.8, 0.8);
+        let cases = [
+            ("plastic", [0.0, 0.4, 0.5, 0.0, 0.0, 0.0]),
+            ("metal", [1.0, 0.3, 0.5, 0.0, 0.0, 0.0]),
+            ("car paint", [0.5, 0.5, 0.5, 1.0, 0.5, 0.0]),
+            ("rough glass", [0.0, 0.5, 0.5, 0.0, 0.0, 1.0]),
+        ];
+
+        reseed(hash_seed(&[14]));
+
+        for (name, parameters) in cases.iter() {
+            let (albedo, pdf_integral) = estimate(&bsdf(grey, *parameters, 1.5));
+
+            // the pdf is normalized but for the reflections sampled below the
+            // surface, which carry no light
+            assert!(
+                pdf_integral < 1.03 && pdf_integral > 0.9,
+                "{}: {}",
+                name,
+                pdf_integral
+            );
+            // no energy is created, the glass loses some as radiance is
+            // compressed entering it
+            assert!(albedo.x() < 1.0, "{}: {:?}", name, albedo);
+            assert!(albedo.x() > 0.3, "{}: {:?}", name, albedo);
+        }
+    }
+
+    #[test]
+    fn test_sampling_matches_eval() {
+        // the weight of a sampled direction is the same whether it comes from the
+        // BSDF or from a light, compare against uniform sampling
+        reseed(hash_seed(&[14]));
+
+        let color = Color::new(0.8, 0.5, 0.2);
+        let b = bsdf(color, [0.3, 0.5, 0.5, 0.5, 0.2, 0.0], 1.5);
+        let (albedo, _) = estimate(&b);
+
+        let n = 400_000;
+        let uniform = (0..n)
+            .map(|_| b.eval(&Vec3::random_unit_vector()) * 4.0 * PI)
+            .fold(Color::zero(), |sum, c| sum + c)
+            / n as f64;
+
+        for c in 0..3 {
+            assert!(
+                (albedo[c] - uniform[c]).abs() < 0.03,
+                "{:?} {:?}",
+                albedo,
+                uniform
+            );
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use std::ops::Mul;
+
+use crate::vec3::{Point3, Vec3};
+
+// Row major 4x4 matrix of an affine transform, applied to column vectors
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Matrix4 {
+    m: [[f64; 4]; 4],
+}
+
+impl Matrix4 {
+    // constructors
+    pub fn new(m: [[f64; 4]; 4]) -> Matrix4 {
+        Matrix4 { m }
+    }
+
+    pub fn identity() -> Matrix4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] = 1.0;
+        }
+
+        Matrix4 { m }
+    }
+
+    pub fn translation(offset: Vec3) -> Matrix4 {
+        let mut t = Matrix4::identity();
+        for i in 0..3 {
+            t.m[i][3] = offset[i];
+        }
+
+        t
+    }
+
+    pub fn scaling(scale: Vec3) -> Matrix4 {
+        let mut s = Matrix4::identity();
+        for i in 0..3 {
+            s.m[i][i] = scale[i];
+        }
+
+        s
+    }
+
+    // Rotation of `angle` degrees around `axis` (right handed)
+    pub fn rotation(axis: Vec3, angle: f64) -> Matrix4 {
+        let a = axis.to_unit_vector();
+        let (x, y, z) = (a.x(), a.y(), a.z());
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let t = 1.0 - cos;
+
+        Matrix4::new([
+            [
+                t * x * x + cos,
+                t * x * y - sin * z,
+                t * x * z + sin * y,
+                0.0,
+            ],
+            [
+                t * x * y + sin * z,
+                t * y * y + cos,
+                t * y * z - sin * x,
+                0.0,
+            ],
+            [
+                t * x * z - sin * y,
+                t * y * z + sin * x,
+                t * z * z + cos,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn rotation_x(angle: f64) -> Matrix4 {
+        Matrix4::rotation(Vec3::new(1.0, 0.0, 0.0), angle)
+    }
+
+    pub fn rotation_y(angle: f64) -> Matrix4 {
+        Matrix4::rotation(Vec3::new(0.0, 1.0, 0.0), angle)
+    }
+
+    pub fn rotation_z(angle: f64) -> Matrix4 {
+        Matrix4::rotation(Vec3::new(0.0, 0.0, 1.0), angle)
+    }
+
+    // accessors
+    pub fn get(&self, row: usize, column: usize) -> f64 {
+        self.m[row][column]
+    }
+
+    pub fn transpose(&self) -> Matrix4 {
+        let mut t = [[0.0; 4]; 4];
+        for (i, row) in t.iter_mut().enumerate() {
+            for (j, v) in row.iter_mut().enumerate() {
+                *v = self.m[j][i];
+            }
+        }
+
+        Matrix4 { m: t }
+    }
+
+    // Determinant of the upper 3x3 (linear) part
+    pub fn determinant3(&self) -> f64 {
+        let m = &self.m;
+
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    // Gauss-Jordan elimination with partial pivoting, None for singular matrices
+    pub fn inverse(&self) -> Option<Matrix4> {
+        let mut a = self.m;
+        let mut inv = Matrix4::identity().m;
+
+        for c in 0..4 {
+            let pivot = (c..4)
+                .max_by(|&i, &j| a[i][c].abs().partial_cmp(&a[j][c].abs()).unwrap())
+                .unwrap();
+            if a[pivot][c].abs() < 1E-12 {
+                return None;
+            }
+            a.swap(c, pivot);
+            inv.swap(c, pivot);
+
+            let p = a[c][c];
+            for j in 0..4 {
+                a[c][j] /= p;
+                inv[c][j] /= p;
+            }
+
+            for r in 0..4 {
+                if r != c {
+                    let f = a[r][c];
+                    for j in 0..4 {
+                        a[r][j] -= f * a[c][j];
+                        inv[r][j] -= f * inv[c][j];
+                    }
+                }
+            }
+        }
+
+        Some(Matrix4 { m: inv })
+    }
+
+    pub fn transform_point(&self, p: Point3) -> Point3 {
+        let m = &self.m;
+
+        Point3::new(
+            m[0][0] * p.x() + m[0][1] * p.y() + m[0][2] * p.z() + m[0][3],
+            m[1][0] * p.x() + m[1][1] * p.y() + m[1][2] * p.z() + m[1][3],
+            m[2][0] * p.x() + m[2][1] * p.y() + m[2][2] * p.z() + m[2][3],
+        )
+    }
+
+    // Directions ignore the translation
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+
+        Vec3::new(
+            m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+            m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+            m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+        )
+    }
+}
+
+impl Default for Matrix4 {
+    fn default() -> Self {
+        Matrix4::identity()
+    }
+}
+
+impl Mul for Matrix4 {
+    type Output = Matrix4;
+
+    fn mul(self, other: Matrix4) -> Matrix4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, v) in row.iter_mut().enumerate() {
+                *v = (0..4).map(|k| self.m[i][k] * other.m[k][j]).sum();
+            }
+        }
+
+        Matrix4 { m }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1E-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_transforms() {
+        let p = Point3::new(1.0, 2.0, 3.0);
+
+        let t = Matrix4::translation(Vec3::new(1.0, -1.0, 0.5));
+        assert_near(t.transform_point(p), Point3::new(2.0, 1.0, 3.5));
+        assert_near(t.transform_vector(p), p);
+
+        let s = Matrix4::scaling(Vec3::new(2.0, 3.0, -1.0));
+        assert_near(s.transform_point(p), Point3::new(2.0, 6.0, -3.0));
+
+        assert_near(
+            Matrix4::rotation_z(90.0).transform_vector(Vec3::new(1.0, 0.0, 0.0)),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        assert_near(
+            Matrix4::rotation_x(90.0).transform_vector(Vec3::new(0.0, 1.0, 0.0)),
+            Vec3::new(0.0, 0.0, 1.0),
+        );
+        assert_near(
+            Matrix4::rotation_y(90.0).transform_vector(Vec3::new(0.0, 0.0, 1.0)),
+            Vec3::new(1.0, 0.0, 0.0),
+        );
+
+        // translate after scaling
+        assert_near(
+            (t * s).transform_point(p),
+            t.transform_point(s.transform_point(p)),
+        );
+    }
+
+    #[test]
+    fn test_inverse() {
+        let m = Matrix4::translation(Vec3::new(3.0, -2.0, 1.0))
+            * Matrix4::rotation(Vec3::new(1.0, 1.0, 0.0), 33.0)
+            * Matrix4::scaling(Vec3::new(0.5, 2.0, 4.0));
+        let inv = m.inverse().unwrap();
+
+        let p = Point3::new(-1.0, 5.0, 2.5);
+        assert_near(inv.transform_point(m.transform_point(p)), p);
+        assert!((m.determinant3() - 4.0).abs() < 1E-9);
+        assert!((inv.determinant3() - 0.25).abs() < 1E-9);
+
+        assert_eq!(Matrix4::scaling(Vec3::new(1.0, 0.0, 1.0)).inverse(), None);
+    }
+}
@@ -0,0 +1,180 @@
+use crate::geometry::aabb::Aabb;
+use crate::texture::perlin::Perlin;
+use crate::vec3::{Point3, Vec3};
+
+// octaves of the Perlin turbulence
+const TURBULENCE_DEPTH: usize = 7;
+
+// Scales the coefficients of a medium from point to point
+pub trait Density: Send + Sync {
+    fn value(&self, p: &Point3) -> f64;
+
+    // bound of `value` over the whole medium, for the majorant of the tracking
+    fn max(&self) -> f64;
+}
+
+pub struct ConstantDensity {
+    pub density: f64,
+}
+
+impl Density for ConstantDensity {
+    fn value(&self, _p: &Point3) -> f64 {
+        self.density
+    }
+
+    fn max(&self) -> f64 {
+        self.density
+    }
+}
+
+// Values at the centres of the cells of a box, interpolated trilinearly and
+// nothing outside of the box
+pub struct GridDensity {
+    bounds: Aabb,
+    resolution: [usize; 3],
+    // x varies first, then y
+    values: Vec<f64>,
+    max: f64,
+}
+
+impl GridDensity {
+    pub fn new(
+        min: Point3,
+        max: Point3,
+        resolution: [usize; 3],
+        values: Vec<f64>,
+    ) -> Result<GridDensity, String> {
+        let [nx, ny, nz] = resolution;
+        if nx * ny * nz == 0 || values.len() != nx * ny * nz {
+            return Err(format!(
+                "A {}x{}x{} density grid has {} values",
+                nx,
+                ny,
+                nz,
+                values.len()
+            ));
+        }
+        if values.iter().any(|&v| !v.is_finite() || v < 0.0) {
+            return Err("Densities are finite and positive".to_string());
+        }
+        if (0..3).any(|a| max[a] <= min[a]) {
+            return Err("The box of a density grid is empty".to_string());
+        }
+
+        Ok(GridDensity {
+            bounds: Aabb::new(min, max),
+            resolution,
+            max: values.iter().cloned().fold(0.0, f64::max),
+            values,
+        })
+    }
+
+    fn at(&self, i: usize, j: usize, k: usize) -> f64 {
+        let [nx, ny, _] = self.resolution;
+        self.values[(k * ny + j) * nx + i]
+    }
+}
+
+impl Density for GridDensity {
+    fn value(&self, p: &Point3) -> f64 {
+        let (min, max) = (self.bounds.min(), self.bounds.max());
+        if (0..3).any(|a| p[a] < min[a] || p[a] > max[a]) {
+            return 0.0;
+        }
+
+        // lower cell of the interpolation and the offsets from its centre
+        let mut cell = [0; 3];
+        let mut offset = Vec3::zero();
+        for a in 0..3 {
+            let n = self.resolution[a];
+            let x =
+                ((p[a] - min[a]) / (max[a] - min[a]) * n as f64 - 0.5).clamp(0.0, (n - 1) as f64);
+            cell[a] = (x as usize).min(n.saturating_sub(2));
+            offset[a] = x - cell[a] as f64;
+        }
+
+        let mut value = 0.0;
+        for corner in 0..8 {
+            let mut weight = 1.0;
+            let mut idx = [0; 3];
+            for a in 0..3 {
+                let upper = (corner >> a) & 1 == 1;
+                idx[a] = (cell[a] + upper as usize).min(self.resolution[a] - 1);
+                weight *= if upper { offset[a] } else { 1.0 - offset[a] };
+            }
+            value += weight * self.at(idx[0], idx[1], idx[2]);
+        }
+
+        value
+    }
+
+    fn max(&self) -> f64 {
+        self.max
+    }
+}
+
+// Clouds of Perlin turbulence of frequency `scale`, reaching `density` at most
+pub struct PerlinDensity {
+    noise: Perlin,
+    scale: f64,
+    density: f64,
+}
+
+impl PerlinDensity {
+    pub fn new(scale: f64, density: f64) -> PerlinDensity {
+        PerlinDensity {
+            noise: Perlin::new(),
+            scale,
+            density,
+        }
+    }
+}
+
+impl Density for PerlinDensity {
+    fn value(&self, p: &Point3) -> f64 {
+        self.density
+            * self
+                .noise
+                .turb(&(self.scale * *p), TURBULENCE_DEPTH)
+                .min(1.0)
+    }
+
+    fn max(&self) -> f64 {
+        self.density
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_interpolation() {
+        // 0 on the cells of the left, 1 on the right ones
+        let values = (0..8).map(|i| (i % 2) as f64).collect();
+        let grid = GridDensity::new(
+            Point3::zero(),
+            Point3::new(2.0, 2.0, 2.0),
+            [2, 2, 2],
+            values,
+        )
+        .unwrap();
+
+        assert_eq!(grid.max(), 1.0);
+        // constant up to the centres, linear between them
+        assert_eq!(grid.value(&Point3::new(0.2, 0.5, 1.7)), 0.0);
+        assert_eq!(grid.value(&Point3::new(1.0, 1.0, 1.0)), 0.5);
+        assert!((grid.value(&Point3::new(1.25, 0.3, 0.9)) - 0.75).abs() < 1E-12);
+        assert_eq!(grid.value(&Point3::new(1.9, 1.0, 1.0)), 1.0);
+        assert_eq!(grid.value(&Point3::new(2.1, 1.0, 1.0)), 0.0);
+
+        assert!(GridDensity::new(Point3::zero(), Point3::zero(), [1, 1, 1], vec![1.0]).is_err());
+        assert!(GridDensity::new(
+            Point3::zero(),
+            Point3::new(1.0, 1.0, 1.0),
+            [2, 1, 1],
+            vec![1.0]
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,336 @@
+// Participating media: volumes inside a boundary that absorb and scatter the
+// light along the rays going through them. Their coefficients change from
+// point to point following a density field, so the collisions are found by
+// delta tracking against a majorant of the extinction and the transmittances
+// by ratio tracking.
+use std::iter;
+use std::sync::Arc;
+
+use crate::camera::ray::Ray;
+use crate::hittable::{HitRecord, Hittable};
+use crate::materials::Material;
+use crate::tools::random_double;
+use crate::vec3::{Color, Vec3};
+
+use density::Density;
+
+pub mod density;
+
+// the boundary is looked for again this far after a crossing
+const CROSSING_EPSILON: f64 = 1E-4;
+
+fn average(c: &Color) -> f64 {
+    (c.x() + c.y() + c.z()) / 3.0
+}
+
+pub struct Medium {
+    boundary: Box<dyn Hittable>,
+    density: Box<dyn Density>,
+    // absorption and scattering coefficients where the density is 1
+    sigma_a: Color,
+    sigma_s: Color,
+    // bounds the extinction of every channel in the whole medium
+    majorant: f64,
+    phase_function: Arc<dyn Material>,
+}
+
+impl Medium {
+    pub fn new(
+        boundary: Box<dyn Hittable>,
+        density: Box<dyn Density>,
+        sigma_a: Color,
+        sigma_s: Color,
+        phase_function: Arc<dyn Material>,
+    ) -> Medium {
+        Medium {
+            majorant: density.max() * (sigma_a + sigma_s).max_component(),
+            boundary,
+            density,
+            sigma_a,
+            sigma_s,
+            phase_function,
+        }
+    }
+
+    // Parts of `r` between `tmin` and `tmax` inside the boundary. Every
+    // crossing of the boundary is followed, so it doesn't need to be convex,
+    // and the boundary is left as many times as it is entered, so the number
+    // of times the origin is inside is the balance of the crossings.
+    fn segments(&self, r: &Ray, tmin: f64, tmax: f64) -> Vec<(f64, f64)> {
+        let mut crossings = Vec::new();
+        let mut t = tmin;
+        while let Some(hr) = self.boundary.hit(r, t, f64::INFINITY) {
+            crossings.push((hr.get_t(), if hr.is_front() { 1 } else { -1 }));
+            t = hr.get_t() + CROSSING_EPSILON;
+        }
+
+        let mut depth: i32 = -crossings.iter().map(|(_, step)| step).sum::<i32>();
+        let mut start = tmin;
+        let mut segments = Vec::new();
+        for (t, step) in crossings.into_iter().chain(iter::once((f64::INFINITY, 0))) {
+            let end = t.min(tmax);
+            if depth > 0 && end > start {
+                // inside of overlapping parts of the boundary
+                match segments.last_mut() {
+                    Some((_, last)) if *last == start => *last = end,
+                    _ => segments.push((start, end)),
+                }
+            }
+            if t >= tmax {
+                break;
+            }
+            depth += step;
+            start = t;
+        }
+
+        segments
+    }
+
+    // First collision along `r` found by delta tracking: a scattering, with the
+    // weight of the path up to it, or an absorption, of weight zero. The null
+    // collisions and the scatterings are chosen following the average of the
+    // channels and weighted by their colour (spectral tracking), so the grey
+    // media are sampled exactly.
+    pub fn sample(&self, r: &Ray, tmin: f64, tmax: f64) -> (Option<HitRecord>, Color) {
+        let mut weight = Color::new(1.0, 1.0, 1.0);
+        if self.majorant <= 0.0 {
+            return (None, weight);
+        }
+
+        let length = r.direction().length();
+        for (t0, t1) in self.segments(r, tmin, tmax) {
+            let mut t = t0;
+            loop {
+                t -= (1.0 - random_double()).ln() / (self.majorant * length);
+                if t >= t1 {
+                    break;
+                }
+
+                let density = self.density.value(&r.at(t));
+                let sigma_a = density * self.sigma_a;
+                let sigma_s = density * self.sigma_s;
+                let sigma_n = Color::new(1.0, 1.0, 1.0) * self.majorant - sigma_a - sigma_s;
+
+                let u = random_double() * self.majorant;
+                if u < average(&sigma_a) {
+                    return (None, Color::zero());
+                }
+                if u < average(&sigma_a) + average(&sigma_s) {
+                    let hr = HitRecord::new(
+                        r.at(t),
+                        Vec3::new(1.0, 0.0, 0.0),
+                        t,
+                        0.0,
+                        0.0,
+                        self.phase_function.clone(),
+                    );
+                    return (Some(hr), weight * sigma_s / average(&sigma_s));
+                }
+                weight = weight * sigma_n / average(&sigma_n);
+            }
+        }
+
+        (None, weight)
+    }
+
+    // Fraction of the light going through the medium along `r`, estimated by
+    // ratio tracking
+    pub fn transmittance(&self, r: &Ray, tmin: f64, tmax: f64) -> Color {
+        let mut transmittance = Color::new(1.0, 1.0, 1.0);
+        if self.majorant <= 0.0 {
+            return transmittance;
+        }
+
+        let length = r.direction().length();
+        for (t0, t1) in self.segments(r, tmin, tmax) {
+            let mut t = t0;
+            loop {
+                t -= (1.0 - random_double()).ln() / (self.majorant * length);
+                if t >= t1 {
+                    break;
+                }
+
+                let sigma_t = self.density.value(&r.at(t)) * (self.sigma_a + self.sigma_s);
+                transmittance =
+                    transmittance * (Color::new(1.0, 1.0, 1.0) - sigma_t / self.majorant);
+            }
+        }
+
+        transmittance
+    }
+}
+
+// The media of a scene, which may overlap
+#[derive(Default)]
+pub struct Media {
+    media: Vec<Medium>,
+}
+
+impl Media {
+    pub fn new(media: Vec<Medium>) -> Media {
+        Media { media }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.media.is_empty()
+    }
+
+    // Closest collision of the media along `r` and the weight of the path up
+    // to it, see Medium::sample. The media are tracked one after the other up
+    // to the closest collision so far.
+    pub fn sample(&self, r: &Ray, tmin: f64, tmax: f64) -> (Option<HitRecord>, Color) {
+        let mut collision = None;
+        let mut weight = Color::new(1.0, 1.0, 1.0);
+        let mut closest_so_far = tmax;
+
+        for medium in self.media.iter() {
+            let (hr, w) = medium.sample(r, tmin, closest_so_far);
+            weight = weight * w;
+            if weight.max_component() <= 0.0 {
+                return (None, weight);
+            }
+            if let Some(hr) = hr {
+                closest_so_far = hr.get_t();
+                collision = Some(hr);
+            }
+        }
+
+        (collision, weight)
+    }
+
+    pub fn transmittance(&self, r: &Ray, tmin: f64, tmax: f64) -> Color {
+        self.media
+            .iter()
+            .fold(Color::new(1.0, 1.0, 1.0), |tr, medium| {
+                tr * medium.transmittance(r, tmin, tmax)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::sphere::Sphere;
+    use crate::materials::isotropic::Isotropic;
+    use crate::vec3::Point3;
+    use density::ConstantDensity;
+
+    // A sphere of radius 1 at the origin
+    fn ball(sigma_a: Color, sigma_s: Color) -> Medium {
+        let phase_function = Arc::new(Isotropic::from(Color::new(1.0, 1.0, 1.0)));
+        let sphere = Sphere {
+            center: Point3::zero(),
+            radius: 1.0,
+            material: phase_function.clone(),
+        };
+
+        Medium::new(
+            Box::new(sphere),
+            Box::new(ConstantDensity { density: 1.0 }),
+            sigma_a,
+            sigma_s,
+            phase_function,
+        )
+    }
+
+    #[test]
+    fn test_transmittance_of_a_ball() {
+        let sigma_t = Color::new(0.2, 0.5, 1.0);
+        let medium = ball(sigma_t, Color::zero());
+        let n = 20000;
+
+        // through the whole ball, from inside it and stopped in the middle by
+        // a surface
+        let cases = [
+            (Point3::new(0.0, 0.0, -3.0), f64::INFINITY, 2.0),
+            (Point3::new(0.0, 0.0, 0.5), f64::INFINITY, 0.5),
+            (Point3::new(0.0, 0.0, -3.0), 3.0, 1.0),
+        ];
+        for (origin, tmax, distance) in cases {
+            let r = Ray::new(origin, Vec3::new(0.0, 0.0, 1.0), 0.0);
+            let mut tr = Color::zero();
+            for _ in 0..n {
+                tr += medium.transmittance(&r, 0.001, tmax);
+            }
+            tr /= n as f64;
+
+            for c in 0..3 {
+                let expected = (-sigma_t[c] * distance).exp();
+                assert!((tr[c] - expected).abs() < 0.02, "{} {}", tr[c], expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_delta_tracking_matches_the_transmittance() {
+        // coloured, so the null collisions are weighted
+        let (sigma_a, sigma_s) = (Color::new(0.1, 0.4, 0.9), Color::new(0.6, 0.3, 0.1));
+        let medium = ball(sigma_a, sigma_s);
+        let r = Ray::new(Point3::new(0.0, 0.0, -3.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let n = 50000;
+
+        // the weights of the paths going through, and of the scatterings
+        let mut through = Color::zero();
+        let mut scattered = Color::zero();
+        for _ in 0..n {
+            match medium.sample(&r, 0.001, f64::INFINITY) {
+                (None, weight) => through += weight,
+                (Some(hr), weight) => {
+                    assert!(hr.get_t() > 2.0 && hr.get_t() < 4.0);
+                    scattered += weight;
+                }
+            }
+        }
+        through /= n as f64;
+        scattered /= n as f64;
+
+        for c in 0..3 {
+            let sigma_t = sigma_a[c] + sigma_s[c];
+            let tr = (-2.0 * sigma_t).exp();
+            assert!((through[c] - tr).abs() < 0.02, "{} {}", through[c], tr);
+            // scattered somewhere along the ball
+            let expected = sigma_s[c] / sigma_t * (1.0 - tr);
+            assert!(
+                (scattered[c] - expected).abs() < 0.02,
+                "{} {}",
+                scattered[c],
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_non_convex_boundary() {
+        // two balls side by side, their union, and a camera in the second one
+        let phase_function: Arc<dyn Material> = Arc::new(Isotropic::from(Color::zero()));
+        let sphere = |x: f64| -> Box<dyn Hittable> {
+            Box::new(Sphere {
+                center: Point3::new(x, 0.0, 0.0),
+                radius: 1.0,
+                material: phase_function.clone(),
+            })
+        };
+        let boundary: Vec<Box<dyn Hittable>> = vec![sphere(0.0), sphere(3.0), sphere(3.5)];
+        let medium = Medium::new(
+            Box::new(boundary),
+            Box::new(ConstantDensity { density: 1.0 }),
+            Color::new(1.0, 1.0, 1.0),
+            Color::zero(),
+            phase_function,
+        );
+
+        let r = Ray::new(Point3::new(-2.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        let segments = medium.segments(&r, 0.001, f64::INFINITY);
+        let expected = [(1.0, 3.0), (4.0, 6.5)];
+        assert_eq!(segments.len(), expected.len());
+        for ((t0, t1), (e0, e1)) in segments.into_iter().zip(expected) {
+            assert!((t0 - e0).abs() < 1E-9 && (t1 - e1).abs() < 1E-9);
+        }
+
+        let inside = Ray::new(Point3::new(3.2, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0), 0.0);
+        let segments = medium.segments(&inside, 0.001, 5.0);
+        assert_eq!(segments.len(), 2);
+        assert!((segments[0].0 - 0.001).abs() < 1E-9 && (segments[0].1 - 1.2).abs() < 1E-9);
+        assert!((segments[1].0 - 2.2).abs() < 1E-9 && (segments[1].1 - 4.2).abs() < 1E-9);
+    }
+}
@@ -27,6 +27,11 @@ impl OrthoNormalBasis {
     pub fn local(&self, a: &Vec3) -> Vec3 {
         a.x() * self.u + a.y() * self.v + a.z() * self.w
     }
+
+    // Inverse of `local`, coordinates of `a` in the basis
+    pub fn to_local(&self, a: &Vec3) -> Vec3 {
+        Vec3::new(a.dot(self.u), a.dot(self.v), a.dot(self.w))
+    }
 }
 
 impl From<Vec3> for OrthoNormalBasis {
@@ -0,0 +1,155 @@
+// Piecewise constant distributions over [0, 1) and [0, 1)^2, sampled by
+// inverting their cumulative distribution
+pub struct Distribution1D {
+    func: Vec<f64>,
+    // cdf[i] is the probability of the first i pieces, cdf[n] = 1
+    cdf: Vec<f64>,
+    integral: f64,
+}
+
+impl Distribution1D {
+    // A function without any positive value is sampled uniformly
+    pub fn new(func: Vec<f64>) -> Distribution1D {
+        let n = func.len();
+        let mut cdf = vec![0.0; n + 1];
+        for i in 0..n {
+            cdf[i + 1] = cdf[i] + func[i].max(0.0) / n as f64;
+        }
+
+        let integral = cdf[n];
+        for (i, c) in cdf.iter_mut().enumerate() {
+            *c = if integral > 0.0 {
+                *c / integral
+            } else {
+                i as f64 / n as f64
+            };
+        }
+
+        Distribution1D {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    pub fn integral(&self) -> f64 {
+        self.integral
+    }
+
+    // Point of [0, 1) for the uniform number `u`, and its density
+    pub fn sample(&self, u: f64) -> (f64, f64) {
+        let (i, offset) = self.invert(u);
+
+        ((i as f64 + offset) / self.func.len() as f64, self.pdf_at(i))
+    }
+
+    // Piece chosen for the uniform number `u`, and its probability
+    pub fn sample_discrete(&self, u: f64) -> (usize, f64) {
+        let (i, _) = self.invert(u);
+
+        (i, self.discrete_pdf(i))
+    }
+
+    pub fn discrete_pdf(&self, i: usize) -> f64 {
+        self.pdf_at(i) / self.func.len() as f64
+    }
+
+    // Piece of the cdf where `u` falls, and the position of `u` in it
+    fn invert(&self, u: f64) -> (usize, f64) {
+        let n = self.func.len();
+        // last piece whose cdf is not above u
+        let i = (self.cdf.partition_point(|&c| c <= u) - 1).min(n - 1);
+
+        let width = self.cdf[i + 1] - self.cdf[i];
+        let offset = if width > 0.0 {
+            (u - self.cdf[i]) / width
+        } else {
+            0.0
+        };
+
+        (i, offset)
+    }
+
+    // Density at x in [0, 1)
+    pub fn pdf(&self, x: f64) -> f64 {
+        let n = self.func.len();
+
+        self.pdf_at(((x * n as f64) as usize).min(n - 1))
+    }
+
+    fn pdf_at(&self, i: usize) -> f64 {
+        if self.integral > 0.0 {
+            self.func[i].max(0.0) / self.integral
+        } else {
+            1.0
+        }
+    }
+}
+
+// Function of (u, v) given by rows of `width` values, v = 0 being the first
+// row: v is drawn from the marginal distribution, then u from its row
+pub struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(func: &[f64], width: usize) -> Distribution2D {
+        let rows: Vec<_> = func
+            .chunks(width)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+        let marginal = Distribution1D::new(rows.iter().map(|row| row.integral()).collect());
+
+        Distribution2D { rows, marginal }
+    }
+
+    // Point of [0, 1)^2 for the uniform numbers `u1` and `u2`, and its density
+    pub fn sample(&self, u1: f64, u2: f64) -> ((f64, f64), f64) {
+        let (v, pdf_v) = self.marginal.sample(u2);
+        let row = &self.rows[self.row(v)];
+        let (u, pdf_u) = row.sample(u1);
+
+        ((u, v), pdf_u * pdf_v)
+    }
+
+    pub fn pdf(&self, u: f64, v: f64) -> f64 {
+        self.marginal.pdf(v) * self.rows[self.row(v)].pdf(u)
+    }
+
+    fn row(&self, v: f64) -> usize {
+        ((v * self.rows.len() as f64) as usize).min(self.rows.len() - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples_follow_the_function() {
+        let d = Distribution1D::new(vec![1.0, 0.0, 3.0]);
+        assert_eq!(d.integral(), 4.0 / 3.0);
+
+        // a quarter of the mass in the first piece, the rest in the last one
+        let (x, pdf) = d.sample(0.125);
+        assert!((x - 1.0 / 6.0).abs() < 1E-12);
+        assert_eq!(pdf, 0.75);
+        let (x, pdf) = d.sample(0.625);
+        assert!((x - 5.0 / 6.0).abs() < 1E-12);
+        assert_eq!(pdf, 2.25);
+        assert_eq!(d.pdf(0.5), 0.0);
+
+        assert_eq!(d.sample_discrete(0.625), (2, 0.75));
+        assert_eq!(d.discrete_pdf(0), 0.25);
+
+        let flat = Distribution1D::new(vec![0.0, 0.0]);
+        assert_eq!(flat.sample(0.75), (0.75, 1.0));
+
+        let d = Distribution2D::new(&[1.0, 1.0, 0.0, 2.0], 2);
+        let ((u, v), pdf) = d.sample(0.25, 0.75);
+        assert!(u > 0.5 && v > 0.5);
+        assert_eq!(pdf, 2.0);
+        assert_eq!(d.pdf(u, v), pdf);
+    }
+}
@@ -3,8 +3,8 @@ use std::ops::Deref;
 use crate::vec3::Vec3;
 
 pub mod cosine;
+pub mod distribution;
 pub mod hittable;
-pub mod mixture;
 pub mod sphere;
 
 pub trait Pdf {
@@ -0,0 +1,230 @@
+// Arbitrary output variables: first hit albedo, normal, depth, object and
+// material ids of the camera rays, accumulated next to the beauty pass.
+//
+// They are written as extra channels of .exr outputs, otherwise as sibling
+// files: PFM for the albedo, normal and depth, 16 bit PNG for the ids.
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use exr::prelude::{AnyChannel, FlatSamples};
+
+use crate::camera::ray::Ray;
+use crate::hittable::Hittable;
+use crate::scene::Scene;
+use crate::vec3::{Color, Vec3};
+
+// First hit of the samples of a pixel: sums of the albedo, normal and depth,
+// the ids of the first sample that hit an object (object ids start at 1)
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct AovSample {
+    pub albedo: Color,
+    pub normal: Vec3,
+    pub depth: f64,
+    pub object_id: u32,
+    pub material_id: u32,
+}
+
+impl AovSample {
+    // Add the first hit of the camera ray `r`, misses count as zeros
+    pub fn add(&mut self, r: &Ray, scene: &Scene) {
+        let hr = match scene.world.hit(r, 0.001, f64::INFINITY) {
+            Some(hr) => hr,
+            None => return,
+        };
+
+        if let Some(srec) = hr.material.scatter(r, &hr) {
+            self.albedo += srec.attenuation();
+        }
+        self.normal += hr.get_outward_normal();
+        self.depth += hr.get_t() * r.direction().length();
+        if self.object_id == 0 {
+            self.object_id = hr.get_object_id();
+            self.material_id = scene.material_id(&hr.material);
+        }
+    }
+}
+
+// The AOV buffers of an image. They are not part of the checkpoints, so they
+// have their own sample counts: after a resume they only average the new
+// samples.
+pub struct Aovs {
+    pub sums: Vec<AovSample>,
+    pub counts: Vec<u32>,
+}
+
+impl Aovs {
+    pub fn new(size: usize) -> Aovs {
+        Aovs {
+            sums: vec![AovSample::default(); size],
+            counts: vec![0; size],
+        }
+    }
+
+    pub fn add(&mut self, idx: usize, sample: &AovSample, samples: u32) {
+        let sum = &mut self.sums[idx];
+
+        sum.albedo += sample.albedo;
+        sum.normal += sample.normal;
+        sum.depth += sample.depth;
+        if sum.object_id == 0 {
+            sum.object_id = sample.object_id;
+            sum.material_id = sample.material_id;
+        }
+        self.counts[idx] += samples;
+    }
+
+    // Averages of a pixel, the normal is renormalized
+    pub fn average(&self, idx: usize) -> AovSample {
+        let sum = &self.sums[idx];
+        let n = self.counts[idx].max(1) as f64;
+        let normal = if sum.normal.near_zero() {
+            Vec3::zero()
+        } else {
+            sum.normal.to_unit_vector()
+        };
+
+        AovSample {
+            albedo: sum.albedo / n,
+            normal,
+            depth: sum.depth / n,
+            ..*sum
+        }
+    }
+
+    // EXR channels, named after the layer convention of compositing software
+    pub fn exr_channels(&self) -> Vec<AnyChannel<FlatSamples>> {
+        let size = self.sums.len();
+        let averages: Vec<_> = (0..size).map(|idx| self.average(idx)).collect();
+        let f32_channel = |name: &str, value: &dyn Fn(&AovSample) -> f64| {
+            AnyChannel::new(
+                name,
+                FlatSamples::F32(averages.iter().map(|a| value(a) as f32).collect()),
+            )
+        };
+        let u32_channel = |name: &str, value: &dyn Fn(&AovSample) -> u32| {
+            AnyChannel::new(name, FlatSamples::U32(averages.iter().map(value).collect()))
+        };
+
+        vec![
+            f32_channel("albedo.R", &|a| a.albedo.x()),
+            f32_channel("albedo.G", &|a| a.albedo.y()),
+            f32_channel("albedo.B", &|a| a.albedo.z()),
+            f32_channel("normal.X", &|a| a.normal.x()),
+            f32_channel("normal.Y", &|a| a.normal.y()),
+            f32_channel("normal.Z", &|a| a.normal.z()),
+            f32_channel("depth.Z", &|a| a.depth),
+            u32_channel("object_id.id", &|a| a.object_id),
+            u32_channel("material_id.id", &|a| a.material_id),
+        ]
+    }
+
+    // Write name.albedo.pfm, name.normal.pfm, name.depth.pfm,
+    // name.object_id.png and name.material_id.png next to the output `name`
+    pub fn save_siblings(&self, name: &str, width: usize, height: usize) -> Result<(), String> {
+        let stem = Path::new(name).with_extension("");
+        let sibling = |aov: &str, extension: &str| {
+            format!("{}.{}.{}", stem.to_string_lossy(), aov, extension)
+        };
+        let averages: Vec<_> = (0..self.sums.len()).map(|idx| self.average(idx)).collect();
+
+        let rgb = |value: &dyn Fn(&AovSample) -> Vec3| {
+            averages
+                .iter()
+                .flat_map(|a| {
+                    let v = value(a);
+                    [v.x() as f32, v.y() as f32, v.z() as f32]
+                })
+                .collect::<Vec<_>>()
+        };
+        let albedo = rgb(&|a| a.albedo);
+        write_pfm(&sibling("albedo", "pfm"), width, height, 3, &albedo)?;
+        let normal = rgb(&|a| a.normal);
+        write_pfm(&sibling("normal", "pfm"), width, height, 3, &normal)?;
+        let depth: Vec<_> = averages.iter().map(|a| a.depth as f32).collect();
+        write_pfm(&sibling("depth", "pfm"), width, height, 1, &depth)?;
+
+        let ids = |value: &dyn Fn(&AovSample) -> u32| {
+            averages
+                .iter()
+                .flat_map(|a| (value(a).min(u16::MAX as u32) as u16).to_ne_bytes())
+                .collect::<Vec<_>>()
+        };
+        for (aov, data) in [
+            ("object_id", ids(&|a| a.object_id)),
+            ("material_id", ids(&|a| a.material_id)),
+        ] {
+            let filename = sibling(aov, "png");
+            image::save_buffer(
+                &filename,
+                &data,
+                width as u32,
+                height as u32,
+                image::ColorType::L16,
+            )
+            .map_err(|e| format!("Failed to write {}: {}", filename, e))?;
+        }
+
+        Ok(())
+    }
+}
+
+// Portable float map of `channels` (1 or 3) values per pixel, top row first
+fn write_pfm(
+    filename: &str,
+    width: usize,
+    height: usize,
+    channels: usize,
+    data: &[f32],
+) -> Result<(), String> {
+    let err = |e: std::io::Error| format!("Failed to write {}: {}", filename, e);
+
+    let mut w = BufWriter::new(File::create(filename).map_err(err)?);
+    let magic = if channels == 3 { "PF" } else { "Pf" };
+    // a negative scale means little endian
+    write!(w, "{}\n{} {}\n-1.0\n", magic, width, height).map_err(err)?;
+    // rows are stored bottom to top
+    for row in data.chunks(width * channels).rev() {
+        for v in row {
+            w.write_all(&v.to_le_bytes()).map_err(err)?;
+        }
+    }
+
+    w.flush().map_err(err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::description::SceneDescription;
+    use crate::scene::SceneKind;
+    use crate::vec3::Point3;
+
+    #[test]
+    fn test_ids_of_the_first_hit() {
+        let scene = SceneDescription::from_kind(SceneKind::CornellBox, false, "")
+            .build()
+            .unwrap();
+        let origin = Point3::new(278.0, 500.0, -800.0);
+        let first_hit = |direction: Vec3| {
+            let mut sample = AovSample::default();
+            sample.add(&Ray::new(origin, direction, 0.0), &scene);
+            sample
+        };
+
+        // the white back wall, then the red and green side walls
+        let back = first_hit(Vec3::new(0.0, 0.0, 1.0));
+        assert!((back.depth - 1355.0).abs() < 1E-9);
+        let left = first_hit(Vec3::new(0.3, 0.0, 1.0));
+        let right = first_hit(Vec3::new(-0.3, 0.0, 1.0));
+        for side in [left, right] {
+            assert_ne!(side.object_id, 0);
+            assert_ne!(side.object_id, back.object_id);
+            assert_ne!(side.material_id, 0);
+            assert_ne!(side.material_id, back.material_id);
+        }
+        assert_ne!(left.material_id, right.material_id);
+
+        assert_eq!(first_hit(Vec3::new(0.0, 0.0, -1.0)), AovSample::default());
+    }
+}
@@ -0,0 +1,200 @@
+// On-disk render state used to resume renders: the linear RGB sums and sample
+// counts of every pixel, the scene hash and the RNG seed.
+//
+// Layout (little endian): magic, width, height, scene hash and seed as u64,
+// then for each pixel r, g, b sums as f64 and the sample count as u32.
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+
+use crate::vec3::Color;
+
+use super::framebuffer::FrameBuffer;
+
+const MAGIC: &[u8; 8] = b"RTCKPT01";
+const HEADER_BYTES: u64 = 40;
+const PIXEL_BYTES: u64 = 28;
+
+pub struct Checkpoint {
+    pub scene_hash: u64,
+    pub seed: u64,
+    pub fb: FrameBuffer,
+}
+
+// Checkpoint file of an output image
+pub fn checkpoint_path(output: &str) -> String {
+    format!("{}.checkpoint", output)
+}
+
+// Delete the checkpoint of a completed render, if there is one
+pub fn remove_checkpoint(filename: &str) -> Result<(), String> {
+    match fs::remove_file(filename) {
+        Err(e) if e.kind() != ErrorKind::NotFound => {
+            Err(format!("Failed to remove checkpoint {}: {}", filename, e))
+        }
+        _ => Ok(()),
+    }
+}
+
+impl Checkpoint {
+    // The file is written next to its final location then renamed, so a
+    // crash while saving keeps the previous checkpoint intact.
+    pub fn save(&self, filename: &str) -> Result<(), String> {
+        let err = |e: std::io::Error| format!("Failed to write checkpoint {}: {}", filename, e);
+        let tmp = format!("{}.tmp", filename);
+
+        let mut w = BufWriter::new(File::create(&tmp).map_err(err)?);
+        w.write_all(MAGIC).map_err(err)?;
+        for v in [
+            self.fb.width as u64,
+            self.fb.height as u64,
+            self.scene_hash,
+            self.seed,
+        ] {
+            w.write_all(&v.to_le_bytes()).map_err(err)?;
+        }
+        for (sum, count) in self.fb.sums.iter().zip(self.fb.counts.iter()) {
+            for c in 0..3 {
+                w.write_all(&sum[c].to_le_bytes()).map_err(err)?;
+            }
+            w.write_all(&count.to_le_bytes()).map_err(err)?;
+        }
+        w.into_inner()
+            .map_err(|e| err(e.into_error()))?
+            .sync_all()
+            .map_err(err)?;
+
+        fs::rename(&tmp, filename).map_err(err)
+    }
+
+    // Open a checkpoint and read its header: width, height, scene hash and seed
+    fn open(filename: &str) -> Result<(BufReader<File>, [u64; 4]), String> {
+        let err = |e: std::io::Error| format!("Failed to read checkpoint {}: {}", filename, e);
+
+        let mut r = BufReader::new(File::open(filename).map_err(err)?);
+        let mut magic = [0u8; 8];
+        r.read_exact(&mut magic).map_err(err)?;
+        if &magic != MAGIC {
+            return Err(format!("{} is not a render checkpoint", filename));
+        }
+
+        let mut header = [0u64; 4];
+        let mut buf = [0u8; 8];
+        for v in header.iter_mut() {
+            r.read_exact(&mut buf).map_err(err)?;
+            *v = u64::from_le_bytes(buf);
+        }
+
+        Ok((r, header))
+    }
+
+    // Seed of a checkpoint, needed before the scene is built
+    pub fn read_seed(filename: &str) -> Result<u64, String> {
+        let (_, [_, _, _, seed]) = Checkpoint::open(filename)?;
+
+        Ok(seed)
+    }
+
+    pub fn load(filename: &str) -> Result<Checkpoint, String> {
+        let err = |e: std::io::Error| format!("Failed to read checkpoint {}: {}", filename, e);
+
+        let (mut r, [width, height, scene_hash, seed]) = Checkpoint::open(filename)?;
+
+        // a damaged header must not allocate an absurd frame buffer
+        let len = r.get_ref().metadata().map_err(err)?.len();
+        let expected = width
+            .checked_mul(height)
+            .and_then(|pixels| pixels.checked_mul(PIXEL_BYTES))
+            .and_then(|bytes| bytes.checked_add(HEADER_BYTES));
+        if expected != Some(len) {
+            return Err(format!(
+                "Checkpoint {} is {} bytes long, not the size of a {}x{} render",
+                filename, len, width, height
+            ));
+        }
+        let (width, height) = (width as usize, height as usize);
+
+        let mut fb = FrameBuffer::new(width, height);
+        let mut f64_buf = [0u8; 8];
+        let mut u32_buf = [0u8; 4];
+        for idx in 0..width * height {
+            let mut sum = Color::zero();
+            for c in 0..3 {
+                r.read_exact(&mut f64_buf).map_err(err)?;
+                sum[c] = f64::from_le_bytes(f64_buf);
+            }
+            r.read_exact(&mut u32_buf).map_err(err)?;
+
+            fb.sums[idx] = sum;
+            fb.counts[idx] = u32::from_le_bytes(u32_buf);
+        }
+
+        Ok(Checkpoint {
+            scene_hash,
+            seed,
+            fb,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let mut fb = FrameBuffer::new(3, 2);
+        fb.add(0, 0, Color::new(0.5, 1.0, 1.5), 4);
+        fb.add(2, 1, Color::new(f64::MAX, 0.0, 1E-300), 7);
+
+        let checkpoint = Checkpoint {
+            scene_hash: 0x1234_5678_9abc_def0,
+            seed: 42,
+            fb,
+        };
+        let filename = std::env::temp_dir()
+            .join(format!("raytracer-test-{}.checkpoint", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        checkpoint.save(&filename).unwrap();
+        let loaded = Checkpoint::load(&filename).unwrap();
+        fs::remove_file(&filename).unwrap();
+
+        assert_eq!(loaded.scene_hash, checkpoint.scene_hash);
+        assert_eq!(loaded.seed, checkpoint.seed);
+        assert_eq!(loaded.fb.width, 3);
+        assert_eq!(loaded.fb.height, 2);
+        assert_eq!(loaded.fb.sums, checkpoint.fb.sums);
+        assert_eq!(loaded.fb.counts, checkpoint.fb.counts);
+    }
+
+    #[test]
+    fn test_checkpoint_size_is_checked() {
+        let checkpoint = Checkpoint {
+            scene_hash: 1,
+            seed: 2,
+            fb: FrameBuffer::new(4, 3),
+        };
+        let filename = std::env::temp_dir()
+            .join(format!(
+                "raytracer-test-{}-size.checkpoint",
+                std::process::id()
+            ))
+            .to_string_lossy()
+            .to_string();
+        checkpoint.save(&filename).unwrap();
+        let bytes = fs::read(&filename).unwrap();
+
+        // truncated, too long, and a header claiming a huge image
+        let mut truncated = bytes.clone();
+        truncated.pop();
+        let mut extended = bytes.clone();
+        extended.push(0);
+        let mut huge = bytes;
+        huge[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+        for damaged in [truncated, extended, huge] {
+            fs::write(&filename, damaged).unwrap();
+            assert!(Checkpoint::load(&filename).is_err());
+        }
+        fs::remove_file(&filename).unwrap();
+    }
+}
@@ -0,0 +1,216 @@
+// Edge-avoiding à-trous wavelet filter (Dammertz et al. 2010) run on a
+// rendered frame buffer. The albedo, normal and depth AOVs stop the filter at
+// the edges of the objects, and the illumination (the colour divided by the
+// albedo) is filtered rather than the colour, which keeps the textures sharp.
+use rayon::prelude::*;
+
+use crate::vec3::Color;
+
+use super::aov::AovSample;
+use super::framebuffer::FrameBuffer;
+
+// passes of the filter, the last one spans 2^(ITERATIONS + 1) pixels
+const ITERATIONS: u32 = 5;
+// B3 spline
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+// on the compressed illumination, halved at each pass
+const SIGMA_COLOR: f64 = 0.3;
+const SIGMA_NORMAL: f64 = 0.5;
+const SIGMA_ALBEDO: f64 = 0.1;
+// relative to the depth, per pixel of the step
+const SIGMA_DEPTH: f64 = 0.1;
+// below it the albedo is not divided out (emitters, black materials, misses)
+const ALBEDO_MIN: f64 = 0.01;
+
+// Replace the averages of `fb` with their denoised values, the sample counts
+// are kept. The AOVs must have been recorded.
+pub fn denoise(fb: &mut FrameBuffer) -> Result<(), String> {
+    let aovs = fb
+        .aovs
+        .as_ref()
+        .ok_or("The denoiser needs the AOVs of the render")?;
+    let (width, height) = (fb.width, fb.height);
+
+    let features: Vec<_> = (0..width * height).map(|idx| aovs.average(idx)).collect();
+    let mut illumination: Vec<_> = (0..width * height)
+        .map(|idx| demodulate(fb.average(idx % width, idx / width), features[idx].albedo))
+        .collect();
+
+    for i in 0..ITERATIONS {
+        let step = 1 << i;
+        let sigma_color = SIGMA_COLOR / step as f64;
+        illumination = (0..width * height)
+            .into_par_iter()
+            .map(|idx| {
+                filter_pixel(
+                    &illumination,
+                    &features,
+                    (width, height),
+                    (idx % width, idx / width),
+                    step,
+                    sigma_color,
+                )
+            })
+            .collect();
+    }
+
+    for (idx, c) in illumination.into_iter().enumerate() {
+        fb.sums[idx] = remodulate(c, features[idx].albedo) * fb.counts[idx] as f64;
+    }
+
+    Ok(())
+}
+
+fn demodulate(c: Color, albedo: Color) -> Color {
+    let mut illumination = c;
+    for i in 0..3 {
+        if albedo[i] > ALBEDO_MIN {
+            illumination[i] /= albedo[i];
+        }
+    }
+
+    illumination
+}
+
+fn remodulate(illumination: Color, albedo: Color) -> Color {
+    let mut c = illumination;
+    for i in 0..3 {
+        if albedo[i] > ALBEDO_MIN {
+            c[i] *= albedo[i];
+        }
+    }
+
+    c
+}
+
+// Colour differences are taken on c / (1 + c), bright pixels are not more
+// different than dark ones
+fn compress(c: Color) -> Color {
+    Color::new(
+        c.x() / (1.0 + c.x()),
+        c.y() / (1.0 + c.y()),
+        c.z() / (1.0 + c.z()),
+    )
+}
+
+// One pass of the filter on the pixel (x, y): a 5x5 kernel of taps `step`
+// pixels apart, weighted by the similarity of their colour and features
+fn filter_pixel(
+    input: &[Color],
+    features: &[AovSample],
+    (width, height): (usize, usize),
+    (x, y): (usize, usize),
+    step: usize,
+    sigma_color: f64,
+) -> Color {
+    let p = y * width + x;
+    let (cp, fp) = (compress(input[p]), &features[p]);
+
+    let mut sum = Color::zero();
+    let mut weights = 0.0;
+    for (j, ky) in KERNEL.iter().enumerate() {
+        let qy = y as isize + (j as isize - 2) * step as isize;
+        if qy < 0 || qy >= height as isize {
+            continue;
+        }
+        for (i, kx) in KERNEL.iter().enumerate() {
+            let qx = x as isize + (i as isize - 2) * step as isize;
+            if qx < 0 || qx >= width as isize {
+                continue;
+            }
+
+            let q = qy as usize * width + qx as usize;
+            let fq = &features[q];
+            let color = (compress(input[q]) - cp).length_squared() / (sigma_color * sigma_color);
+            let normal = (fq.normal - fp.normal).length_squared() / (SIGMA_NORMAL * SIGMA_NORMAL);
+            let albedo = (fq.albedo - fp.albedo).length_squared() / (SIGMA_ALBEDO * SIGMA_ALBEDO);
+            // misses have a zero depth
+            let depth = (fq.depth - fp.depth).abs()
+                / (SIGMA_DEPTH * step as f64 * fp.depth.max(fq.depth).max(1E-9));
+
+            let w = kx * ky * (-(color + normal + albedo + depth)).exp();
+            sum += w * input[q];
+            weights += w;
+        }
+    }
+
+    // the centre tap always has a positive weight
+    sum / weights
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::aov::Aovs;
+    use crate::renderer::tiles::{tiles, TileOrder};
+    use crate::renderer::{render_pass, Budget, Pass, RendererKind};
+    use crate::scene::description::SceneDescription;
+    use crate::scene::{Overrides, SceneKind};
+    use crate::tools::{hash_seed, reseed};
+    use indicatif::ProgressBar;
+
+    fn render(samples: u32) -> FrameBuffer {
+        reseed(hash_seed(&[3]));
+        let mut desc = SceneDescription::from_kind(SceneKind::CornellBox, false, "");
+        desc.config.apply_overrides(&Overrides {
+            width: Some(32),
+            samples_per_pixel: Some(samples),
+            ..Default::default()
+        });
+        let scene = desc.build().unwrap();
+
+        let mut fb = FrameBuffer::new(scene.cfg.width, scene.cfg.height);
+        fb.aovs = Some(Aovs::new(fb.width * fb.height));
+        let tiles = tiles(fb.width, fb.height, 16, TileOrder::Scanline, 3);
+        let pass = Pass {
+            samples,
+            seed: 3,
+            aovs: true,
+        };
+        render_pass(
+            RendererKind::ParallelRayon,
+            &scene,
+            &ProgressBar::hidden(),
+            &mut fb,
+            &tiles,
+            pass,
+            &Budget { deadline: None },
+            &mut |_| {},
+        );
+
+        fb
+    }
+
+    // Off the light: a few samples miss its edges, there is nothing to
+    // denoise in these black pixels
+    fn mse(fb: &FrameBuffer, reference: &FrameBuffer) -> f64 {
+        let (width, height) = (fb.width, fb.height);
+        let errors: Vec<_> = (0..width * height)
+            .map(|idx| {
+                let (x, y) = (idx % width, idx / width);
+                (fb.average(x, y), reference.average(x, y))
+            })
+            .filter(|(_, r)| r.max_component() <= 1.0)
+            .map(|(c, r)| (c - r).length_squared() / 3.0)
+            .collect();
+
+        errors.iter().sum::<f64>() / errors.len() as f64
+    }
+
+    #[test]
+    fn test_denoiser_reduces_the_error() {
+        let reference = render(512);
+        let mut fb = render(4);
+
+        let noisy = mse(&fb, &reference);
+        denoise(&mut fb).unwrap();
+        let denoised = mse(&fb, &reference);
+
+        assert!(
+            denoised < 0.5 * noisy,
+            "MSE {} after denoising, {} before",
+            denoised,
+            noisy
+        );
+    }
+}
@@ -0,0 +1,253 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use exr::prelude::{
+    AnyChannel, AnyChannels, Encoding, FlatSamples, Image, Layer, LayerAttributes, SmallVec,
+    WritableImage,
+};
+use image::codecs::hdr::HdrEncoder;
+use image::Rgb;
+
+use crate::vec3::Color;
+
+use super::aov::{AovSample, Aovs};
+use super::tonemap::ToneMapping;
+use super::BYTES_PER_PIXEL;
+
+// Linear RGB accumulation buffer, each pixel holds the sum of its samples and
+// their count so passes with any number of samples can be added over time.
+pub struct FrameBuffer {
+    pub width: usize,
+    pub height: usize,
+    pub sums: Vec<Color>,
+    pub counts: Vec<u32>,
+    // recorded when the AOVs are written too
+    pub aovs: Option<Aovs>,
+}
+
+impl FrameBuffer {
+    pub fn new(width: usize, height: usize) -> FrameBuffer {
+        FrameBuffer {
+            width,
+            height,
+            sums: vec![Color::zero(); width * height],
+            counts: vec![0; width * height],
+            aovs: None,
+        }
+    }
+
+    pub fn add(&mut self, x: usize, y: usize, sum: Color, samples: u32) {
+        let idx = y * self.width + x;
+
+        self.sums[idx] += sum;
+        self.counts[idx] += samples;
+    }
+
+    pub fn add_aov(&mut self, x: usize, y: usize, sample: &AovSample, samples: u32) {
+        let idx = y * self.width + x;
+
+        if let Some(aovs) = self.aovs.as_mut() {
+            aovs.add(idx, sample, samples);
+        }
+    }
+
+    // Lowest number of samples of a pixel
+    pub fn min_samples(&self) -> u32 {
+        self.counts.iter().copied().min().unwrap_or(0)
+    }
+
+    // Linear average of a pixel, NaN components are replaced with zero
+    pub fn average(&self, x: usize, y: usize) -> Color {
+        let idx = y * self.width + x;
+        let mut c = self.sums[idx] / self.counts[idx].max(1) as f64;
+
+        for i in 0..3 {
+            if c[i].is_nan() {
+                c[i] = 0.0;
+            }
+        }
+
+        c
+    }
+
+    pub fn to_rgb8(&self, tone_mapping: &ToneMapping) -> Vec<u8> {
+        let mut pixels = vec![0u8; self.width * self.height * BYTES_PER_PIXEL];
+
+        for (idx, pixel) in pixels.chunks_mut(BYTES_PER_PIXEL).enumerate() {
+            // pixels without samples stay black
+            let c = self.average(idx % self.width, idx / self.width);
+            pixel.copy_from_slice(&tone_mapping.quantize(c));
+        }
+
+        pixels
+    }
+
+    // Write the current average, .exr and .hdr files keep the linear radiance,
+    // other formats are deduced from the extension and tone mapped. The AOVs
+    // are extra channels of .exr files, sibling files of the others.
+    pub fn save(&self, name: &str, tone_mapping: &ToneMapping) -> Result<(), String> {
+        let extension = Path::new(name)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase());
+
+        match extension.as_deref() {
+            Some("exr") => self.save_exr(name),
+            Some("hdr") => self.save_hdr(name),
+            _ => image::save_buffer(
+                name,
+                self.to_rgb8(tone_mapping).as_slice(),
+                self.width as u32,
+                self.height as u32,
+                image::ColorType::Rgb8,
+            )
+            .map_err(|e| e.to_string()),
+        }
+        .map_err(|e| format!("Failed to write {}: {}", name, e))?;
+
+        match &self.aovs {
+            Some(aovs) if extension.as_deref() != Some("exr") => {
+                aovs.save_siblings(name, self.width, self.height)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn save_exr(&self, name: &str) -> Result<(), String> {
+        let aovs = match &self.aovs {
+            Some(aovs) => aovs,
+            None => {
+                return exr::prelude::write_rgb_file(name, self.width, self.height, |x, y| {
+                    let c = self.average(x, y);
+                    (c.x() as f32, c.y() as f32, c.z() as f32)
+                })
+                .map_err(|e| e.to_string())
+            }
+        };
+
+        let averages: Vec<_> = (0..self.width * self.height)
+            .map(|idx| self.average(idx % self.width, idx / self.width))
+            .collect();
+        let mut channels: Vec<_> = ["R", "G", "B"]
+            .iter()
+            .enumerate()
+            .map(|(c, name)| {
+                AnyChannel::new(
+                    *name,
+                    FlatSamples::F32(averages.iter().map(|a| a[c] as f32).collect()),
+                )
+            })
+            .collect();
+        channels.extend(aovs.exr_channels());
+
+        let layer = Layer::new(
+            (self.width, self.height),
+            LayerAttributes::default(),
+            Encoding::FAST_LOSSLESS,
+            AnyChannels::sort(SmallVec::from_vec(channels)),
+        );
+        Image::from_layer(layer)
+            .write()
+            .to_file(name)
+            .map_err(|e| e.to_string())
+    }
+
+    fn save_hdr(&self, name: &str) -> Result<(), String> {
+        let data: Vec<Rgb<f32>> = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let c = self.average(x, y);
+                Rgb([c.x() as f32, c.y() as f32, c.z() as f32])
+            })
+            .collect();
+
+        let file = File::create(name).map_err(|e| e.to_string())?;
+        HdrEncoder::new(BufWriter::new(file))
+            .encode(&data, self.width, self.height)
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::Vec3;
+    use exr::prelude::{ReadChannels, ReadLayers};
+
+    #[test]
+    fn test_exr_keeps_radiance_above_one() {
+        let mut fb = FrameBuffer::new(2, 1);
+        fb.add(0, 0, Color::new(12.0, 6.0, 3.0), 2);
+        fb.add(1, 0, Color::new(0.25, 0.5, 0.75), 1);
+
+        let filename = std::env::temp_dir()
+            .join(format!("raytracer-test-{}.exr", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        fb.save(&filename, &ToneMapping::default()).unwrap();
+
+        let image = exr::prelude::read_first_rgba_layer_from_file(
+            &filename,
+            |resolution, _| {
+                vec![(0.0f32, 0.0f32, 0.0f32); resolution.width() * resolution.height()]
+            },
+            |pixels, position, (r, g, b, _a): (f32, f32, f32, f32)| {
+                pixels[position.y() * 2 + position.x()] = (r, g, b)
+            },
+        )
+        .unwrap();
+        std::fs::remove_file(&filename).unwrap();
+
+        let pixels = image.layer_data.channel_data.pixels;
+        assert_eq!(pixels, vec![(6.0, 3.0, 1.5), (0.25, 0.5, 0.75)]);
+    }
+
+    #[test]
+    fn test_exr_aov_channels() {
+        let mut fb = FrameBuffer::new(2, 1);
+        fb.aovs = Some(Aovs::new(2));
+        fb.add(0, 0, Color::new(1.0, 2.0, 3.0), 2);
+        let sample = AovSample {
+            albedo: Color::new(1.0, 0.5, 0.0),
+            normal: Vec3::new(0.0, 0.0, -4.0),
+            depth: 10.0,
+            object_id: 3,
+            material_id: 2,
+        };
+        fb.add_aov(0, 0, &sample, 2);
+
+        let filename = std::env::temp_dir()
+            .join(format!("raytracer-test-aovs-{}.exr", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        fb.save(&filename, &ToneMapping::default()).unwrap();
+
+        let image = exr::prelude::read()
+            .no_deep_data()
+            .largest_resolution_level()
+            .all_channels()
+            .first_valid_layer()
+            .all_attributes()
+            .from_file(&filename)
+            .unwrap();
+        std::fs::remove_file(&filename).unwrap();
+
+        let channel = |name: &str| {
+            image
+                .layer_data
+                .channel_data
+                .list
+                .iter()
+                .find(|c| c.name == *name)
+                .unwrap_or_else(|| panic!("no {} channel", name))
+                .sample_data
+                .clone()
+        };
+        assert_eq!(channel("G"), FlatSamples::F32(vec![1.0, 0.0]));
+        assert_eq!(channel("albedo.R"), FlatSamples::F32(vec![0.5, 0.0]));
+        assert_eq!(channel("normal.Z"), FlatSamples::F32(vec![-1.0, 0.0]));
+        assert_eq!(channel("depth.Z"), FlatSamples::F32(vec![5.0, 0.0]));
+        assert_eq!(channel("object_id.id"), FlatSamples::U32(vec![3, 0]));
+        assert_eq!(channel("material_id.id"), FlatSamples::U32(vec![2, 0]));
+    }
+}
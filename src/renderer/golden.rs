@@ -0,0 +1,214 @@
+// Golden image regression tests: every built-in scene is rendered small with a
+// fixed seed and compared with its reference in tests/golden.
+//
+// On failure the render and an amplified difference image are written to
+// target/golden. Run with UPDATE_GOLDEN=1 to (re)generate the references after
+// an intended change, and look at them before committing them: renders with
+// non-finite pixels or all black are refused as references.
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use clap::ArgEnum;
+use indicatif::ProgressBar;
+
+use crate::scene::description::SceneDescription;
+use crate::scene::{Overrides, SceneKind};
+use crate::tools::{hash_seed, reseed};
+
+use super::framebuffer::FrameBuffer;
+use super::tiles::{tiles, TileOrder};
+use super::tonemap::ToneMapping;
+use super::{render_pass, Budget, Pass, RendererKind, BYTES_PER_PIXEL};
+
+const REFERENCE_DIR: &str = "tests/golden";
+const OUTPUT_DIR: &str = "target/golden";
+const WIDTH: usize = 48;
+const SAMPLES: u32 = 16;
+const SEED: u64 = 2021;
+// on the 0-255 scale, leaves room for floating point differences across platforms
+const MAX_RMSE: f64 = 1.5;
+const DIFF_SCALE: f64 = 8.0;
+
+struct Render {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+    // pixels whose sum of samples is NaN or infinite, black once quantized
+    non_finite: usize,
+}
+
+fn render_scene(kind: SceneKind) -> Render {
+    reseed(hash_seed(&[SEED]));
+    let mut desc = SceneDescription::from_kind(kind, false, "data/1k/earth.jpg");
+    desc.config.apply_overrides(&Overrides {
+        width: Some(WIDTH),
+        samples_per_pixel: Some(SAMPLES),
+        ..Default::default()
+    });
+    let scene = desc.build().unwrap();
+
+    let mut fb = FrameBuffer::new(scene.cfg.width, scene.cfg.height);
+    let tiles = tiles(fb.width, fb.height, 16, TileOrder::default(), SEED);
+    let pass = Pass {
+        samples: SAMPLES,
+        seed: SEED,
+        aovs: false,
+    };
+    render_pass(
+        RendererKind::ParallelRayon,
+        &scene,
+        &ProgressBar::hidden(),
+        &mut fb,
+        &tiles,
+        pass,
+        &Budget { deadline: None },
+        &mut |_| {},
+    );
+
+    Render {
+        width: fb.width,
+        height: fb.height,
+        pixels: fb.to_rgb8(&ToneMapping::default()),
+        non_finite: fb
+            .sums
+            .iter()
+            .filter(|c| (0..3).any(|i| !c[i].is_finite()))
+            .count(),
+    }
+}
+
+fn is_black(pixels: &[u8]) -> bool {
+    pixels.iter().all(|&c| c == 0)
+}
+
+// A broken render must not be compared with, nor become, a reference
+fn check_sanity(kind: SceneKind, render: &Render) -> Result<(), String> {
+    if render.non_finite > 0 {
+        return Err(format!(
+            "{}: {} pixels are not finite",
+            kind, render.non_finite
+        ));
+    }
+    if is_black(&render.pixels) {
+        return Err(format!("{}: the render is all black", kind));
+    }
+
+    Ok(())
+}
+
+fn save(name: &str, render: &Render) {
+    image::save_buffer(
+        name,
+        &render.pixels,
+        render.width as u32,
+        render.height as u32,
+        image::ColorType::Rgb8,
+    )
+    .unwrap();
+}
+
+fn rmse(a: &[u8], b: &[u8]) -> f64 {
+    let sum: f64 = a
+        .iter()
+        .zip(b.iter())
+        .map(|(&a, &b)| (a as f64 - b as f64).powi(2))
+        .sum();
+
+    (sum / a.len() as f64).sqrt()
+}
+
+// Compare a scene with its reference, returns the error message on failure
+fn check_scene(kind: SceneKind) -> Result<(), String> {
+    let render = render_scene(kind);
+    let reference_file = format!("{}/{}.png", REFERENCE_DIR, kind);
+    check_sanity(kind, &render)?;
+
+    if env::var("UPDATE_GOLDEN").is_ok() {
+        fs::create_dir_all(REFERENCE_DIR).unwrap();
+        save(&reference_file, &render);
+        return Ok(());
+    }
+
+    if !Path::new(&reference_file).exists() {
+        return Err(format!(
+            "{}: no reference image {}, run with UPDATE_GOLDEN=1",
+            kind, reference_file
+        ));
+    }
+    let reference = image::open(&reference_file).unwrap().to_rgb8();
+    if is_black(reference.as_raw()) {
+        return Err(format!(
+            "{}: the reference {} is all black, regenerate it",
+            kind, reference_file
+        ));
+    }
+
+    let error = if (reference.width() as usize, reference.height() as usize)
+        != (render.width, render.height)
+    {
+        format!(
+            "{}: rendered {}x{}, the reference is {}x{}",
+            kind,
+            render.width,
+            render.height,
+            reference.width(),
+            reference.height()
+        )
+    } else {
+        let rmse = rmse(&render.pixels, reference.as_raw());
+        if rmse <= MAX_RMSE {
+            return Ok(());
+        }
+
+        let diff = Render {
+            width: render.width,
+            height: render.height,
+            non_finite: 0,
+            pixels: render
+                .pixels
+                .chunks(BYTES_PER_PIXEL)
+                .zip(reference.as_raw().chunks(BYTES_PER_PIXEL))
+                .flat_map(|(a, b)| {
+                    (0..BYTES_PER_PIXEL).map(move |c| {
+                        let d = (a[c] as f64 - b[c] as f64).abs() * DIFF_SCALE;
+                        d.min(255.0) as u8
+                    })
+                })
+                .collect(),
+        };
+        fs::create_dir_all(OUTPUT_DIR).unwrap();
+        save(&format!("{}/{}-diff.png", OUTPUT_DIR, kind), &diff);
+
+        format!("{}: RMSE {:.2} > {}", kind, rmse, MAX_RMSE)
+    };
+
+    fs::create_dir_all(OUTPUT_DIR).unwrap();
+    save(&format!("{}/{}.png", OUTPUT_DIR, kind), &render);
+
+    Err(format!("{} (see {})", error, OUTPUT_DIR))
+}
+
+#[test]
+fn test_golden_images() {
+    let failures: Vec<String> = SceneKind::value_variants()
+        .iter()
+        .filter_map(|kind| check_scene(*kind).err())
+        .collect();
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn test_broken_renders_are_refused() {
+    let render = |pixels: Vec<u8>, non_finite: usize| Render {
+        width: 2,
+        height: 1,
+        pixels,
+        non_finite,
+    };
+
+    assert!(check_sanity(SceneKind::CornellBox, &render(vec![0, 0, 0, 9, 9, 9], 0)).is_ok());
+    assert!(check_sanity(SceneKind::CornellBox, &render(vec![0, 0, 0, 9, 9, 9], 1)).is_err());
+    assert!(check_sanity(SceneKind::CornellBox, &render(vec![0; 6], 0)).is_err());
+}
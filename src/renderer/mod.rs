@@ -1,24 +1,36 @@
 use std::fmt::Display;
+use std::process;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
 use clap::{ArgEnum, PossibleValue};
 use indicatif::{ProgressBar, ProgressStyle};
 
-use crate::camera::ray::Ray;
-use crate::hittable::Hittable;
-use crate::materials::ScatterRecord;
-use crate::pdf::hittable::HittablePdf;
-use crate::pdf::mixture::MixturePdf;
-use crate::pdf::Pdf;
+use crate::geometry::traversal::global_stats;
 use crate::scene::Scene;
-// use crate::tools::random_double_range;
+use crate::tools::{hash_seed, random_double, reseed};
 use crate::vec3::Color;
 
+use aov::{AovSample, Aovs};
+use checkpoint::{checkpoint_path, remove_checkpoint, Checkpoint};
+use framebuffer::FrameBuffer;
+use preview::Preview;
+use tiles::{tiles, Tile, TileOrder};
+use tonemap::ToneMapping;
+
+pub mod aov;
+pub mod checkpoint;
+pub mod denoise;
+pub mod framebuffer;
+#[cfg(test)]
+mod golden;
 pub mod parallel_crossbeam;
 pub mod parallel_rayon;
+pub mod preview;
 pub mod sequential;
-
-// TODO render to frame buffer using https://lib.rs/crates/pixels or https://lib.rs/crates/minifb
+pub mod tiles;
+pub mod tonemap;
 
 pub(crate) const BYTES_PER_PIXEL: usize = 3;
 
@@ -59,82 +71,487 @@ impl RendererKind {
     }
 }
 
-pub(crate) fn ray_color(
-    r: &Ray,
-    background: &Color,
-    world: &Vec<Box<dyn Hittable>>,
-    light: &Vec<Box<dyn Hittable>>,
-    depth: u32,
-) -> Color {
-    // If we've exceeded the ray bounce limit, no more light is gathered.
-    if depth == 0 {
-        return Color::zero();
+// Samples added to every pixel by one call of a renderer
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Pass {
+    pub samples: u32,
+    pub seed: u64,
+    // also record the AOVs of the camera rays
+    pub aovs: bool,
+}
+
+// Render the next samples of the pixel (x, y), y = 0 being the top row, which
+// already has `count` samples. Returns their sum and number, and the AOVs of
+// the pass, pixels are never rendered beyond `samples_per_pixel`.
+pub(crate) fn sample_pixel(
+    scene: &Scene,
+    pass: Pass,
+    x: usize,
+    y: usize,
+    count: u32,
+) -> (Color, u32, Option<AovSample>) {
+    let width = scene.cfg.width;
+    let height = scene.cfg.height;
+    let samples = pass
+        .samples
+        .min(scene.cfg.samples_per_pixel.saturating_sub(count));
+
+    let mut c = Color::zero();
+    let mut aov = pass.aovs.then(AovSample::default);
+    for s in count..count + samples {
+        // every sample has its own random sequence, whatever the renderer and the threads
+        reseed(hash_seed(&[pass.seed, x as u64, y as u64, s as u64]));
+
+        let u = (x as f64 + random_double()) / (width as f64 - 1f64);
+        let v = ((height - y) as f64 + random_double()) / (height as f64 - 1f64);
+
+        let r = scene.camera.get_ray(u, v);
+
+        c += scene.integrator.ray_color(&r, scene);
+        // after the integrator, which keeps the beauty pass of a seed unchanged
+        if let Some(aov) = aov.as_mut() {
+            aov.add(&r, scene);
+        }
     }
 
-    if let Some(hr) = world.hit(r, 0.001, std::f64::INFINITY) {
-        let emitted = hr
-            .material
-            .emitted(r, &hr, hr.get_u(), hr.get_v(), &hr.get_p());
-
-        let scatter = hr.material.scatter(r, &hr);
-        if let Some(srec) = scatter {
-            match srec {
-                ScatterRecord::Specular { attenuation, ray } => {
-                    return attenuation * ray_color(&ray, background, world, light, depth - 1);
-                }
-                ScatterRecord::Diffuse { attenuation, pdf } => {
-                    let light_pdf = HittablePdf::new(light, hr.get_p());
-                    let mixed_pdf = MixturePdf::new(light_pdf, pdf);
-
-                    let scattered = Ray::new(hr.get_p(), mixed_pdf.generate(), r.time());
-                    let pdf_val = mixed_pdf.value(&scattered.direction());
-
-                    return emitted
-                        + attenuation
-                            * hr.material.scattering_pdf(r, &hr, &scattered)
-                            * ray_color(&scattered, background, world, light, depth - 1)
-                            / pdf_val;
-                }
-            }
+    (c, samples, aov)
+}
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+// The first Ctrl-C stops the render after the current pixels, the second one exits
+pub fn install_interrupt_handler() -> Result<(), String> {
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            process::exit(130);
         }
+        eprintln!("\nInterrupted, writing the image (press Ctrl-C again to abort)");
+    })
+    .map_err(|e| format!("Failed to install the Ctrl-C handler: {}", e))
+}
+
+// Stop condition checked by the renderers before each pixel
+pub(crate) struct Budget {
+    deadline: Option<Instant>,
+}
+
+impl Budget {
+    pub(crate) fn exhausted(&self) -> bool {
+        INTERRUPTED.load(Ordering::Relaxed) || self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+}
 
-        return emitted;
+// Add the samples of a pass to every pixel of `fb`
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn render_pass(
+    renderer: RendererKind,
+    scene: &Scene,
+    bar: &ProgressBar,
+    fb: &mut FrameBuffer,
+    tiles: &[Tile],
+    pass: Pass,
+    budget: &Budget,
+    on_tile: &mut dyn FnMut(&FrameBuffer),
+) {
+    match renderer {
+        RendererKind::ParallelCrossbeam => {
+            parallel_crossbeam::render(scene, bar, fb, tiles, pass, budget, on_tile)
+        }
+        RendererKind::ParallelRayon => {
+            parallel_rayon::render(scene, bar, fb, tiles, pass, budget, on_tile)
+        }
+        RendererKind::Sequential => {
+            sequential::render(scene, bar, fb, tiles, pass, budget, on_tile)
+        }
     }
+}
 
-    *background
+#[derive(Debug, Default, Clone)]
+pub struct RenderOptions {
+    // render in passes of this many samples per pixel, writing the image after each one
+    pub pass_samples: Option<u32>,
+    // stop after this duration, keeping what was rendered so far
+    pub time_limit: Option<Duration>,
+    // continue from the checkpoint of the output image
+    pub resume: bool,
+    // used for 8 bit outputs
+    pub tone_mapping: ToneMapping,
+    // seed of the random sequences of the samples
+    pub seed: u64,
+    // width and height of the tiles, in pixels
+    pub tile_size: usize,
+    pub tile_order: TileOrder,
+    // show the render in progress in a window
+    pub preview: bool,
+    // write the preview frames to this directory
+    pub preview_dump: Option<String>,
+    // write the albedo, normal, depth, object and material ids along the image
+    pub aovs: bool,
 }
 
-pub fn render(scene: &Scene, renderer: RendererKind, name: &str) {
+// Render the scene into `name`, returns the frame buffer of the image
+pub fn render(
+    scene: &Scene,
+    renderer: RendererKind,
+    name: &str,
+    options: &RenderOptions,
+) -> Result<FrameBuffer, String> {
     let width = scene.cfg.width;
     let height = scene.cfg.height;
+    let samples_per_pixel = scene.cfg.samples_per_pixel;
 
-    let len = width * height * BYTES_PER_PIXEL;
-    // https://github.com/rust-lang/rust/issues/54628
-    let mut pixels = vec![0u8; len];
+    let checkpoint_file = checkpoint_path(name);
+    let mut state = if options.resume {
+        // they would only average the new samples, or be empty
+        if options.aovs {
+            return Err(format!(
+                "Cannot resume {}: the AOVs are not saved in the checkpoints",
+                checkpoint_file
+            ));
+        }
+        let state = Checkpoint::load(&checkpoint_file)?;
+        if (state.fb.width, state.fb.height) != (width, height) {
+            return Err(format!(
+                "Cannot resume {}: it was rendered at {}x{}, not {}x{}",
+                checkpoint_file, state.fb.width, state.fb.height, width, height
+            ));
+        }
+        if state.seed != options.seed {
+            return Err(format!(
+                "Cannot resume {}: it was rendered with seed {}, not {}",
+                checkpoint_file, state.seed, options.seed
+            ));
+        }
+        if state.scene_hash != scene.hash {
+            return Err(format!(
+                "Cannot resume {}: the scene has changed",
+                checkpoint_file
+            ));
+        }
+        println!(
+            "Resuming {} from {} samples per pixel",
+            name,
+            state.fb.min_samples()
+        );
+        state
+    } else {
+        Checkpoint {
+            scene_hash: scene.hash,
+            seed: options.seed,
+            fb: FrameBuffer::new(width, height),
+        }
+    };
+
+    if options.aovs {
+        state.fb.aovs = Some(Aovs::new(width * height));
+    }
+
+    let budget = Budget {
+        deadline: options.time_limit.map(|limit| Instant::now() + limit),
+    };
+
+    let pass_samples = options
+        .pass_samples
+        .unwrap_or(samples_per_pixel)
+        .clamp(1, samples_per_pixel.max(1));
+    let remaining = samples_per_pixel.saturating_sub(state.fb.min_samples());
+    let passes = remaining.div_ceil(pass_samples);
 
     // FIXME https://docs.rs/indicatif/0.15.0/indicatif/#iterators
-    let bar_len = width as u64 * height as u64;
+    let bar_len = width as u64 * height as u64 * passes as u64;
     let bar = ProgressBar::new(bar_len);
     bar.set_style(ProgressStyle::default_bar().template(
         "{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] ({pos}/{len}, ETA {eta})",
     ));
     bar.set_draw_delta(bar_len / 100);
 
-    match renderer {
-        RendererKind::ParallelCrossbeam => parallel_crossbeam::render(&scene, &bar, &mut pixels),
-        RendererKind::ParallelRayon => parallel_rayon::render(&scene, &bar, &mut pixels),
-        RendererKind::Sequential => sequential::render(&scene, &bar, &mut pixels),
+    let tiles = tiles(
+        width,
+        height,
+        options.tile_size,
+        options.tile_order,
+        state.seed,
+    );
+    let pass = Pass {
+        samples: pass_samples,
+        seed: state.seed,
+        aovs: options.aovs,
+    };
+    let mut preview = if options.preview || options.preview_dump.is_some() {
+        Some(Preview::new(
+            width,
+            height,
+            options.preview,
+            options.preview_dump.clone(),
+            options.tone_mapping,
+        )?)
+    } else {
+        None
+    };
+
+    // the checkpoint is saved after every pass of the progressive renders, and
+    // by any render stopped early, until it is completed
+    let progressive =
+        options.pass_samples.is_some() || options.time_limit.is_some() || options.resume;
+
+    let start = Instant::now();
+    let rays_before = global_stats().rays;
+    while state.fb.min_samples() < samples_per_pixel && !budget.exhausted() {
+        let mut on_tile = |fb: &FrameBuffer| {
+            if let Some(preview) = preview.as_mut() {
+                preview.tile_done(fb)
+            }
+        };
+        render_pass(
+            renderer,
+            scene,
+            &bar,
+            &mut state.fb,
+            &tiles,
+            pass,
+            &budget,
+            &mut on_tile,
+        );
+        if let Some(preview) = preview.as_mut() {
+            preview.pass_done(&state.fb)?;
+        }
+
+        state.fb.save(name, &options.tone_mapping)?;
+        if progressive && state.fb.min_samples() < samples_per_pixel {
+            state.save(&checkpoint_file)?;
+        }
+    }
+
+    if state.fb.min_samples() < samples_per_pixel {
+        // a stopped pass leaves some pixels with fewer samples, they are
+        // completed first when resuming
+        state.save(&checkpoint_file)?;
+        bar.abandon();
+        println!(
+            "Render stopped early, {} of {} samples per pixel in {}, continue with --resume",
+            state.fb.min_samples(),
+            samples_per_pixel,
+            name
+        );
+    } else {
+        bar.finish();
+        // nothing was rendered when resuming a complete render
+        state.fb.save(name, &options.tone_mapping)?;
+        remove_checkpoint(&checkpoint_file)?;
     }
 
-    bar.finish();
-
-    // write the generated image (format is deduced based on extension)
-    image::save_buffer(
-        name,
-        pixels.as_slice(),
-        width as u32,
-        height as u32,
-        image::ColorType::Rgb8,
-    )
-    .unwrap();
+    let rays = global_stats().rays - rays_before;
+    println!(
+        "Traced {} rays, {:.2} Mrays/s",
+        rays,
+        rays as f64 / start.elapsed().as_secs_f64() / 1E6
+    );
+
+    Ok(state.fb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::description::SceneDescription;
+    use crate::scene::{Overrides, SceneKind};
+    use image::GenericImageView;
+    use std::fs;
+    use std::path::Path;
+
+    fn render_seeded(
+        renderer: RendererKind,
+        pass_samples: u32,
+        seed: u64,
+        tile_size: usize,
+        tile_order: TileOrder,
+    ) -> FrameBuffer {
+        reseed(hash_seed(&[seed]));
+        let mut desc = SceneDescription::from_kind(SceneKind::CornellBoxSmoke, false, "");
+        desc.config.apply_overrides(&Overrides {
+            width: Some(12),
+            samples_per_pixel: Some(4),
+            ..Default::default()
+        });
+        let scene = desc.build().unwrap();
+
+        let mut fb = FrameBuffer::new(scene.cfg.width, scene.cfg.height);
+        let budget = Budget { deadline: None };
+        let bar = ProgressBar::hidden();
+        let tiles = tiles(fb.width, fb.height, tile_size, tile_order, seed);
+        let pass = Pass {
+            samples: pass_samples,
+            seed,
+            aovs: false,
+        };
+        while fb.min_samples() < scene.cfg.samples_per_pixel {
+            render_pass(
+                renderer,
+                &scene,
+                &bar,
+                &mut fb,
+                &tiles,
+                pass,
+                &budget,
+                &mut |_| {},
+            );
+        }
+
+        fb
+    }
+
+    #[test]
+    fn test_passes_and_time_limit() {
+        let mut desc = SceneDescription::from_kind(SceneKind::CornellBox, false, "");
+        desc.config.apply_overrides(&Overrides {
+            width: Some(8),
+            samples_per_pixel: Some(7),
+            ..Default::default()
+        });
+        let scene = desc.build().unwrap();
+        let name = std::env::temp_dir()
+            .join(format!("raytracer-test-{}-passes.png", process::id()))
+            .to_string_lossy()
+            .to_string();
+        let checkpoint_file = checkpoint_path(&name);
+
+        // passes of 3 samples, the last one only adds 1
+        let options = RenderOptions {
+            pass_samples: Some(3),
+            ..Default::default()
+        };
+        let fb = render(&scene, RendererKind::Sequential, &name, &options).unwrap();
+        assert!(fb.counts.iter().all(|&count| count == 7));
+        assert!(!Path::new(&checkpoint_file).exists());
+
+        // stopped before the first pass, and completed by resuming
+        let options = RenderOptions {
+            pass_samples: Some(3),
+            time_limit: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        let fb = render(&scene, RendererKind::ParallelRayon, &name, &options).unwrap();
+        assert!(fb.counts.iter().all(|&count| count == 0));
+        assert!(Path::new(&checkpoint_file).exists());
+
+        // not with the AOVs, which the checkpoint doesn't have
+        let options = RenderOptions {
+            resume: true,
+            aovs: true,
+            ..Default::default()
+        };
+        assert!(render(&scene, RendererKind::Sequential, &name, &options).is_err());
+
+        let options = RenderOptions {
+            resume: true,
+            ..Default::default()
+        };
+        let fb = render(&scene, RendererKind::ParallelCrossbeam, &name, &options).unwrap();
+        assert!(fb.counts.iter().all(|&count| count == 7));
+        assert!(!Path::new(&checkpoint_file).exists());
+
+        fs::remove_file(&name).unwrap();
+    }
+
+    #[test]
+    fn test_tiles_are_reported_as_they_are_added() {
+        let mut desc = SceneDescription::from_kind(SceneKind::CornellBox, false, "");
+        desc.config.apply_overrides(&Overrides {
+            width: Some(12),
+            samples_per_pixel: Some(1),
+            ..Default::default()
+        });
+        let scene = desc.build().unwrap();
+        let budget = Budget { deadline: None };
+        let bar = ProgressBar::hidden();
+        let pass = Pass {
+            samples: 1,
+            seed: 3,
+            aovs: false,
+        };
+
+        for renderer in RendererKind::value_variants() {
+            let mut fb = FrameBuffer::new(scene.cfg.width, scene.cfg.height);
+            let tiles = tiles(fb.width, fb.height, 4, TileOrder::Scanline, 3);
+
+            // each call sees one more tile of samples
+            let mut added = Vec::new();
+            render_pass(
+                *renderer,
+                &scene,
+                &bar,
+                &mut fb,
+                &tiles,
+                pass,
+                &budget,
+                &mut |fb| added.push(fb.counts.iter().sum::<u32>()),
+            );
+            let expected: Vec<u32> = tiles
+                .iter()
+                .scan(0, |sum, tile| {
+                    *sum += tile.pixel_count() as u32;
+                    Some(*sum)
+                })
+                .collect();
+            assert_eq!(added, expected, "{}", renderer);
+        }
+    }
+
+    #[test]
+    fn test_preview_dump() {
+        let mut desc = SceneDescription::from_kind(SceneKind::CornellBox, false, "");
+        desc.config.apply_overrides(&Overrides {
+            width: Some(10),
+            samples_per_pixel: Some(5),
+            ..Default::default()
+        });
+        let scene = desc.build().unwrap();
+        let dir = std::env::temp_dir().join(format!("raytracer-test-{}-preview", process::id()));
+        let name = dir.join("image.png").to_string_lossy().to_string();
+
+        // one frame per pass, the last one is the image
+        let options = RenderOptions {
+            pass_samples: Some(2),
+            preview_dump: Some(dir.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        render(&scene, RendererKind::ParallelRayon, &name, &options).unwrap();
+
+        let frames: Vec<_> = (0..3)
+            .map(|i| image::open(dir.join(format!("frame-{:04}.png", i))).unwrap())
+            .collect();
+        assert!(!dir.join("frame-0003.png").exists());
+        for frame in frames.iter() {
+            let (width, height) = (scene.cfg.width as u32, scene.cfg.height as u32);
+            assert_eq!(frame.dimensions(), (width, height));
+        }
+        assert_ne!(frames[0].as_bytes(), frames[2].as_bytes());
+        assert_eq!(frames[2].as_bytes(), image::open(&name).unwrap().as_bytes());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_seeded_renders_are_identical() {
+        let reference = render_seeded(RendererKind::Sequential, 2, 7, 16, TileOrder::Scanline);
+
+        let single_thread = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let renders = [
+            render_seeded(RendererKind::ParallelCrossbeam, 2, 7, 5, TileOrder::Random),
+            render_seeded(RendererKind::ParallelRayon, 2, 7, 3, TileOrder::Spiral),
+            single_thread.install(|| {
+                render_seeded(RendererKind::ParallelRayon, 2, 7, 4, TileOrder::Hilbert)
+            }),
+        ];
+        for fb in renders.iter() {
+            assert_eq!(fb.counts, reference.counts);
+            assert_eq!(fb.sums, reference.sums);
+        }
+
+        let other_seed = render_seeded(RendererKind::Sequential, 2, 8, 16, TileOrder::Scanline);
+        assert_ne!(other_seed.sums, reference.sums);
+    }
 }
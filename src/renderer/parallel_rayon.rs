@@ -1,42 +1,59 @@
+use crossbeam_channel::{unbounded, TryRecvError};
 use indicatif::ProgressBar;
 use rayon::prelude::*;
+use rayon::Yield;
 
 use crate::scene::Scene;
-use crate::tools::random_double;
-use crate::vec3::Color;
 
-use super::{ray_color, BYTES_PER_PIXEL};
-
-pub(crate) fn render(scene: &Scene, bar: &ProgressBar, pixels: &mut Vec<u8>) {
-    // Iterate over the coordinates and pixels of the image
-    let width = scene.cfg.width;
-    let height = scene.cfg.height;
-    let samples_per_pixel = scene.cfg.samples_per_pixel;
-    let max_depth = scene.cfg.max_depth;
-
-    pixels
-        .par_chunks_mut(BYTES_PER_PIXEL)
-        .into_par_iter()
-        .enumerate()
-        .for_each(|(idx, pixel)| {
-            let y = height - (idx / width);
-            let x = idx % width;
-
-            let mut c = Color::zero();
-            for _s in 0..samples_per_pixel {
-                let u = (x as f64 + random_double()) / (width as f64 - 1f64);
-                let v = (y as f64 + random_double()) / (height as f64 - 1f64);
-
-                let r = scene.camera.get_ray(u, v);
+use super::framebuffer::FrameBuffer;
+use super::tiles::{add_tile, render_tile, Tile};
+use super::{Budget, Pass};
+
+pub(crate) fn render(
+    scene: &Scene,
+    bar: &ProgressBar,
+    fb: &mut FrameBuffer,
+    tiles: &[Tile],
+    pass: Pass,
+    budget: &Budget,
+    on_tile: &mut dyn FnMut(&FrameBuffer),
+) {
+    let counts = fb.counts.clone();
+    let counts = &counts;
+
+    let (tx, rx) = unbounded();
+
+    rayon::in_place_scope(|s| {
+        // Iterate over the tiles of the image in the pool
+        s.spawn(move |_| {
+            tiles.par_iter().for_each_with(tx, |tx, tile| {
+                let samples = render_tile(scene, pass, tile, counts, budget);
+                bar.inc(tile.pixel_count() as u64);
+                tx.send((tile, samples)).unwrap();
+            });
+        });
 
-                c += ray_color(&r, &scene.background, &scene.world, &scene.light, max_depth);
+        // and add them on the calling thread as they come
+        loop {
+            match rx.try_recv() {
+                Ok((tile, samples)) => {
+                    add_tile(fb, tile, &samples);
+                    on_tile(fb);
+                }
+                Err(TryRecvError::Empty) => match rayon::yield_now() {
+                    // outside of the pool, wait for its threads
+                    None => {
+                        if let Ok((tile, samples)) = rx.recv() {
+                            add_tile(fb, tile, &samples);
+                            on_tile(fb);
+                        }
+                    }
+                    // a thread of the pool renders tiles too while it waits
+                    Some(Yield::Executed) => {}
+                    Some(Yield::Idle) => std::thread::yield_now(),
+                },
+                Err(TryRecvError::Disconnected) => break,
             }
-
-            let avg = c.to_u8_avg_gamma2(samples_per_pixel);
-            pixel[0] = avg[0];
-            pixel[1] = avg[1];
-            pixel[2] = avg[2];
-
-            bar.inc(1);
-        });
+        }
+    });
 }
@@ -0,0 +1,138 @@
+// Live preview of the render in progress, in a window (`preview` feature) or
+// as numbered frames written to a directory for headless runs.
+use std::fs;
+use std::time::{Duration, Instant};
+
+use super::framebuffer::FrameBuffer;
+use super::tonemap::ToneMapping;
+
+// refreshes of the window while tiles come in
+const REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
+pub struct Preview {
+    #[cfg(feature = "preview")]
+    window: Option<minifb::Window>,
+    dump_dir: Option<String>,
+    frame: usize,
+    last_refresh: Instant,
+    tone_mapping: ToneMapping,
+}
+
+#[cfg(feature = "preview")]
+fn open_window(width: usize, height: usize) -> Option<minifb::Window> {
+    let options = minifb::WindowOptions {
+        resize: true,
+        ..Default::default()
+    };
+
+    match minifb::Window::new("raytracer", width, height, options) {
+        Ok(mut window) => {
+            // refreshes are throttled by the renderer, never wait for the display
+            window.set_target_fps(0);
+            Some(window)
+        }
+        Err(e) => {
+            eprintln!("Preview window unavailable, rendering without it: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "preview"))]
+fn open_window(_width: usize, _height: usize) {
+    eprintln!("Built without the preview feature, rendering without the preview window");
+}
+
+impl Preview {
+    // `window` asks for a window, falling back to no window when it can't be opened;
+    // frames are written to `dump_dir` after every pass
+    pub fn new(
+        width: usize,
+        height: usize,
+        window: bool,
+        dump_dir: Option<String>,
+        tone_mapping: ToneMapping,
+    ) -> Result<Preview, String> {
+        if let Some(dir) = &dump_dir {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create preview directory {}: {}", dir, e))?;
+        }
+
+        #[cfg(feature = "preview")]
+        let window = if window {
+            open_window(width, height)
+        } else {
+            None
+        };
+        #[cfg(not(feature = "preview"))]
+        if window {
+            open_window(width, height);
+        }
+
+        Ok(Preview {
+            #[cfg(feature = "preview")]
+            window,
+            dump_dir,
+            frame: 0,
+            last_refresh: Instant::now(),
+            tone_mapping,
+        })
+    }
+
+    // Some tiles were added, refresh the window if it was not done recently
+    pub fn tile_done(&mut self, fb: &FrameBuffer) {
+        if self.last_refresh.elapsed() >= REFRESH_INTERVAL {
+            self.refresh_window(fb);
+        }
+    }
+
+    // A pass is complete, refresh the window and dump a frame
+    pub fn pass_done(&mut self, fb: &FrameBuffer) -> Result<(), String> {
+        self.refresh_window(fb);
+
+        if let Some(dir) = &self.dump_dir {
+            let name = format!("{}/frame-{:04}.png", dir, self.frame);
+            image::save_buffer(
+                &name,
+                fb.to_rgb8(&self.tone_mapping).as_slice(),
+                fb.width as u32,
+                fb.height as u32,
+                image::ColorType::Rgb8,
+            )
+            .map_err(|e| format!("Failed to write {}: {}", name, e))?;
+        }
+        self.frame += 1;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "preview")]
+    fn refresh_window(&mut self, fb: &FrameBuffer) {
+        self.last_refresh = Instant::now();
+
+        let window = match &mut self.window {
+            Some(window) => window,
+            None => return,
+        };
+        if !window.is_open() || window.is_key_down(minifb::Key::Escape) {
+            // closing the preview does not stop the render
+            self.window = None;
+            return;
+        }
+
+        let buffer: Vec<u32> = fb
+            .to_rgb8(&self.tone_mapping)
+            .chunks(super::BYTES_PER_PIXEL)
+            .map(|p| (p[0] as u32) << 16 | (p[1] as u32) << 8 | p[2] as u32)
+            .collect();
+        if let Err(e) = window.update_with_buffer(&buffer, fb.width, fb.height) {
+            eprintln!("Preview window failed, closing it: {}", e);
+            self.window = None;
+        }
+    }
+
+    #[cfg(not(feature = "preview"))]
+    fn refresh_window(&mut self, _fb: &FrameBuffer) {
+        self.last_refresh = Instant::now();
+    }
+}
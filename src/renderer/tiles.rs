@@ -0,0 +1,230 @@
+// Split of the image in tiles, the unit of work of the renderers
+use std::fmt::Display;
+use std::str::FromStr;
+
+use clap::{ArgEnum, PossibleValue};
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use crate::geometry::traversal;
+use crate::scene::Scene;
+use crate::vec3::Color;
+
+use super::aov::AovSample;
+use super::framebuffer::FrameBuffer;
+use super::{sample_pixel, Budget, Pass};
+
+#[derive(ArgEnum, Debug, Default, PartialEq, Clone, Copy)]
+pub enum TileOrder {
+    // rows of tiles from the top
+    Scanline,
+    // along a Hilbert curve, neighbour tiles are rendered close in time
+    #[default]
+    Hilbert,
+    // from the centre of the image outwards
+    Spiral,
+    Random,
+}
+
+impl FromStr for TileOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        for variant in Self::value_variants() {
+            if variant.to_possible_value().unwrap().matches(s, false) {
+                return Ok(*variant);
+            }
+        }
+        Err(format!("Invalid variant: {}", s))
+    }
+}
+
+impl Display for TileOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .fmt(f)
+    }
+}
+
+impl TileOrder {
+    pub fn possible_values() -> impl Iterator<Item = PossibleValue<'static>> {
+        Self::value_variants()
+            .iter()
+            .filter_map(ArgEnum::to_possible_value)
+    }
+}
+
+// Pixels [x0, x1) x [y0, y1), y = 0 being the top row
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tile {
+    pub x0: usize,
+    pub y0: usize,
+    pub x1: usize,
+    pub y1: usize,
+}
+
+impl Tile {
+    pub fn pixel_count(&self) -> usize {
+        (self.x1 - self.x0) * (self.y1 - self.y0)
+    }
+
+    pub fn pixels(&self) -> impl Iterator<Item = (usize, usize)> {
+        let (x0, x1) = (self.x0, self.x1);
+
+        (self.y0..self.y1).flat_map(move |y| (x0..x1).map(move |x| (x, y)))
+    }
+}
+
+// Distance along a Hilbert curve covering a n x n grid, n being a power of 2
+fn hilbert_index(n: usize, mut x: usize, mut y: usize) -> usize {
+    let mut d = 0;
+    let mut s = n / 2;
+
+    while s > 0 {
+        let rx = ((x & s) > 0) as usize;
+        let ry = ((y & s) > 0) as usize;
+        d += s * s * ((3 * rx) ^ ry);
+
+        // rotate the quadrant
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - (x & (s - 1));
+                y = s - 1 - (y & (s - 1));
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+
+    d
+}
+
+// Tiles covering the image in the given order, `seed` shuffles the random order
+pub fn tiles(
+    width: usize,
+    height: usize,
+    tile_size: usize,
+    order: TileOrder,
+    seed: u64,
+) -> Vec<Tile> {
+    let tile_size = tile_size.max(1);
+    let columns = width.div_ceil(tile_size);
+    let rows = height.div_ceil(tile_size);
+
+    // (column, row) of every tile, in scanline order
+    let mut grid: Vec<(usize, usize)> = (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .collect();
+
+    match order {
+        TileOrder::Scanline => {}
+        TileOrder::Hilbert => {
+            let n = columns.max(rows).next_power_of_two();
+            grid.sort_by_key(|&(column, row)| hilbert_index(n, column, row));
+        }
+        TileOrder::Spiral => {
+            // ring around the central tile first, then angle within the ring
+            let cx = (columns as f64 - 1.0) / 2.0;
+            let cy = (rows as f64 - 1.0) / 2.0;
+            let key = |&(column, row): &(usize, usize)| {
+                let dx = column as f64 - cx;
+                let dy = row as f64 - cy;
+                (dx.abs().max(dy.abs()), dy.atan2(dx))
+            };
+            grid.sort_by(|a, b| key(a).partial_cmp(&key(b)).unwrap());
+        }
+        TileOrder::Random => grid.shuffle(&mut SmallRng::seed_from_u64(seed)),
+    }
+
+    grid.iter()
+        .map(|&(column, row)| Tile {
+            x0: column * tile_size,
+            y0: row * tile_size,
+            x1: ((column + 1) * tile_size).min(width),
+            y1: ((row + 1) * tile_size).min(height),
+        })
+        .collect()
+}
+
+// Render the next samples of every pixel of a tile (see `sample_pixel`), in
+// `Tile::pixels` order. Pixels are skipped once the budget is exhausted.
+pub(crate) fn render_tile(
+    scene: &Scene,
+    pass: Pass,
+    tile: &Tile,
+    counts: &[u32],
+    budget: &Budget,
+) -> Vec<(Color, u32, Option<AovSample>)> {
+    let width = scene.cfg.width;
+
+    let samples = tile
+        .pixels()
+        .map(|(x, y)| {
+            if budget.exhausted() {
+                return (Color::zero(), 0, None);
+            }
+
+            sample_pixel(scene, pass, x, y, counts[y * width + x])
+        })
+        .collect();
+    // the rays of the worker threads are all counted when the render ends
+    traversal::flush();
+
+    samples
+}
+
+// Add the samples of a tile rendered by `render_tile`
+pub(crate) fn add_tile(
+    fb: &mut FrameBuffer,
+    tile: &Tile,
+    samples: &[(Color, u32, Option<AovSample>)],
+) {
+    for ((x, y), (c, n, aov)) in tile.pixels().zip(samples.iter()) {
+        fb.add(x, y, *c, *n);
+        if let Some(aov) = aov {
+            fb.add_aov(x, y, aov, *n);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tiles_cover_the_image_once() {
+        let (width, height) = (37, 23);
+
+        for order in TileOrder::value_variants() {
+            let mut covered = vec![0; width * height];
+            for tile in tiles(width, height, 8, *order, 1) {
+                for (x, y) in tile.pixels() {
+                    covered[y * width + x] += 1;
+                }
+            }
+
+            assert!(covered.iter().all(|&c| c == 1), "{} order", order);
+        }
+    }
+
+    #[test]
+    fn test_tile_orders() {
+        let first = |order| tiles(64, 64, 16, order, 1)[0];
+
+        assert_eq!(first(TileOrder::Scanline), first(TileOrder::Hilbert));
+        // 4x4 tiles, the centre is between the 4 central ones
+        let centre = first(TileOrder::Spiral);
+        assert!((16..48).contains(&centre.x0) && (16..48).contains(&centre.y0));
+
+        // consecutive tiles of the Hilbert order are neighbours
+        let hilbert = tiles(64, 64, 16, TileOrder::Hilbert, 1);
+        for pair in hilbert.windows(2) {
+            let dx = (pair[0].x0 as i64 - pair[1].x0 as i64).abs();
+            let dy = (pair[0].y0 as i64 - pair[1].y0 as i64).abs();
+            assert_eq!(dx + dy, 16);
+        }
+    }
+}
@@ -0,0 +1,220 @@
+// Mapping of linear radiance to display values for 8 bit outputs
+use std::fmt::Display;
+use std::str::FromStr;
+
+use clap::{ArgEnum, PossibleValue};
+
+use crate::vec3::Color;
+
+#[derive(ArgEnum, Debug, PartialEq, Clone, Copy)]
+pub enum ToneMapper {
+    // no compression, values above 1 are clipped
+    Clamp,
+    Reinhard,
+    // Reinhard reaching 1 at the white point
+    ReinhardExtended,
+    // Narkowicz fit of the ACES filmic curve
+    Aces,
+    // Hable's filmic curve
+    Uncharted2,
+}
+
+// Opto-electronic transfer function applied after tone mapping
+#[derive(ArgEnum, Debug, PartialEq, Clone, Copy)]
+pub enum Oetf {
+    Srgb,
+    // square root, as in the books
+    Gamma2,
+}
+
+impl FromStr for ToneMapper {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        for variant in Self::value_variants() {
+            if variant.to_possible_value().unwrap().matches(s, false) {
+                return Ok(*variant);
+            }
+        }
+        Err(format!("Invalid variant: {}", s))
+    }
+}
+
+impl Display for ToneMapper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .fmt(f)
+    }
+}
+
+impl ToneMapper {
+    pub fn possible_values() -> impl Iterator<Item = PossibleValue<'static>> {
+        Self::value_variants()
+            .iter()
+            .filter_map(ArgEnum::to_possible_value)
+    }
+}
+
+impl FromStr for Oetf {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        for variant in Self::value_variants() {
+            if variant.to_possible_value().unwrap().matches(s, false) {
+                return Ok(*variant);
+            }
+        }
+        Err(format!("Invalid variant: {}", s))
+    }
+}
+
+impl Display for Oetf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .fmt(f)
+    }
+}
+
+impl Oetf {
+    pub fn possible_values() -> impl Iterator<Item = PossibleValue<'static>> {
+        Self::value_variants()
+            .iter()
+            .filter_map(ArgEnum::to_possible_value)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ToneMapping {
+    pub operator: ToneMapper,
+    // exposure in stops, the radiance is scaled by 2^exposure
+    pub exposure: f64,
+    // smallest radiance mapped to white by the extended Reinhard operator
+    pub white: f64,
+    pub oetf: Oetf,
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        ToneMapping {
+            operator: ToneMapper::Clamp,
+            exposure: 0.0,
+            white: 4.0,
+            oetf: Oetf::Srgb,
+        }
+    }
+}
+
+fn uncharted2_curve(x: f64) -> f64 {
+    let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+
+    (x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f) - e / f
+}
+
+impl ToneMapping {
+    // Tone map one linear channel to [0, 1]
+    pub fn tone_map(&self, x: f64) -> f64 {
+        let x = x * 2f64.powf(self.exposure);
+
+        let y = match self.operator {
+            ToneMapper::Clamp => x,
+            ToneMapper::Reinhard => x / (1.0 + x),
+            ToneMapper::ReinhardExtended => x * (1.0 + x / (self.white * self.white)) / (1.0 + x),
+            ToneMapper::Aces => (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14),
+            ToneMapper::Uncharted2 => {
+                const EXPOSURE_BIAS: f64 = 2.0;
+                const WHITE: f64 = 11.2;
+
+                uncharted2_curve(EXPOSURE_BIAS * x) / uncharted2_curve(WHITE)
+            }
+        };
+
+        y.clamp(0.0, 1.0)
+    }
+
+    pub fn encode(&self, y: f64) -> f64 {
+        match self.oetf {
+            Oetf::Srgb => {
+                if y <= 0.0031308 {
+                    12.92 * y
+                } else {
+                    1.055 * y.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            Oetf::Gamma2 => y.sqrt(),
+        }
+    }
+
+    // Tone mapped and encoded 8 bit value of a linear color
+    pub fn quantize(&self, c: Color) -> [u8; 3] {
+        let mut rgb = [0u8; 3];
+
+        for (i, v) in rgb.iter_mut().enumerate() {
+            let y = self.encode(self.tone_map(c[i]));
+            *v = (y.clamp(0.0, 0.999) * 256.0) as u8;
+        }
+
+        rgb
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(operator: ToneMapper) -> ToneMapping {
+        ToneMapping {
+            operator,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_operators_are_monotonic_and_bounded() {
+        for operator in ToneMapper::value_variants() {
+            let tm = mapping(*operator);
+            let mut previous = tm.tone_map(0.0);
+            assert!(previous.abs() < 1E-3, "{} of 0 is {}", operator, previous);
+
+            for i in 1..1000 {
+                let y = tm.tone_map(i as f64 * 0.05);
+                assert!(y >= previous, "{} is not monotonic", operator);
+                assert!(y <= 1.0);
+                previous = y;
+            }
+        }
+    }
+
+    #[test]
+    fn test_reinhard() {
+        let tm = mapping(ToneMapper::Reinhard);
+        assert_eq!(tm.tone_map(1.0), 0.5);
+
+        let tm = ToneMapping {
+            exposure: 1.0,
+            ..tm
+        };
+        assert_eq!(tm.tone_map(0.5), 0.5);
+
+        let tm = ToneMapping {
+            operator: ToneMapper::ReinhardExtended,
+            exposure: 0.0,
+            ..tm
+        };
+        assert_eq!(tm.tone_map(tm.white), 1.0);
+    }
+
+    #[test]
+    fn test_srgb_oetf() {
+        let tm = ToneMapping::default();
+
+        assert_eq!(tm.encode(0.0), 0.0);
+        assert!((tm.encode(1.0) - 1.0).abs() < 1E-12);
+        assert!((tm.encode(0.0031308) - 0.04045).abs() < 1E-5);
+        assert!((tm.encode(0.18) - 0.46135).abs() < 1E-4);
+        assert_eq!(tm.quantize(Color::new(0.0, 1.0, 100.0)), [0, 255, 255]);
+    }
+}
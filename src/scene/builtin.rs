@@ -0,0 +1,666 @@
+// Built-in scenes, expressed as scene descriptions
+use std::collections::BTreeMap;
+
+use crate::tools::{random_double, random_double_range};
+use crate::vec3::{Color, Point3, Vec3};
+
+use super::description::{
+    CameraDescription, MaterialDescription, ObjectDescription, SceneDescription,
+    TextureDescription, TransformDescription,
+};
+use super::{Config, SceneKind};
+
+fn lambertian(color: Color) -> MaterialDescription {
+    MaterialDescription::Lambertian {
+        albedo: TextureDescription::Solid(color),
+    }
+}
+
+fn diffuse_light(color: Color) -> MaterialDescription {
+    MaterialDescription::DiffuseLight {
+        emit: TextureDescription::Solid(color),
+    }
+}
+
+fn checker() -> TextureDescription {
+    TextureDescription::Checker {
+        odd: Box::new(TextureDescription::Solid(Color::new(0.2, 0.3, 0.1))),
+        even: Box::new(TextureDescription::Solid(Color::new(0.9, 0.9, 0.9))),
+    }
+}
+
+impl SceneDescription {
+    pub fn from_kind(kind: SceneKind, moving: bool, filename: &str) -> SceneDescription {
+        // Image config
+        let config = Config::new(&kind, moving);
+
+        // Camera
+        let lookfrom = match kind {
+            SceneKind::SimpleLight => Point3::new(26.0, 3.0, 6.0),
+            SceneKind::CornellBox
+            | SceneKind::CornellBoxSmoke
+            | SceneKind::CornellBoxMetal
+            | SceneKind::CornellBoxGlassSphere => Point3::new(278.0, 278.0, -800.0),
+            SceneKind::FinalScene => Point3::new(478.0, 278.0, -600.0),
+            _ => Point3::new(13.0, 2.0, 3.0),
+        };
+
+        let lookat = match kind {
+            SceneKind::SimpleLight => Point3::new(0.0, 2.0, 0.0),
+            SceneKind::CornellBox
+            | SceneKind::CornellBoxSmoke
+            | SceneKind::CornellBoxMetal
+            | SceneKind::CornellBoxGlassSphere => Point3::new(278.0, 278.0, 0.0),
+            SceneKind::FinalScene => Point3::new(278.0, 278.0, 0.0),
+            _ => Point3::zero(),
+        };
+
+        let vfov = match kind {
+            SceneKind::CornellBox
+            | SceneKind::CornellBoxSmoke
+            | SceneKind::CornellBoxMetal
+            | SceneKind::CornellBoxGlassSphere
+            | SceneKind::FinalScene => 40.0,
+            _ => 20.0,
+        };
+        let aperture = match kind {
+            SceneKind::RandomUniform | SceneKind::RandomChecker => 0.1,
+            _ => 0.0,
+        };
+
+        let camera = CameraDescription {
+            lookfrom,
+            lookat,
+            vup: Vec3::new(0.0, 1.0, 0.0),
+            vfov,
+            aperture,
+            focus_dist: 10.0,
+        };
+
+        let background = match kind {
+            SceneKind::SimpleLight
+            | SceneKind::CornellBox
+            | SceneKind::CornellBoxSmoke
+            | SceneKind::CornellBoxMetal
+            | SceneKind::CornellBoxGlassSphere
+            | SceneKind::FinalScene => Color::zero(),
+            _ => Color::new(0.7, 0.8, 1.0),
+        };
+
+        let mut desc = SceneDescription {
+            config,
+            camera,
+            background,
+            environment: None,
+            sky: None,
+            materials: BTreeMap::new(),
+            prototypes: BTreeMap::new(),
+            world: Vec::new(),
+            lights: Vec::new(),
+        };
+
+        match kind {
+            SceneKind::RandomUniform | SceneKind::RandomChecker => {
+                desc.create_random(kind);
+            }
+            SceneKind::TwoCheckerSpheres => desc.create_two_spheres(),
+            SceneKind::TwoPerlinSpheres => desc.create_two_perlin_spheres(),
+            SceneKind::ImageSphere => desc.create_image_sphere(filename),
+            SceneKind::SimpleLight => desc.create_simple_light(),
+            SceneKind::CornellBox => desc.create_cornell_box(),
+            SceneKind::CornellBoxSmoke => desc.create_cornell_box_smoke(),
+            SceneKind::FinalScene => desc.create_final_scene(filename),
+            SceneKind::CornellBoxMetal => desc.create_cornell_box_metal(),
+            SceneKind::CornellBoxGlassSphere => desc.create_cornell_box_glass_sphere(),
+        }
+
+        desc
+    }
+
+    fn add_material(&mut self, name: &str, material: MaterialDescription) {
+        self.materials.insert(name.to_string(), material);
+    }
+
+    fn add_prototype(&mut self, name: &str, object: ObjectDescription) {
+        self.prototypes.insert(name.to_string(), object);
+    }
+
+    // The Cornell box walls and its ceiling light, shared by all the Cornell box variants
+    fn create_cornell_box_walls(&mut self) {
+        self.add_material("red", lambertian(Color::new(0.65, 0.05, 0.05)));
+        self.add_material("white", lambertian(Color::new(0.73, 0.73, 0.73)));
+        self.add_material("green", lambertian(Color::new(0.12, 0.45, 0.15)));
+        self.add_material("light", diffuse_light(Color::new(15.0, 15.0, 15.0)));
+
+        // The Box itself
+        self.world.push(ObjectDescription::YzRect {
+            y0: 0.0,
+            y1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 555.0,
+            material: "green".to_string(),
+        });
+        self.world.push(ObjectDescription::YzRect {
+            y0: 0.0,
+            y1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 0.0,
+            material: "red".to_string(),
+        });
+
+        let light = ObjectDescription::XzRect {
+            x0: 213.0,
+            x1: 343.0,
+            z0: 227.0,
+            z1: 332.0,
+            k: 554.0,
+            material: "light".to_string(),
+        };
+        self.world.push(ObjectDescription::FlipNormals {
+            object: Box::new(light),
+        });
+
+        self.world.push(ObjectDescription::XzRect {
+            x0: 0.0,
+            x1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 0.0,
+            material: "white".to_string(),
+        });
+        self.world.push(ObjectDescription::XzRect {
+            x0: 0.0,
+            x1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 555.0,
+            material: "white".to_string(),
+        });
+
+        self.world.push(ObjectDescription::XyRect {
+            x0: 0.0,
+            x1: 555.0,
+            y0: 0.0,
+            y1: 555.0,
+            k: 555.0,
+            material: "white".to_string(),
+        });
+    }
+
+    fn cornell_box_block(
+        p1: Point3,
+        angle: f64,
+        offset: Vec3,
+        material: &str,
+    ) -> ObjectDescription {
+        let cube = ObjectDescription::Cube {
+            p0: Point3::zero(),
+            p1,
+            material: material.to_string(),
+        };
+        let rotated = ObjectDescription::RotateY {
+            angle,
+            object: Box::new(cube),
+        };
+
+        ObjectDescription::Translate {
+            offset,
+            object: Box::new(rotated),
+        }
+    }
+
+    fn create_cornell_box_glass_sphere(&mut self) {
+        self.create_cornell_box_walls();
+        self.add_material("glass", MaterialDescription::Dielectric { ref_idx: 1.5 });
+
+        // The inner rectangular box
+        self.world.push(Self::cornell_box_block(
+            Point3::new(165.0, 330.0, 165.0),
+            15.0,
+            Vec3::new(265.0, 0.0, 295.0),
+            "white",
+        ));
+
+        self.world.push(ObjectDescription::Sphere {
+            center: Point3::new(190.0, 90.0, 190.0),
+            radius: 90.0,
+            material: "glass".to_string(),
+        });
+    }
+
+    fn create_cornell_box_metal(&mut self) {
+        self.create_cornell_box_walls();
+        self.add_material(
+            "aluminum",
+            MaterialDescription::Metal {
+                albedo: Color::new(0.8, 0.85, 0.88),
+                fuzz: 0.0,
+            },
+        );
+
+        // The inner rectangular boxes
+        self.world.push(Self::cornell_box_block(
+            Point3::new(165.0, 330.0, 165.0),
+            15.0,
+            Vec3::new(265.0, 0.0, 295.0),
+            "aluminum",
+        ));
+        self.world.push(Self::cornell_box_block(
+            Point3::new(165.0, 165.0, 165.0),
+            -18.0,
+            Vec3::new(130.0, 0.0, 65.0),
+            "white",
+        ));
+    }
+
+    fn create_final_scene(&mut self, filename: &str) {
+        // random boxes for ground
+        self.add_material("ground", lambertian(Color::new(0.48, 0.83, 0.53)));
+        self.add_prototype(
+            "ground-box",
+            ObjectDescription::Cube {
+                p0: Point3::zero(),
+                p1: Point3::new(1.0, 1.0, 1.0),
+                material: "ground".to_string(),
+            },
+        );
+        let mut boxes1 = Vec::new();
+        let boxes_per_side = 20;
+        for i in 0..boxes_per_side {
+            for j in 0..boxes_per_side {
+                let w = 100.0;
+                let x0 = -1000.0 + i as f64 * w;
+                let z0 = -1000.0 + j as f64 * w;
+                let y1 = random_double_range(1.0, 101.0);
+
+                boxes1.push(ObjectDescription::Instance {
+                    prototype: "ground-box".to_string(),
+                    transforms: vec![
+                        TransformDescription::Scale(Vec3::new(w, y1, w)),
+                        TransformDescription::Translate(Vec3::new(x0, 0.0, z0)),
+                    ],
+                    material: None,
+                });
+            }
+        }
+        self.world.push(ObjectDescription::Bvh { objects: boxes1 });
+
+        // light
+        self.add_material("light", diffuse_light(Color::new(7.0, 7.0, 7.0)));
+        let light = ObjectDescription::XzRect {
+            x0: 123.0,
+            x1: 423.0,
+            z0: 147.0,
+            z1: 412.0,
+            k: 554.0,
+            material: "light".to_string(),
+        };
+        self.world.push(ObjectDescription::FlipNormals {
+            object: Box::new(light),
+        });
+
+        // moving sphere
+        let center1 = Point3::new(400.0, 400.0, 200.0);
+        let center2 = center1 + Vec3::new(30.0, 0.0, 0.0);
+        self.add_material("moving", lambertian(Color::new(0.7, 0.3, 0.1)));
+        self.world.push(ObjectDescription::MovingSphere {
+            center0: center1,
+            center1: center2,
+            time0: 0.0,
+            time1: 1.0,
+            radius: 50.0,
+            material: "moving".to_string(),
+        });
+
+        // dielectric sphere
+        self.add_material(
+            "dielectric",
+            MaterialDescription::Dielectric { ref_idx: 1.5 },
+        );
+        self.world.push(ObjectDescription::Sphere {
+            center: Point3::new(260.0, 150.0, 45.0),
+            radius: 50.0,
+            material: "dielectric".to_string(),
+        });
+
+        // metal sphere
+        self.add_material(
+            "metal",
+            MaterialDescription::Metal {
+                albedo: Color::new(0.8, 0.8, 0.9),
+                fuzz: 1.0,
+            },
+        );
+        self.world.push(ObjectDescription::Sphere {
+            center: Point3::new(0.0, 150.0, 145.0),
+            radius: 50.0,
+            material: "metal".to_string(),
+        });
+
+        //  blue subsurface reflection sphere
+        let boundary = ObjectDescription::Sphere {
+            center: Point3::new(360.0, 150.0, 145.0),
+            radius: 70.0,
+            material: "dielectric".to_string(),
+        };
+        self.world.push(boundary.clone());
+        self.world.push(ObjectDescription::ConstantMedium {
+            boundary: Box::new(boundary),
+            density: 0.2,
+            albedo: TextureDescription::Solid(Color::new(0.2, 0.4, 0.9)),
+        });
+
+        // mist
+        let boundary = ObjectDescription::Sphere {
+            center: Point3::new(0.0, 0.0, 0.0),
+            radius: 5000.0,
+            material: "dielectric".to_string(),
+        };
+        self.world.push(ObjectDescription::ConstantMedium {
+            boundary: Box::new(boundary),
+            density: 0.0001,
+            albedo: TextureDescription::Solid(Color::new(1.0, 1.0, 1.0)),
+        });
+
+        // earth mapped sphere
+        self.add_material(
+            "earth",
+            MaterialDescription::Lambertian {
+                albedo: TextureDescription::Image {
+                    filename: filename.to_string(),
+                },
+            },
+        );
+        self.world.push(ObjectDescription::Sphere {
+            center: Point3::new(400.0, 200.0, 400.0),
+            radius: 100.0,
+            material: "earth".to_string(),
+        });
+
+        // perlin noise sphere
+        self.add_material(
+            "noise",
+            MaterialDescription::Lambertian {
+                albedo: TextureDescription::Noise { scale: 0.1 },
+            },
+        );
+        self.world.push(ObjectDescription::Sphere {
+            center: Point3::new(220.0, 280.0, 300.0),
+            radius: 80.0,
+            material: "noise".to_string(),
+        });
+
+        // random sphere within a box
+        self.add_material("white", lambertian(Color::new(0.73, 0.73, 0.73)));
+        self.add_prototype(
+            "small-sphere",
+            ObjectDescription::Sphere {
+                center: Point3::zero(),
+                radius: 10.0,
+                material: "white".to_string(),
+            },
+        );
+        let mut boxes2 = Vec::new();
+        let ns = 1000;
+        for _i in 0..ns {
+            boxes2.push(ObjectDescription::Instance {
+                prototype: "small-sphere".to_string(),
+                transforms: vec![TransformDescription::Translate(Point3::random_range(
+                    0.0, 165.0,
+                ))],
+                material: None,
+            });
+        }
+        let rotated = ObjectDescription::RotateY {
+            angle: 15.0,
+            object: Box::new(ObjectDescription::Bvh { objects: boxes2 }),
+        };
+        self.world.push(ObjectDescription::Translate {
+            offset: Vec3::new(-100.0, 270.0, 395.0),
+            object: Box::new(rotated),
+        });
+    }
+
+    fn create_cornell_box_smoke(&mut self) {
+        self.create_cornell_box_walls();
+
+        // The inner rectangular boxes with smoke
+        self.world.push(ObjectDescription::ConstantMedium {
+            boundary: Box::new(Self::cornell_box_block(
+                Point3::new(165.0, 330.0, 165.0),
+                15.0,
+                Vec3::new(265.0, 0.0, 295.0),
+                "white",
+            )),
+            density: 0.01,
+            albedo: TextureDescription::Solid(Color::zero()),
+        });
+        self.world.push(ObjectDescription::ConstantMedium {
+            boundary: Box::new(Self::cornell_box_block(
+                Point3::new(165.0, 165.0, 165.0),
+                -18.0,
+                Vec3::new(130.0, 0.0, 65.0),
+                "white",
+            )),
+            density: 0.01,
+            albedo: TextureDescription::Solid(Color::new(1.0, 1.0, 1.0)),
+        });
+    }
+
+    fn create_cornell_box(&mut self) {
+        self.create_cornell_box_walls();
+
+        // The inner rectangular boxes
+        self.world.push(Self::cornell_box_block(
+            Point3::new(165.0, 330.0, 165.0),
+            15.0,
+            Vec3::new(265.0, 0.0, 295.0),
+            "white",
+        ));
+        self.world.push(Self::cornell_box_block(
+            Point3::new(165.0, 165.0, 165.0),
+            -18.0,
+            Vec3::new(130.0, 0.0, 65.0),
+            "white",
+        ));
+    }
+
+    fn create_simple_light(&mut self) {
+        self.create_two_perlin_spheres();
+
+        // Rectangle light
+        self.add_material("light", diffuse_light(Color::new(4.0, 4.0, 4.0)));
+        let light = ObjectDescription::XyRect {
+            x0: 3.0,
+            x1: 5.0,
+            y0: 1.0,
+            y1: 3.0,
+            k: -2.0,
+            material: "light".to_string(),
+        };
+        self.world.push(light);
+    }
+
+    fn create_image_sphere(&mut self, filename: &str) {
+        // sphere
+        self.add_material(
+            "image",
+            MaterialDescription::Lambertian {
+                albedo: TextureDescription::Image {
+                    filename: filename.to_string(),
+                },
+            },
+        );
+        self.world.push(ObjectDescription::Sphere {
+            center: Point3::zero(),
+            radius: 2.0,
+            material: "image".to_string(),
+        });
+    }
+
+    fn create_two_perlin_spheres(&mut self) {
+        self.add_material(
+            "noise",
+            MaterialDescription::Lambertian {
+                albedo: TextureDescription::Noise { scale: 4.0 },
+            },
+        );
+
+        // ground
+        self.world.push(ObjectDescription::Sphere {
+            center: Point3::new(0.0, -1000.0, 0.0),
+            radius: 1000.0,
+            material: "noise".to_string(),
+        });
+
+        // sphere
+        self.world.push(ObjectDescription::Sphere {
+            center: Point3::new(0.0, 2.0, 0.0),
+            radius: 2.0,
+            material: "noise".to_string(),
+        });
+    }
+
+    fn create_two_spheres(&mut self) {
+        self.add_material(
+            "checker",
+            MaterialDescription::Lambertian { albedo: checker() },
+        );
+
+        self.world.push(ObjectDescription::Sphere {
+            center: Point3::new(0.0, -10.0, 0.0),
+            radius: 10.0,
+            material: "checker".to_string(),
+        });
+
+        self.world.push(ObjectDescription::Sphere {
+            center: Point3::new(0.0, 10.0, 0.0),
+            radius: 10.0,
+            material: "checker".to_string(),
+        });
+    }
+
+    fn create_random(&mut self, kind: SceneKind) {
+        let mut world = Vec::new();
+
+        // ground
+        match kind {
+            SceneKind::RandomUniform => {
+                self.add_material("ground", lambertian(Color::new(0.5, 0.5, 0.5)));
+                world.push(ObjectDescription::Sphere {
+                    center: Point3::new(0.0, -100.5, -1.0),
+                    radius: 100.0,
+                    material: "ground".to_string(),
+                });
+            }
+            SceneKind::RandomChecker => {
+                self.add_material(
+                    "ground",
+                    MaterialDescription::Lambertian { albedo: checker() },
+                );
+                world.push(ObjectDescription::Sphere {
+                    center: Point3::new(0.0, -1000.0, 0.0),
+                    radius: 1000.0,
+                    material: "ground".to_string(),
+                });
+            }
+            _ => panic!("Invalid kind expect one of the Random ones: {:?}", kind),
+        }
+
+        // random part, every small sphere gets its own material
+        for a in -11..11 {
+            for b in -11..11 {
+                let choose_mat = random_double();
+                let center = Point3::new(
+                    a as f64 + 0.9 * random_double(),
+                    0.2,
+                    b as f64 + 0.9 * random_double(),
+                );
+
+                if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
+                    let name = format!("sphere_{}_{}", a, b);
+
+                    if choose_mat < 0.8 {
+                        // diffuse
+                        let albedo = Color::random() * Color::random();
+                        self.add_material(&name, lambertian(albedo));
+                        if self.config.time0 == self.config.time1 {
+                            // simple sphere
+                            world.push(ObjectDescription::Sphere {
+                                center,
+                                radius: 0.2,
+                                material: name,
+                            });
+                        } else {
+                            // moving sphere
+                            let center2 =
+                                center + Vec3::new(0.0, random_double_range(0.0, 0.5), 0.0);
+                            world.push(ObjectDescription::MovingSphere {
+                                center0: center,
+                                center1: center2,
+                                time0: 0.0,
+                                time1: 1.0,
+                                radius: 0.2,
+                                material: name,
+                            });
+                        }
+                    } else if choose_mat < 0.95 {
+                        // metal
+                        let albedo = Color::random_range(0.5, 1.0);
+                        let fuzz = random_double_range(0.0, 0.5);
+                        self.add_material(&name, MaterialDescription::Metal { albedo, fuzz });
+                        world.push(ObjectDescription::Sphere {
+                            center,
+                            radius: 0.2,
+                            material: name,
+                        });
+                    } else {
+                        // glass
+                        self.add_material(&name, MaterialDescription::Dielectric { ref_idx: 1.5 });
+                        world.push(ObjectDescription::Sphere {
+                            center,
+                            radius: 0.2,
+                            material: name,
+                        });
+                    }
+                }
+            }
+        }
+
+        // fixed part
+        self.add_material(
+            "material1",
+            MaterialDescription::Dielectric { ref_idx: 1.5 },
+        );
+        world.push(ObjectDescription::Sphere {
+            center: Point3::new(0.0, 1.0, 0.0),
+            radius: 1.0,
+            material: "material1".to_string(),
+        });
+
+        self.add_material("material2", lambertian(Color::new(0.4, 0.2, 0.1)));
+        world.push(ObjectDescription::Sphere {
+            center: Point3::new(-4.0, 1.0, 0.0),
+            radius: 1.0,
+            material: "material2".to_string(),
+        });
+
+        self.add_material(
+            "material3",
+            MaterialDescription::Metal {
+                albedo: Color::new(0.7, 0.6, 0.5),
+                fuzz: 0.0,
+            },
+        );
+        world.push(ObjectDescription::Sphere {
+            center: Point3::new(4.0, 1.0, 0.0),
+            radius: 1.0,
+            material: "material3".to_string(),
+        });
+
+        // With Bvh to speedup render
+        self.world.push(ObjectDescription::Bvh { objects: world });
+    }
+}
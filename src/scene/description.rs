@@ -0,0 +1,1547 @@
+// Declarative scene description, (de)serialized from/to RON files
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::f64::consts::PI;
+use std::fs;
+use std::sync::Arc;
+
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::background::{environment::EnvironmentMap, sky::Sky, Background};
+use crate::camera::camera::Camera;
+use crate::geometry::{
+    aarect::{XyRect, XzRect, YzRect},
+    bvh::{BvhKind, BvhNode, BvhStats},
+    cube::Cube,
+    emitting::Emitting,
+    flip_normals::FlipNormals,
+    identified::Identified,
+    instance::Instance,
+    mesh::Mesh,
+    moving_sphere::MovingSphere,
+    obj::load_obj,
+    rotate::RotateY,
+    sah_bvh::SahBvh,
+    sphere::Sphere,
+    transform::Transform,
+    translate::Translate,
+    triangle::Triangle,
+};
+use crate::hittable::Hittable;
+use crate::integrator;
+use crate::lights::{
+    area::AreaLight, directional::DirectionalLight, point::PointLight, spot::SpotLight, Light,
+    Lights,
+};
+use crate::materials::{
+    conductor::{ComplexIor, Conductor},
+    dielectric::Dielectric,
+    diffuse_light::DiffuseLight,
+    isotropic::Isotropic,
+    lambertian::Lambertian,
+    metal::Metal,
+    principled::Principled,
+    rough_dielectric::RoughDielectric,
+    Material,
+};
+use crate::matrix::Matrix4;
+use crate::media::{
+    density::{ConstantDensity, Density, GridDensity, PerlinDensity},
+    Media, Medium,
+};
+use crate::texture::{
+    checker::CheckerTexture, image::ImageTexture, noise::NoiseTexture, solid::SolidTexture, Texture,
+};
+use crate::vec3::{Color, Point3, Vec3};
+
+use super::{Config, Overrides, Scene};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SceneDescription {
+    pub config: Config,
+    pub camera: CameraDescription,
+    pub background: Color,
+    // lights the scene and replaces the background
+    #[serde(default)]
+    pub environment: Option<EnvironmentDescription>,
+    // daylight, in place of the background too
+    #[serde(default)]
+    pub sky: Option<SkyDescription>,
+    pub materials: BTreeMap<String, MaterialDescription>,
+    // shared objects placed in the scene with `Instance`
+    #[serde(default)]
+    pub prototypes: BTreeMap<String, ObjectDescription>,
+    // the emissive objects of the world are lights too, they are not listed
+    pub world: Vec<ObjectDescription>,
+    #[serde(default)]
+    pub lights: Vec<LightDescription>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CameraDescription {
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    pub vup: Vec3,
+    pub vfov: f64,
+    pub aperture: f64,
+    pub focus_dist: f64,
+}
+
+// Equirectangular .hdr or .exr image
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EnvironmentDescription {
+    pub filename: String,
+    // degrees around the vertical axis
+    #[serde(default)]
+    pub rotation: f64,
+    #[serde(default = "default_intensity")]
+    pub intensity: f64,
+}
+
+fn default_intensity() -> f64 {
+    1.0
+}
+
+// Preetham sky and its sun, angles in degrees
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SkyDescription {
+    // of the sun above the horizon, in [0, 90]
+    pub elevation: f64,
+    // of the sun around the vertical axis, from +x toward +z
+    #[serde(default)]
+    pub azimuth: f64,
+    // haziness of the atmosphere, from 2 to 10
+    #[serde(default = "default_turbidity")]
+    pub turbidity: f64,
+    #[serde(default = "default_intensity")]
+    pub intensity: f64,
+}
+
+fn default_turbidity() -> f64 {
+    3.0
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum TextureDescription {
+    Solid(Color),
+    // grey level, for the scalar parameters of materials
+    Value(f64),
+    Checker {
+        odd: Box<TextureDescription>,
+        even: Box<TextureDescription>,
+    },
+    Noise {
+        scale: f64,
+    },
+    Image {
+        filename: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum MaterialDescription {
+    Lambertian {
+        albedo: TextureDescription,
+    },
+    Metal {
+        albedo: Color,
+        fuzz: f64,
+    },
+    Dielectric {
+        ref_idx: f64,
+    },
+    // GGX microfacet materials, `roughness` in [0, 1]
+    Conductor {
+        ior: ComplexIor,
+        roughness: f64,
+    },
+    RoughDielectric {
+        ref_idx: f64,
+        roughness: f64,
+    },
+    DiffuseLight {
+        emit: TextureDescription,
+    },
+    Isotropic {
+        albedo: TextureDescription,
+    },
+    // parameters left out take the defaults of `Principled::from`
+    Principled {
+        base_color: TextureDescription,
+        #[serde(default = "zero")]
+        metallic: TextureDescription,
+        #[serde(default = "half")]
+        roughness: TextureDescription,
+        #[serde(default = "half")]
+        specular: TextureDescription,
+        #[serde(default = "zero")]
+        clearcoat: TextureDescription,
+        #[serde(default = "zero")]
+        sheen: TextureDescription,
+        #[serde(default = "zero")]
+        transmission: TextureDescription,
+        #[serde(default = "glass_ior")]
+        ior: f64,
+    },
+}
+
+fn zero() -> TextureDescription {
+    TextureDescription::Value(0.0)
+}
+
+fn half() -> TextureDescription {
+    TextureDescription::Value(0.5)
+}
+
+fn glass_ior() -> f64 {
+    1.5
+}
+
+// Materials are referenced by their name in the `materials` table
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum ObjectDescription {
+    Sphere {
+        center: Point3,
+        radius: f64,
+        material: String,
+    },
+    MovingSphere {
+        center0: Point3,
+        center1: Point3,
+        time0: f64,
+        time1: f64,
+        radius: f64,
+        material: String,
+    },
+    XyRect {
+        x0: f64,
+        x1: f64,
+        y0: f64,
+        y1: f64,
+        k: f64,
+        material: String,
+    },
+    XzRect {
+        x0: f64,
+        x1: f64,
+        z0: f64,
+        z1: f64,
+        k: f64,
+        material: String,
+    },
+    YzRect {
+        y0: f64,
+        y1: f64,
+        z0: f64,
+        z1: f64,
+        k: f64,
+        material: String,
+    },
+    Cube {
+        p0: Point3,
+        p1: Point3,
+        material: String,
+    },
+    Triangle {
+        v0: Point3,
+        v1: Point3,
+        v2: Point3,
+        material: String,
+    },
+    // normals and uvs are optional, when given there is one per position
+    Mesh {
+        positions: Vec<Point3>,
+        #[serde(default)]
+        normals: Vec<Vec3>,
+        #[serde(default)]
+        uvs: Vec<(f64, f64)>,
+        indices: Vec<[usize; 3]>,
+        material: String,
+    },
+    // the optional material overrides the ones from the MTL file
+    Obj {
+        filename: String,
+        #[serde(default)]
+        material: Option<String>,
+    },
+    Bvh {
+        objects: Vec<ObjectDescription>,
+    },
+    Translate {
+        offset: Vec3,
+        object: Box<ObjectDescription>,
+    },
+    RotateY {
+        angle: f64,
+        object: Box<ObjectDescription>,
+    },
+    FlipNormals {
+        object: Box<ObjectDescription>,
+    },
+    // transforms are applied to the object in list order
+    Transform {
+        transforms: Vec<TransformDescription>,
+        object: Box<ObjectDescription>,
+    },
+    // copy of a prototype, the optional material overrides the prototype ones
+    Instance {
+        prototype: String,
+        #[serde(default)]
+        transforms: Vec<TransformDescription>,
+        #[serde(default)]
+        material: Option<String>,
+    },
+    // media are objects of the world, not parts of other objects
+    ConstantMedium {
+        boundary: Box<ObjectDescription>,
+        density: f64,
+        albedo: TextureDescription,
+    },
+    // absorption and scattering coefficients per unit length, scaled by the
+    // density
+    Medium {
+        boundary: Box<ObjectDescription>,
+        density: DensityDescription,
+        sigma_a: Color,
+        sigma_s: Color,
+    },
+}
+
+impl ObjectDescription {
+    // The prototypes instanced in the object, with the material of each instance
+    fn instances<'a>(&'a self, found: &mut Vec<(&'a str, Option<&'a str>)>) {
+        match self {
+            ObjectDescription::Instance {
+                prototype,
+                material,
+                ..
+            } => found.push((prototype, material.as_deref())),
+            ObjectDescription::Bvh { objects } => {
+                for object in objects.iter() {
+                    object.instances(found);
+                }
+            }
+            ObjectDescription::Translate { object, .. }
+            | ObjectDescription::RotateY { object, .. }
+            | ObjectDescription::FlipNormals { object }
+            | ObjectDescription::Transform { object, .. } => object.instances(found),
+            ObjectDescription::ConstantMedium { boundary, .. }
+            | ObjectDescription::Medium { boundary, .. } => boundary.instances(found),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum DensityDescription {
+    Constant(f64),
+    // values at the centres of the cells of the box, x varying first, then y
+    Grid {
+        min: Point3,
+        max: Point3,
+        resolution: (usize, usize, usize),
+        values: Vec<f64>,
+    },
+    // Perlin turbulence of frequency `scale`, up to `density`
+    Perlin {
+        scale: f64,
+        density: f64,
+    },
+}
+
+// Lights without any geometry, intensities in W/sr and angles in degrees
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum LightDescription {
+    Point {
+        position: Point3,
+        intensity: Color,
+    },
+    // full intensity up to `falloff_start` from the axis, none beyond `angle`
+    Spot {
+        position: Point3,
+        direction: Vec3,
+        intensity: Color,
+        angle: f64,
+        falloff_start: f64,
+    },
+    // the light travels along `direction`
+    Directional {
+        direction: Vec3,
+        irradiance: Color,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum TransformDescription {
+    Translate(Vec3),
+    Scale(Vec3),
+    // angles in degrees
+    RotateX(f64),
+    RotateY(f64),
+    RotateZ(f64),
+    Rotate { axis: Vec3, angle: f64 },
+    // row major, the last row is expected to be (0, 0, 0, 1)
+    Matrix([[f64; 4]; 4]),
+}
+
+// Matrix of a list of transforms applied in order
+fn compose(transforms: &[TransformDescription]) -> Matrix4 {
+    transforms
+        .iter()
+        .fold(Matrix4::identity(), |m, t| t.matrix() * m)
+}
+
+impl TransformDescription {
+    pub fn matrix(&self) -> Matrix4 {
+        match self {
+            TransformDescription::Translate(offset) => Matrix4::translation(*offset),
+            TransformDescription::Scale(scale) => Matrix4::scaling(*scale),
+            TransformDescription::RotateX(angle) => Matrix4::rotation_x(*angle),
+            TransformDescription::RotateY(angle) => Matrix4::rotation_y(*angle),
+            TransformDescription::RotateZ(angle) => Matrix4::rotation_z(*angle),
+            TransformDescription::Rotate { axis, angle } => Matrix4::rotation(*axis, *angle),
+            TransformDescription::Matrix(m) => Matrix4::new(*m),
+        }
+    }
+}
+
+impl SceneDescription {
+    pub fn load(filename: &str) -> Result<SceneDescription, String> {
+        let content = fs::read_to_string(filename)
+            .map_err(|e| format!("Failed to read scene file {}: {}", filename, e))?;
+
+        ron::from_str(&content)
+            .map_err(|e| format!("Failed to parse scene file {}: {}", filename, e))
+    }
+
+    pub fn save(&self, filename: &str) -> Result<(), String> {
+        fs::write(filename, self.to_ron()?)
+            .map_err(|e| format!("Failed to write scene file {}: {}", filename, e))
+    }
+
+    // Configuration, environment and sky given on the command line
+    pub fn apply_overrides(&mut self, overrides: &Overrides) {
+        self.config.apply_overrides(overrides);
+        if let Some(environment) = &overrides.environment {
+            self.environment = Some(environment.clone());
+            self.sky = None;
+        }
+        if let Some(sky) = &overrides.sky {
+            self.sky = Some(sky.clone());
+            self.environment = None;
+        }
+    }
+
+    pub fn to_ron(&self) -> Result<String, String> {
+        // keep leaf values (vectors, textures) on a single line
+        let pretty = PrettyConfig::new().depth_limit(5);
+
+        ron::ser::to_string_pretty(self, pretty).map_err(|e| e.to_string())
+    }
+
+    // Stable hash of everything that changes the rendered image, except the
+    // resolution and the number of samples
+    pub fn hash(&self) -> u64 {
+        let mut desc = self.clone();
+        desc.config.width = 0;
+        desc.config.height = 0;
+        desc.config.samples_per_pixel = 0;
+        desc.config.bvh = BvhKind::default();
+
+        let ron = ron::to_string(&desc).expect("Scene descriptions are serializable");
+
+        // FNV-1a
+        ron.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+            (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+    }
+
+    // The prototypes whose primitives all emit in one of their instances at
+    // least, and the ones they instance
+    fn lit_prototypes(&self, builder: &SceneBuilder) -> Result<BTreeSet<&str>, String> {
+        let mut instances = Vec::new();
+        for desc in self.world.iter().chain(self.prototypes.values()) {
+            desc.instances(&mut instances);
+        }
+        let mut lit = BTreeSet::new();
+        for (prototype, material) in instances {
+            if let Some(material) = material {
+                if builder.material(material)?.emission().max_component() > 0.0 {
+                    lit.insert(prototype);
+                }
+            }
+        }
+
+        let mut pending: Vec<&str> = lit.iter().copied().collect();
+        while let Some(name) = pending.pop() {
+            let mut instances = Vec::new();
+            if let Some(desc) = self.prototypes.get(name) {
+                desc.instances(&mut instances);
+            }
+            for (prototype, _) in instances {
+                if lit.insert(prototype) {
+                    pending.push(prototype);
+                }
+            }
+        }
+
+        Ok(lit)
+    }
+
+    pub fn build(&self) -> Result<Scene, String> {
+        let cfg = self.config.clone();
+
+        let camera = Camera::new(
+            self.camera.lookfrom,
+            self.camera.lookat,
+            self.camera.vup,
+            self.camera.vfov,
+            cfg.ratio,
+            self.camera.aperture,
+            self.camera.focus_dist,
+            cfg.time0,
+            cfg.time1,
+        );
+
+        let mut builder = SceneBuilder {
+            materials: BTreeMap::new(),
+            time0: cfg.time0,
+            time1: cfg.time1,
+            bvh: cfg.bvh,
+            bvh_stats: RefCell::new(Vec::new()),
+            prototypes: BTreeMap::new(),
+        };
+        for (name, desc) in self.materials.iter() {
+            builder
+                .materials
+                .insert(name.clone(), builder.build_material(desc));
+        }
+        // in name order, a prototype can instance the ones before it
+        let lit = self.lit_prototypes(&builder)?;
+        for (name, desc) in self.prototypes.iter() {
+            let mut emitters = Emitters {
+                list: Vec::new(),
+                every_primitive: lit.contains(name.as_str()),
+            };
+            let object = builder.build_object(desc, &mut emitters)?;
+            builder.prototypes.insert(
+                name.clone(),
+                Prototype {
+                    object: Arc::from(object),
+                    emitters: emitters.list,
+                    every_primitive: emitters.every_primitive,
+                },
+            );
+        }
+
+        // object ids start at 1, 0 is for the background
+        let mut world: Vec<Box<dyn Hittable>> = Vec::new();
+        let mut media = Vec::new();
+        let mut emitters = Emitters::default();
+        for (i, desc) in self.world.iter().enumerate() {
+            match builder.build_medium(desc)? {
+                Some(medium) => media.push(medium),
+                None => {
+                    let object = builder.build_object(desc, &mut emitters)?;
+                    world.push(Box::new(Identified::new(object, i as u32 + 1)));
+                }
+            }
+        }
+        // the emissive objects of the world are lights by themselves, the first
+        // ones so that the indices carried by their hits are theirs
+        let mut lights = emitters
+            .list
+            .iter()
+            .map(|emitter| emitter.light())
+            .collect::<Result<Vec<_>, _>>()?;
+        for desc in self.lights.iter() {
+            lights.push(build_light(desc)?);
+        }
+
+        let background: Arc<dyn Background> = match (&self.environment, &self.sky) {
+            (Some(_), Some(_)) => {
+                return Err("A scene has an environment or a sky, not both".to_string())
+            }
+            (Some(env), None) => {
+                let map = Arc::new(EnvironmentMap::load(
+                    &env.filename,
+                    env.rotation,
+                    env.intensity,
+                )?);
+                // sampled like the other lights
+                lights.push(Box::new(map.clone() as Arc<dyn Light>));
+                map
+            }
+            (None, Some(sky)) => {
+                if !(0.0..=90.0).contains(&sky.elevation) {
+                    return Err(format!(
+                        "Sun elevation {} is not between 0 and 90 degrees",
+                        sky.elevation
+                    ));
+                }
+                if !(2.0..=10.0).contains(&sky.turbidity) {
+                    return Err(format!(
+                        "Turbidity {} is not between 2 and 10",
+                        sky.turbidity
+                    ));
+                }
+
+                let sky = Sky::new(sky.elevation, sky.azimuth, sky.turbidity, sky.intensity);
+                lights.push(Box::new(sky.sun()));
+                Arc::new(sky)
+            }
+            (None, None) => Arc::new(self.background),
+        };
+
+        let scene_radius = world
+            .bounding_box(cfg.time0, cfg.time1)
+            .map_or(1.0, |b| 0.5 * (b.max() - b.min()).length());
+        let lights = Lights::new(
+            lights,
+            cfg.light_sampler,
+            scene_radius,
+            cfg.time0,
+            cfg.time1,
+        );
+
+        Ok(Scene {
+            integrator: integrator::build(&cfg, &world),
+            cfg,
+            world,
+            lights,
+            media: Media::new(media),
+            materials: builder.materials.into_values().collect(),
+            camera,
+            background,
+            bvh_stats: builder.bvh_stats.into_inner(),
+            hash: self.hash(),
+        })
+    }
+}
+
+struct SceneBuilder {
+    materials: BTreeMap<String, Arc<dyn Material>>,
+    time0: f64,
+    time1: f64,
+    bvh: BvhKind,
+    bvh_stats: RefCell<Vec<BvhStats>>,
+    prototypes: BTreeMap<String, Prototype>,
+}
+
+// A shared object, with the emitters of its instances
+struct Prototype {
+    object: Arc<dyn Hittable>,
+    emitters: Vec<Emitter>,
+    // all the primitives are emitters, for the instances which make them emit
+    every_primitive: bool,
+}
+
+// Surface of an emitter, for the area of its light
+#[derive(Clone)]
+enum Surface {
+    Sphere(f64),
+    // edge vectors of parallelograms
+    Parallelograms(Vec<(Vec3, Vec3)>),
+    // two edge vectors
+    Triangle(Vec3, Vec3),
+}
+
+// latitudes of the integration over the transformed spheres, twice as many
+// longitudes
+const SPHERE_AREA_STEPS: usize = 64;
+
+impl Surface {
+    // Area once transformed by `matrix`. The edge vectors are transformed, the
+    // area elements of a sphere scale by |det M| |M^-T n| and are summed over a
+    // grid of latitudes and longitudes, which is exact for the similarities.
+    fn area(&self, matrix: &Matrix4) -> f64 {
+        let parallelogram = |a: &Vec3, b: &Vec3| {
+            matrix
+                .transform_vector(*a)
+                .cross(matrix.transform_vector(*b))
+        };
+
+        match self {
+            Surface::Parallelograms(edges) => edges
+                .iter()
+                .map(|(a, b)| parallelogram(a, b).length())
+                .sum(),
+            Surface::Triangle(a, b) => 0.5 * parallelogram(a, b).length(),
+            Surface::Sphere(radius) => {
+                let normal_matrix = match matrix.inverse() {
+                    Some(inverse) => inverse.transpose(),
+                    None => return 0.0,
+                };
+                let det = matrix.determinant3().abs();
+                let (n, step) = (SPHERE_AREA_STEPS, PI / SPHERE_AREA_STEPS as f64);
+
+                let mut area = 0.0;
+                for i in 0..n {
+                    let (theta0, theta1) = (i as f64 * step, (i + 1) as f64 * step);
+                    let theta = 0.5 * (theta0 + theta1);
+                    // solid angle of a cell of the band
+                    let cell = step * (theta0.cos() - theta1.cos());
+                    for j in 0..2 * n {
+                        let phi = (j as f64 + 0.5) * step;
+                        let normal = Vec3::new(
+                            theta.sin() * phi.cos(),
+                            theta.cos(),
+                            theta.sin() * phi.sin(),
+                        );
+                        area += cell * normal_matrix.transform_vector(normal).length();
+                    }
+                }
+
+                det * radius * radius * area
+            }
+        }
+    }
+}
+
+// An emissive primitive found while building an object, `matrix` moves it
+// into the frame of the object
+#[derive(Clone)]
+struct Emitter {
+    shape: Arc<dyn Hittable>,
+    matrix: Matrix4,
+    emission: Color,
+    surface: Surface,
+}
+
+impl Emitter {
+    // The same primitive made of `material`
+    fn with_material(&self, material: &Arc<dyn Material>) -> Result<Emitter, String> {
+        let shape = Instance::new(
+            self.shape.clone(),
+            Matrix4::identity(),
+            Some(material.clone()),
+        )?;
+
+        Ok(Emitter {
+            shape: Arc::new(shape),
+            matrix: self.matrix,
+            emission: material.emission(),
+            surface: self.surface.clone(),
+        })
+    }
+
+    fn light(&self) -> Result<Box<dyn Light>, String> {
+        let area = self.surface.area(&self.matrix);
+        let shape: Arc<dyn Hittable> = if self.matrix == Matrix4::identity() {
+            self.shape.clone()
+        } else {
+            Arc::new(Transform::new(self.shape.clone(), self.matrix)?)
+        };
+
+        Ok(Box::new(AreaLight::new(shape, self.emission, area)))
+    }
+}
+
+// The emitters found while building an object, in its frame
+#[derive(Default)]
+struct Emitters {
+    list: Vec<Emitter>,
+    // the primitives which don't emit are registered too, with no emission
+    every_primitive: bool,
+}
+
+fn move_emitters(emitters: &mut [Emitter], matrix: Matrix4) {
+    for emitter in emitters.iter_mut() {
+        emitter.matrix = matrix * emitter.matrix;
+    }
+}
+
+fn build_light(desc: &LightDescription) -> Result<Box<dyn Light>, String> {
+    let light: Box<dyn Light> = match desc {
+        LightDescription::Point {
+            position,
+            intensity,
+        } => Box::new(PointLight {
+            position: *position,
+            intensity: *intensity,
+        }),
+        LightDescription::Spot {
+            position,
+            direction,
+            intensity,
+            angle,
+            falloff_start,
+        } => {
+            if !(0.0..=180.0).contains(angle) {
+                return Err(format!(
+                    "Spot light angle {} is not between 0 and 180 degrees",
+                    angle
+                ));
+            }
+
+            Box::new(SpotLight::new(
+                *position,
+                *direction,
+                *intensity,
+                *angle,
+                *falloff_start,
+            ))
+        }
+        LightDescription::Directional {
+            direction,
+            irradiance,
+        } => Box::new(DirectionalLight::new(*direction, *irradiance)),
+    };
+
+    Ok(light)
+}
+
+impl SceneBuilder {
+    fn build_bvh(&self, objects: Vec<Box<dyn Hittable>>) -> Box<dyn Hittable> {
+        match self.bvh {
+            BvhKind::Median => {
+                let (bvh, stats) = BvhNode::with_stats(objects, self.time0, self.time1);
+                self.bvh_stats.borrow_mut().push(stats);
+                Box::new(bvh)
+            }
+            BvhKind::Sah => {
+                let bvh = SahBvh::new(objects, self.time0, self.time1);
+                self.bvh_stats.borrow_mut().push(*bvh.stats());
+                Box::new(bvh)
+            }
+        }
+    }
+
+    fn build_texture(&self, desc: &TextureDescription) -> Box<dyn Texture> {
+        match desc {
+            TextureDescription::Solid(color) => Box::new(SolidTexture::from(*color)),
+            TextureDescription::Value(v) => Box::new(SolidTexture::from(Color::new(*v, *v, *v))),
+            TextureDescription::Checker { odd, even } => Box::new(CheckerTexture {
+                odd: self.build_texture(odd),
+                even: self.build_texture(even),
+            }),
+            TextureDescription::Noise { scale } => Box::new(NoiseTexture::from(*scale)),
+            TextureDescription::Image { filename } => Box::new(ImageTexture::new(filename)),
+        }
+    }
+
+    fn build_material(&self, desc: &MaterialDescription) -> Arc<dyn Material> {
+        match desc {
+            MaterialDescription::Lambertian { albedo } => Arc::new(Lambertian {
+                albedo: self.build_texture(albedo),
+            }),
+            MaterialDescription::Metal { albedo, fuzz } => Arc::new(Metal::new(*albedo, *fuzz)),
+            MaterialDescription::Dielectric { ref_idx } => Arc::new(Dielectric::new(*ref_idx)),
+            MaterialDescription::Conductor { ior, roughness } => {
+                Arc::new(Conductor::new(*ior, *roughness))
+            }
+            MaterialDescription::RoughDielectric { ref_idx, roughness } => {
+                Arc::new(RoughDielectric::new(*ref_idx, *roughness))
+            }
+            MaterialDescription::DiffuseLight { emit } => Arc::new(DiffuseLight {
+                emit: self.build_texture(emit),
+            }),
+            MaterialDescription::Isotropic { albedo } => Arc::new(Isotropic {
+                albedo: self.build_texture(albedo),
+            }),
+            MaterialDescription::Principled {
+                base_color,
+                metallic,
+                roughness,
+                specular,
+                clearcoat,
+                sheen,
+                transmission,
+                ior,
+            } => Arc::new(Principled {
+                base_color: self.build_texture(base_color),
+                metallic: self.build_texture(metallic),
+                roughness: self.build_texture(roughness),
+                specular: self.build_texture(specular),
+                clearcoat: self.build_texture(clearcoat),
+                sheen: self.build_texture(sheen),
+                transmission: self.build_texture(transmission),
+                ior: *ior,
+            }),
+        }
+    }
+
+    fn material(&self, name: &str) -> Result<Arc<dyn Material>, String> {
+        self.materials
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("Unknown material: {}", name))
+    }
+
+    // The media of the world, which are traced apart from its objects. A
+    // constant medium only scatters, with the colour of its albedo.
+    fn build_medium(&self, desc: &ObjectDescription) -> Result<Option<Medium>, String> {
+        let medium = match desc {
+            ObjectDescription::ConstantMedium {
+                boundary,
+                density,
+                albedo,
+            } => Medium::new(
+                self.build_object(boundary, &mut Emitters::default())?,
+                Box::new(ConstantDensity { density: *density }),
+                Color::zero(),
+                Color::new(1.0, 1.0, 1.0),
+                Arc::new(Isotropic {
+                    albedo: self.build_texture(albedo),
+                }),
+            ),
+            ObjectDescription::Medium {
+                boundary,
+                density,
+                sigma_a,
+                sigma_s,
+            } => {
+                if (0..3).any(|c| sigma_a[c] < 0.0 || sigma_s[c] < 0.0) {
+                    return Err("The coefficients of a medium are positive".to_string());
+                }
+                let density: Box<dyn Density> = match density {
+                    DensityDescription::Constant(density) => {
+                        Box::new(ConstantDensity { density: *density })
+                    }
+                    DensityDescription::Grid {
+                        min,
+                        max,
+                        resolution: (nx, ny, nz),
+                        values,
+                    } => Box::new(GridDensity::new(
+                        *min,
+                        *max,
+                        [*nx, *ny, *nz],
+                        values.clone(),
+                    )?),
+                    DensityDescription::Perlin { scale, density } => {
+                        Box::new(PerlinDensity::new(*scale, *density))
+                    }
+                };
+                if density.max() < 0.0 {
+                    return Err("The density of a medium is positive".to_string());
+                }
+
+                Medium::new(
+                    self.build_object(boundary, &mut Emitters::default())?,
+                    density,
+                    *sigma_a,
+                    *sigma_s,
+                    Arc::new(Isotropic::from(Color::new(1.0, 1.0, 1.0))),
+                )
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some(medium))
+    }
+
+    // An emissive primitive is shared with the emitter registered for it, and
+    // its hits carry the index of the emitter in `emitters`
+    fn emissive(
+        &self,
+        object: Box<dyn Hittable>,
+        material: &Arc<dyn Material>,
+        surface: Surface,
+        emitters: &mut Emitters,
+    ) -> Box<dyn Hittable> {
+        let emission = material.emission();
+        if emission.max_component() <= 0.0 && !emitters.every_primitive {
+            return object;
+        }
+
+        let shape: Arc<dyn Hittable> = Arc::from(object);
+        let light = emitters.list.len();
+        emitters.list.push(Emitter {
+            shape: shape.clone(),
+            matrix: Matrix4::identity(),
+            emission,
+            surface,
+        });
+        Box::new(Emitting::new(shape, light))
+    }
+
+    // The triangles of a mesh in a BVH, each emissive one is an emitter
+    fn build_triangles(
+        &self,
+        triangles: Vec<Triangle>,
+        emitters: &mut Emitters,
+    ) -> Box<dyn Hittable> {
+        let triangles = triangles
+            .into_iter()
+            .map(|triangle| {
+                let (p0, p1, p2) = triangle.vertices();
+                let material = triangle.material.clone();
+                let surface = Surface::Triangle(p1 - p0, p2 - p0);
+                self.emissive(Box::new(triangle), &material, surface, emitters)
+            })
+            .collect();
+
+        self.build_bvh(triangles)
+    }
+
+    // The object of `desc`, its emissive primitives are added to `emitters`
+    // in the frame of the object
+    fn build_object(
+        &self,
+        desc: &ObjectDescription,
+        emitters: &mut Emitters,
+    ) -> Result<Box<dyn Hittable>, String> {
+        let first = emitters.list.len();
+        let object: Box<dyn Hittable> = match desc {
+            ObjectDescription::Sphere {
+                center,
+                radius,
+                material,
+            } => {
+                let material = self.material(material)?;
+                let sphere = Sphere {
+                    center: *center,
+                    radius: *radius,
+                    material: material.clone(),
+                };
+                let surface = Surface::Sphere(*radius);
+                self.emissive(Box::new(sphere), &material, surface, emitters)
+            }
+            ObjectDescription::MovingSphere {
+                center0,
+                center1,
+                time0,
+                time1,
+                radius,
+                material,
+            } => {
+                let material = self.material(material)?;
+                let sphere = MovingSphere {
+                    center0: *center0,
+                    center1: *center1,
+                    time0: *time0,
+                    time1: *time1,
+                    radius: *radius,
+                    material: material.clone(),
+                };
+                let surface = Surface::Sphere(*radius);
+                self.emissive(Box::new(sphere), &material, surface, emitters)
+            }
+            ObjectDescription::XyRect {
+                x0,
+                x1,
+                y0,
+                y1,
+                k,
+                material,
+            } => {
+                let material = self.material(material)?;
+                let rect = XyRect {
+                    x0: *x0,
+                    x1: *x1,
+                    y0: *y0,
+                    y1: *y1,
+                    k: *k,
+                    material: material.clone(),
+                };
+                let surface = Surface::Parallelograms(vec![(
+                    Vec3::new(x1 - x0, 0.0, 0.0),
+                    Vec3::new(0.0, y1 - y0, 0.0),
+                )]);
+                self.emissive(Box::new(rect), &material, surface, emitters)
+            }
+            ObjectDescription::XzRect {
+                x0,
+                x1,
+                z0,
+                z1,
+                k,
+                material,
+            } => {
+                let material = self.material(material)?;
+                let rect = XzRect {
+                    x0: *x0,
+                    x1: *x1,
+                    z0: *z0,
+                    z1: *z1,
+                    k: *k,
+                    material: material.clone(),
+                };
+                let surface = Surface::Parallelograms(vec![(
+                    Vec3::new(x1 - x0, 0.0, 0.0),
+                    Vec3::new(0.0, 0.0, z1 - z0),
+                )]);
+                self.emissive(Box::new(rect), &material, surface, emitters)
+            }
+            ObjectDescription::YzRect {
+                y0,
+                y1,
+                z0,
+                z1,
+                k,
+                material,
+            } => {
+                let material = self.material(material)?;
+                let rect = YzRect {
+                    y0: *y0,
+                    y1: *y1,
+                    z0: *z0,
+                    z1: *z1,
+                    k: *k,
+                    material: material.clone(),
+                };
+                let surface = Surface::Parallelograms(vec![(
+                    Vec3::new(0.0, y1 - y0, 0.0),
+                    Vec3::new(0.0, 0.0, z1 - z0),
+                )]);
+                self.emissive(Box::new(rect), &material, surface, emitters)
+            }
+            ObjectDescription::Cube { p0, p1, material } => {
+                let material = self.material(material)?;
+                let cube = Cube::new_from_mat(*p0, *p1, material.clone());
+                let d = *p1 - *p0;
+                let (dx, dy, dz) = (
+                    Vec3::new(d.x(), 0.0, 0.0),
+                    Vec3::new(0.0, d.y(), 0.0),
+                    Vec3::new(0.0, 0.0, d.z()),
+                );
+                let surface = Surface::Parallelograms(vec![
+                    (dx, dy),
+                    (dx, dy),
+                    (dx, dz),
+                    (dx, dz),
+                    (dy, dz),
+                    (dy, dz),
+                ]);
+                self.emissive(Box::new(cube), &material, surface, emitters)
+            }
+            ObjectDescription::Triangle {
+                v0,
+                v1,
+                v2,
+                material,
+            } => {
+                let material = self.material(material)?;
+                let triangle = Triangle::from_vertices(*v0, *v1, *v2, material.clone());
+                let surface = Surface::Triangle(*v1 - *v0, *v2 - *v0);
+                self.emissive(Box::new(triangle), &material, surface, emitters)
+            }
+            ObjectDescription::Mesh {
+                positions,
+                normals,
+                uvs,
+                indices,
+                material,
+            } => {
+                let mesh = Mesh {
+                    positions: positions.clone(),
+                    normals: normals.clone(),
+                    uvs: uvs.clone(),
+                    indices: indices.clone(),
+                };
+                mesh.check()?;
+
+                let triangles = Mesh::triangles(&Arc::new(mesh), self.material(material)?);
+                self.build_triangles(triangles, emitters)
+            }
+            ObjectDescription::Obj { filename, material } => {
+                let material = match material {
+                    Some(name) => Some(self.material(name)?),
+                    None => None,
+                };
+                let triangles = load_obj(filename, material)?;
+                if triangles.is_empty() {
+                    return Err(format!("OBJ file {} has no faces", filename));
+                }
+
+                self.build_triangles(triangles, emitters)
+            }
+            ObjectDescription::Bvh { objects } => {
+                let objects = objects
+                    .iter()
+                    .map(|desc| self.build_object(desc, emitters))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                self.build_bvh(objects)
+            }
+            ObjectDescription::Translate { offset, object } => {
+                let object = self.build_object(object, emitters)?;
+                move_emitters(&mut emitters.list[first..], Matrix4::translation(*offset));
+
+                Box::new(Translate::new(object, *offset))
+            }
+            ObjectDescription::RotateY { angle, object } => {
+                let object = self.build_object(object, emitters)?;
+                move_emitters(&mut emitters.list[first..], Matrix4::rotation_y(*angle));
+
+                Box::new(RotateY::new(object, *angle))
+            }
+            ObjectDescription::FlipNormals { object } => {
+                let object = self.build_object(object, emitters)?;
+                for emitter in emitters.list[first..].iter_mut() {
+                    emitter.shape = Arc::new(FlipNormals::new(emitter.shape.clone()));
+                }
+
+                Box::new(FlipNormals::new(object))
+            }
+            ObjectDescription::Transform { transforms, object } => {
+                let object = self.build_object(object, emitters)?;
+                let matrix = compose(transforms);
+                move_emitters(&mut emitters.list[first..], matrix);
+
+                Box::new(Transform::new(object, matrix)?)
+            }
+            ObjectDescription::Instance {
+                prototype: name,
+                transforms,
+                material,
+            } => {
+                let prototype = self
+                    .prototypes
+                    .get(name)
+                    .ok_or_else(|| format!("Unknown prototype: {}", name))?;
+                let matrix = compose(transforms);
+                let material = match material {
+                    Some(name) => Some(self.material(name)?),
+                    None => None,
+                };
+                let instance = Instance::new(prototype.object.clone(), matrix, material.clone())?;
+
+                // the emitters of the prototype which emit in this copy, sharing
+                // its primitives and placed like it
+                let object: Box<dyn Hittable> = match &material {
+                    // all of them, numbered like the primitives
+                    Some(material)
+                        if emitters.every_primitive
+                            || material.emission().max_component() > 0.0 =>
+                    {
+                        for emitter in prototype.emitters.iter() {
+                            emitters.list.push(emitter.with_material(material)?);
+                        }
+                        Box::new(instance.with_lights(first, None))
+                    }
+                    None if emitters.every_primitive || !prototype.every_primitive => {
+                        emitters.list.extend(prototype.emitters.iter().cloned());
+                        Box::new(instance.with_lights(first, None))
+                    }
+                    // only the emissive primitives, the hits on the others
+                    // carry no light
+                    None => {
+                        let mut table = Vec::new();
+                        for emitter in prototype.emitters.iter() {
+                            if emitter.emission.max_component() > 0.0 {
+                                table.push(Some(emitters.list.len() - first));
+                                emitters.list.push(emitter.clone());
+                            } else {
+                                table.push(None);
+                            }
+                        }
+                        Box::new(instance.with_lights(first, Some(Arc::from(table))))
+                    }
+                    // none of them emits anymore
+                    Some(_) => Box::new(instance),
+                };
+                move_emitters(&mut emitters.list[first..], matrix);
+
+                object
+            }
+            ObjectDescription::ConstantMedium { .. } | ObjectDescription::Medium { .. } => {
+                return Err(
+                    "A medium is an object of the world, not part of another object".to_string(),
+                )
+            }
+        };
+
+        Ok(object)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::ray::Ray;
+    use crate::scene::SceneKind;
+    use crate::tools::{hash_seed, reseed};
+
+    #[test]
+    fn test_ron_round_trip() {
+        for kind in [
+            SceneKind::RandomChecker,
+            SceneKind::CornellBoxSmoke,
+            SceneKind::CornellBoxGlassSphere,
+        ] {
+            let desc = SceneDescription::from_kind(kind, false, "data/1k/earth.jpg");
+            let ron = desc.to_ron().unwrap();
+
+            assert_eq!(ron::from_str::<SceneDescription>(&ron).unwrap(), desc);
+        }
+    }
+
+    #[test]
+    fn test_unknown_material() {
+        let mut desc = SceneDescription::from_kind(SceneKind::CornellBox, false, "");
+        desc.materials.remove("white");
+
+        assert!(desc.build().is_err());
+    }
+
+    #[test]
+    fn test_emitters_are_lights() {
+        let mut desc = SceneDescription::from_kind(SceneKind::CornellBox, false, "");
+        desc.materials.insert(
+            "dark".to_string(),
+            MaterialDescription::DiffuseLight {
+                emit: TextureDescription::Value(0.0),
+            },
+        );
+        let sphere = |material: &str| ObjectDescription::Sphere {
+            center: Point3::new(100.0, 100.0, 100.0),
+            radius: 20.0,
+            material: material.to_string(),
+        };
+        // the ceiling light, the emissive sphere and the point light
+        desc.world.push(ObjectDescription::Bvh {
+            objects: vec![sphere("light"), sphere("white"), sphere("dark")],
+        });
+        desc.world.push(ObjectDescription::ConstantMedium {
+            boundary: Box::new(sphere("light")),
+            density: 0.1,
+            albedo: TextureDescription::Value(0.5),
+        });
+        desc.lights.push(LightDescription::Point {
+            position: Point3::new(278.0, 500.0, 278.0),
+            intensity: Color::new(1.0, 1.0, 1.0),
+        });
+
+        assert_eq!(desc.build().unwrap().lights.len(), 3);
+    }
+
+    #[test]
+    fn test_emitters_inside_objects() {
+        let mut desc = SceneDescription::from_kind(SceneKind::CornellBox, false, "");
+        let sphere = |center: Point3, material: &str| ObjectDescription::Sphere {
+            center,
+            radius: 1.0,
+            material: material.to_string(),
+        };
+        let cube = |material: &str| ObjectDescription::Cube {
+            p0: Point3::new(2.0, 2.0, 2.0),
+            p1: Point3::new(3.0, 3.0, 3.0),
+            material: material.to_string(),
+        };
+        let instance = |transforms: Vec<TransformDescription>, material: Option<&str>| {
+            ObjectDescription::Instance {
+                prototype: "lamp".to_string(),
+                transforms,
+                material: material.map(str::to_string),
+            }
+        };
+        desc.prototypes.insert(
+            "lamp".to_string(),
+            ObjectDescription::Bvh {
+                objects: vec![sphere(Point3::zero(), "light"), cube("white")],
+            },
+        );
+
+        // one sphere out of the Cornell box, turned a quarter around y and moved up
+        desc.world = vec![ObjectDescription::Translate {
+            offset: Vec3::new(0.0, 100.0, 0.0),
+            object: Box::new(ObjectDescription::RotateY {
+                angle: 90.0,
+                object: Box::new(sphere(Point3::new(10.0, 0.0, 0.0), "light")),
+            }),
+        }];
+        let scene = desc.build().unwrap();
+        assert_eq!(scene.lights.len(), 1);
+        let expected = Vec3::new(0.0, 100.0, -10.0).to_unit_vector();
+        for _ in 0..100 {
+            let sample = scene.lights.sample(&Point3::zero(), 0.0).unwrap();
+            assert!(sample.direction.dot(expected) > 0.9999);
+            assert!((sample.distance - 100.5).abs() < 1.01);
+        }
+
+        // the sphere of an instance, the cube of the prototype does not emit
+        desc.world = vec![instance(
+            vec![TransformDescription::Translate(Vec3::new(0.0, 50.0, 0.0))],
+            None,
+        )];
+        let scene = desc.build().unwrap();
+        assert_eq!(scene.lights.len(), 1);
+        let sample = scene.lights.sample(&Point3::zero(), 0.0).unwrap();
+        assert!(sample.direction.y() > 0.999);
+
+        // the hits of a second one carry the index of its own light
+        desc.world.push(instance(
+            vec![TransformDescription::Translate(Vec3::new(0.0, -50.0, 0.0))],
+            None,
+        ));
+        let scene = desc.build().unwrap();
+        let down = Ray::new(Point3::zero(), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let hr = scene.world.hit(&down, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(hr.get_light(), Some(1));
+        let pdf = |light: usize| {
+            scene
+                .lights
+                .light_pdf(light, &Point3::zero(), &down.direction())
+        };
+        assert!(pdf(1) > 0.0);
+        assert_eq!(pdf(0), 0.0);
+
+        desc.world = vec![
+            // the sphere, the cube and the moving sphere
+            ObjectDescription::FlipNormals {
+                object: Box::new(ObjectDescription::Bvh {
+                    objects: vec![
+                        sphere(Point3::zero(), "light"),
+                        cube("light"),
+                        ObjectDescription::MovingSphere {
+                            center0: Point3::zero(),
+                            center1: Point3::new(0.0, 1.0, 0.0),
+                            time0: 0.0,
+                            time1: 1.0,
+                            radius: 1.0,
+                            material: "light".to_string(),
+                        },
+                    ],
+                }),
+            },
+            // the two faces of the mesh
+            ObjectDescription::Mesh {
+                positions: vec![
+                    Point3::zero(),
+                    Point3::new(1.0, 0.0, 0.0),
+                    Point3::new(1.0, 1.0, 0.0),
+                    Point3::new(0.0, 1.0, 0.0),
+                ],
+                normals: Vec::new(),
+                uvs: Vec::new(),
+                indices: vec![[0, 1, 2], [0, 2, 3]],
+                material: "light".to_string(),
+            },
+            // the face of the lamp material
+            ObjectDescription::Obj {
+                filename: "data/obj/materials.obj".to_string(),
+                material: None,
+            },
+            // one of the prototype, none when it is white, both when it is a light
+            instance(Vec::new(), None),
+            instance(Vec::new(), Some("white")),
+            instance(Vec::new(), Some("light")),
+        ];
+        assert_eq!(desc.build().unwrap().lights.len(), 3 + 2 + 1 + 1 + 2);
+    }
+
+    #[test]
+    fn test_emissive_instances_share_the_prototype() {
+        let mut desc = SceneDescription::from_kind(SceneKind::CornellBox, false, "");
+        desc.prototypes.insert(
+            "lamp".to_string(),
+            ObjectDescription::Bvh {
+                objects: vec![
+                    ObjectDescription::Sphere {
+                        center: Point3::zero(),
+                        radius: 1.0,
+                        material: "light".to_string(),
+                    },
+                    ObjectDescription::Cube {
+                        p0: Point3::new(2.0, 2.0, 2.0),
+                        p1: Point3::new(3.0, 3.0, 3.0),
+                        material: "white".to_string(),
+                    },
+                ],
+            },
+        );
+        let instance = |y: f64, material: Option<&str>| ObjectDescription::Instance {
+            prototype: "lamp".to_string(),
+            transforms: vec![TransformDescription::Translate(Vec3::new(0.0, y, 0.0))],
+            material: material.map(str::to_string),
+        };
+
+        // the sphere of the one above, the sphere and the cube of the one below
+        desc.world = vec![instance(50.0, None), instance(-50.0, Some("light"))];
+        let scene = desc.build().unwrap();
+        assert_eq!(scene.lights.len(), 3);
+
+        let hit = |origin: Point3, y: f64| {
+            let r = Ray::new(origin, Vec3::new(0.0, y, 0.0), 0.0);
+            let hr = scene.world.hit(&r, 0.001, f64::INFINITY).unwrap();
+            let light = hr.get_light();
+            if let Some(light) = light {
+                assert!(scene.lights.light_pdf(light, &origin, &r.direction()) > 0.0);
+            }
+
+            (hr.get_p().y(), light)
+        };
+        let below_cube = Point3::new(2.5, 0.0, 2.5);
+        assert_eq!(hit(Point3::zero(), 1.0), (49.0, Some(0)));
+        assert_eq!(hit(below_cube, 1.0), (52.0, None));
+        assert_eq!(hit(Point3::zero(), -1.0), (-49.0, Some(1)));
+        assert_eq!(hit(below_cube, -1.0), (-47.0, Some(2)));
+
+        // the cube made of light is sampled with the emission of its material,
+        // the light tree seldom picks it next to the sphere below
+        reseed(hash_seed(&[3]));
+        let p = Point3::new(2.5, -45.0, 2.5);
+        let lit = (0..1000).any(|_| {
+            let sample = scene.lights.sample(&p, 0.0).unwrap();
+            sample.direction.y() < -0.9 && sample.radiance.max_component() > 0.0
+        });
+        assert!(lit);
+    }
+
+    #[test]
+    fn test_transformed_areas() {
+        let close = |a: f64, b: f64, tolerance: f64| (a - b).abs() < tolerance * b;
+        let rotation = Matrix4::rotation(Vec3::new(1.0, 2.0, 3.0), 40.0);
+        let stretch = Matrix4::scaling(Vec3::new(2.0, 5.0, 7.0));
+
+        let rect =
+            Surface::Parallelograms(vec![(Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 3.0, 0.0))]);
+        assert!(close(rect.area(&rotation), 6.0, 1E-12));
+        assert!(close(rect.area(&stretch), 60.0, 1E-12));
+        assert!(close(rect.area(&(rotation * stretch)), 60.0, 1E-12));
+
+        // in the xz plane
+        let triangle = Surface::Triangle(Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(close(triangle.area(&stretch), 7.0, 1E-12));
+
+        let sphere = Surface::Sphere(2.0);
+        let similarity = Matrix4::translation(Vec3::new(4.0, 5.0, 6.0))
+            * rotation
+            * Matrix4::scaling(Vec3::new(3.0, 3.0, 3.0));
+        assert!(close(sphere.area(&similarity), 4.0 * PI * 36.0, 1E-9));
+
+        // a prolate spheroid of semi-axes 1, 2 and 1
+        let spheroid = Surface::Sphere(1.0).area(&Matrix4::scaling(Vec3::new(1.0, 2.0, 1.0)));
+        let e = 0.75f64.sqrt();
+        let expected = 2.0 * PI * (1.0 + 2.0 / e * e.asin());
+        assert!(close(spheroid, expected, 1E-4), "{} {}", spheroid, expected);
+    }
+
+    #[test]
+    fn test_media_are_world_objects() {
+        let mut desc = SceneDescription::from_kind(SceneKind::CornellBoxSmoke, false, "");
+        let objects = desc.world.len();
+        let scene = desc.build().unwrap();
+        // the two blocks of smoke
+        assert!(!scene.media.is_empty());
+        assert_eq!(scene.world.len(), objects - 2);
+
+        let medium = ObjectDescription::Medium {
+            boundary: Box::new(ObjectDescription::Sphere {
+                center: Point3::new(278.0, 278.0, 278.0),
+                radius: 100.0,
+                material: "white".to_string(),
+            }),
+            density: DensityDescription::Grid {
+                min: Point3::zero(),
+                max: Point3::new(555.0, 555.0, 555.0),
+                resolution: (2, 2, 2),
+                values: vec![1.0; 8],
+            },
+            sigma_a: Color::new(0.01, 0.02, 0.03),
+            sigma_s: Color::new(0.01, 0.01, 0.01),
+        };
+        desc.world.push(medium.clone());
+        assert!(desc.build().is_ok());
+
+        // the same medium, inside of another object
+        desc.world.pop();
+        desc.world.push(ObjectDescription::Bvh {
+            objects: vec![medium],
+        });
+        assert!(desc.build().is_err());
+    }
+}
@@ -0,0 +1,311 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use clap::{ArgEnum, PossibleValue};
+use serde::{Deserialize, Serialize};
+
+use crate::background::Background;
+use crate::camera::camera::Camera;
+use crate::geometry::bvh::{BvhKind, BvhStats};
+use crate::hittable::Hittable;
+use crate::integrator::{Integrator, IntegratorKind};
+use crate::lights::{LightSamplerKind, Lights};
+use crate::materials::Material;
+use crate::media::Media;
+
+pub mod builtin;
+pub mod description;
+
+use description::{EnvironmentDescription, SceneDescription, SkyDescription};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Config {
+    pub ratio: f64,
+    pub width: usize,
+    pub height: usize,
+    pub samples_per_pixel: u32,
+    pub max_depth: u32,
+    pub time0: f64,
+    pub time1: f64,
+    #[serde(default)]
+    pub bvh: BvhKind,
+    // paths may be stopped by Russian roulette from this bounce on
+    #[serde(default = "default_rr_min_depth")]
+    pub rr_min_depth: u32,
+    // samples brighter than this are scaled down, trading a bias for fewer
+    // fireflies
+    #[serde(default)]
+    pub max_luminance: Option<f64>,
+    #[serde(default)]
+    pub integrator: IntegratorKind,
+    // occlusion radius of the ambient occlusion integrator, a tenth of the
+    // scene size when not given
+    #[serde(default)]
+    pub ao_distance: Option<f64>,
+    #[serde(default)]
+    pub light_sampler: LightSamplerKind,
+}
+
+fn default_rr_min_depth() -> u32 {
+    3
+}
+
+// Configuration values given on the command line
+#[derive(Debug, Default, Clone)]
+pub struct Overrides {
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    pub samples_per_pixel: Option<u32>,
+    pub bvh: Option<BvhKind>,
+    pub rr_min_depth: Option<u32>,
+    pub max_luminance: Option<f64>,
+    pub integrator: Option<IntegratorKind>,
+    pub ao_distance: Option<f64>,
+    pub light_sampler: Option<LightSamplerKind>,
+    // replace the background of the scene
+    pub environment: Option<EnvironmentDescription>,
+    pub sky: Option<SkyDescription>,
+}
+
+#[derive(ArgEnum, Debug, PartialEq, Clone, Copy)]
+pub enum SceneKind {
+    RandomUniform,
+    RandomChecker,
+    TwoCheckerSpheres,
+    TwoPerlinSpheres,
+    ImageSphere,
+    SimpleLight,
+    CornellBox,
+    CornellBoxSmoke,
+    FinalScene,
+    CornellBoxMetal,
+    CornellBoxGlassSphere,
+}
+
+impl FromStr for SceneKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        for variant in Self::value_variants() {
+            if variant.to_possible_value().unwrap().matches(s, false) {
+                return Ok(*variant);
+            }
+        }
+        Err(format!("Invalid variant: {}", s))
+    }
+}
+
+impl Display for SceneKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .fmt(f)
+    }
+}
+
+impl SceneKind {
+    pub fn possible_values() -> impl Iterator<Item = PossibleValue<'static>> {
+        Self::value_variants()
+            .iter()
+            .filter_map(ArgEnum::to_possible_value)
+    }
+}
+
+pub struct Scene {
+    pub cfg: Config,
+    pub world: Vec<Box<dyn Hittable>>,
+    // the emissive objects of the world and the other lights
+    pub lights: Lights,
+    // the media of the world, apart from its objects
+    pub media: Media,
+    pub integrator: Box<dyn Integrator>,
+    // the named materials, in name order
+    pub materials: Vec<Arc<dyn Material>>,
+    pub camera: Camera,
+    pub background: Arc<dyn Background>,
+    // construction statistics of every BVH in the scene
+    pub bvh_stats: Vec<BvhStats>,
+    // see SceneDescription::hash
+    pub hash: u64,
+}
+
+impl Config {
+    fn new(kind: &SceneKind, moving: bool) -> Config {
+        match kind {
+            SceneKind::CornellBox
+            | SceneKind::CornellBoxSmoke
+            | SceneKind::CornellBoxMetal
+            | SceneKind::CornellBoxGlassSphere => {
+                let ratio: f64 = 1.0;
+                let width: usize = 600; // def: 600
+                let height: usize = (width as f64 / ratio) as usize;
+                let samples_per_pixel: u32 = 100; // def: 200
+                let max_depth: u32 = 50;
+                let time0 = 0.0;
+                let time1 = if moving { 1.0 } else { 0.0 };
+
+                Config {
+                    ratio,
+                    width,
+                    height,
+                    samples_per_pixel,
+                    max_depth,
+                    time0,
+                    time1,
+                    bvh: BvhKind::default(),
+                    rr_min_depth: default_rr_min_depth(),
+                    max_luminance: None,
+                    integrator: IntegratorKind::default(),
+                    ao_distance: None,
+                    light_sampler: LightSamplerKind::default(),
+                }
+            }
+            SceneKind::FinalScene => {
+                let ratio: f64 = 1.0;
+                let width: usize = 1000;
+                let height: usize = (width as f64 / ratio) as usize;
+                let samples_per_pixel: u32 = 1000; /* <100: 1min, <500: 8mins, <1000: 15mins, 10K: 4hours */
+                let max_depth: u32 = 50;
+                let time0 = 0.0;
+                let time1 = 1.0;
+
+                Config {
+                    ratio,
+                    width,
+                    height,
+                    samples_per_pixel,
+                    max_depth,
+                    time0,
+                    time1,
+                    bvh: BvhKind::default(),
+                    rr_min_depth: default_rr_min_depth(),
+                    max_luminance: None,
+                    integrator: IntegratorKind::default(),
+                    ao_distance: None,
+                    light_sampler: LightSamplerKind::default(),
+                }
+            }
+            _ => {
+                let ratio: f64 = 3.0 / 2.0;
+                let width: usize = 1200;
+                let height: usize = (width as f64 / ratio) as usize;
+                let samples_per_pixel: u32 = 500; // def: 500
+                let max_depth: u32 = 50;
+                let time0 = 0.0;
+                let time1 = if moving { 1.0 } else { 0.0 };
+
+                Config {
+                    ratio,
+                    width,
+                    height,
+                    samples_per_pixel,
+                    max_depth,
+                    time0,
+                    time1,
+                    bvh: BvhKind::default(),
+                    rr_min_depth: default_rr_min_depth(),
+                    max_luminance: None,
+                    integrator: IntegratorKind::default(),
+                    ao_distance: None,
+                    light_sampler: LightSamplerKind::default(),
+                }
+            }
+        }
+    }
+
+    pub fn apply_overrides(&mut self, overrides: &Overrides) {
+        if let Some(width) = overrides.width {
+            self.width = width;
+            self.height = (width as f64 / self.ratio) as usize;
+        }
+        if let Some(height) = overrides.height {
+            self.height = height;
+        }
+        if let Some(samples_per_pixel) = overrides.samples_per_pixel {
+            self.samples_per_pixel = samples_per_pixel;
+        }
+        if let Some(bvh) = overrides.bvh {
+            self.bvh = bvh;
+        }
+        if let Some(rr_min_depth) = overrides.rr_min_depth {
+            self.rr_min_depth = rr_min_depth;
+        }
+        if let Some(max_luminance) = overrides.max_luminance {
+            self.max_luminance = Some(max_luminance);
+        }
+        if let Some(integrator) = overrides.integrator {
+            self.integrator = integrator;
+        }
+        if let Some(ao_distance) = overrides.ao_distance {
+            self.ao_distance = Some(ao_distance);
+        }
+        if let Some(light_sampler) = overrides.light_sampler {
+            self.light_sampler = light_sampler;
+        }
+    }
+}
+
+impl Scene {
+    // Position of a named material in `materials` plus one, 0 for the others
+    pub fn material_id(&self, material: &Arc<dyn Material>) -> u32 {
+        self.materials
+            .iter()
+            .position(|m| Arc::ptr_eq(m, material))
+            .map_or(0, |i| i as u32 + 1)
+    }
+
+    // The overrides may still make a built-in scene invalid (an environment map
+    // which can't be read...)
+    pub fn new(
+        moving: bool,
+        kind: SceneKind,
+        filename: &str,
+        overrides: &Overrides,
+    ) -> Result<Scene, String> {
+        let mut desc = SceneDescription::from_kind(kind, moving, filename);
+        desc.apply_overrides(overrides);
+
+        desc.build()
+    }
+
+    pub fn from_file(filename: &str, overrides: &Overrides) -> Result<Scene, String> {
+        let mut desc = SceneDescription::load(filename)?;
+        desc.apply_overrides(overrides);
+
+        desc.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_overrides() {
+        let overrides = Overrides {
+            environment: Some(EnvironmentDescription {
+                filename: "/nonexistent.hdr".to_string(),
+                rotation: 0.0,
+                intensity: 1.0,
+            }),
+            ..Default::default()
+        };
+        assert!(Scene::new(false, SceneKind::CornellBox, "", &overrides).is_err());
+
+        // a sun below the horizon, too hazy a sky
+        for (elevation, turbidity) in [(100.0, 3.0), (-10.0, 3.0), (45.0, 12.0)] {
+            let overrides = Overrides {
+                sky: Some(SkyDescription {
+                    elevation,
+                    azimuth: 0.0,
+                    turbidity,
+                    intensity: 1.0,
+                }),
+                ..Default::default()
+            };
+            assert!(Scene::new(false, SceneKind::CornellBox, "", &overrides).is_err());
+        }
+    }
+}
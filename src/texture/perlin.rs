@@ -68,14 +68,14 @@ impl Perlin {
         let ww = w * w * (3.0 - 2.0 * w);
 
         let mut accum = 0.0;
-        for i in 0..2 {
-            for j in 0..2 {
-                for k in 0..2 {
+        for (i, ci) in c.iter().enumerate() {
+            for (j, cij) in ci.iter().enumerate() {
+                for (k, cijk) in cij.iter().enumerate() {
                     let weight_v = Vec3::new(u - i as f64, v - j as f64, w - k as f64);
                     accum += (i as f64 * uu + (1 - i) as f64 * (1.0 - uu))
                         * (j as f64 * vv + (1 - j) as f64 * (1.0 - vv))
                         * (k as f64 * ww + (1 - k) as f64 * (1.0 - ww))
-                        * c[i][j][k].dot(weight_v);
+                        * cijk.dot(weight_v);
                 }
             }
         }
@@ -105,7 +105,7 @@ impl Perlin {
         v
     }
 
-    fn permute(p: &mut Vec<usize>, n: usize) {
+    fn permute(p: &mut [usize], n: usize) {
         for i in (0..n).rev() {
             let target = random_usize_range(0, i);
             p.swap(i, target);
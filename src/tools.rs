@@ -1,17 +1,40 @@
-use rand::Rng;
-
-pub fn random_double_range(min: f64, max: f64) -> f64 {
-    let mut rng = rand::thread_rng();
-
-    rng.gen_range(min..max)
-}
-
-pub fn random_double() -> f64 {
-    random_double_range(0.0, 1.0)
-}
-
-pub fn random_usize_range(min: usize, max: usize) -> usize {
-    let mut rng = rand::thread_rng();
-
-    rng.gen_range(min..max + 1)
-}
+use std::cell::RefCell;
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+thread_local! {
+    // Renderers reseed it for every sample, from the seed, the pixel and the index of
+    // the sample, so a render can be reproduced and resumed
+    static RNG: RefCell<SmallRng> = RefCell::new(SmallRng::from_entropy());
+}
+
+pub fn reseed(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = SmallRng::seed_from_u64(seed));
+}
+
+// Combine several values into one seed (splitmix64 finalizer on each step)
+pub fn hash_seed(values: &[u64]) -> u64 {
+    values.iter().fold(0x9E37_79B9_7F4A_7C15, |acc, &v| {
+        let mut z = acc ^ v.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    })
+}
+
+pub fn random_u64() -> u64 {
+    RNG.with(|rng| rng.borrow_mut().gen())
+}
+
+pub fn random_double_range(min: f64, max: f64) -> f64 {
+    RNG.with(|rng| rng.borrow_mut().gen_range(min..max))
+}
+
+pub fn random_double() -> f64 {
+    random_double_range(0.0, 1.0)
+}
+
+pub fn random_usize_range(min: usize, max: usize) -> usize {
+    RNG.with(|rng| rng.borrow_mut().gen_range(min..max + 1))
+}